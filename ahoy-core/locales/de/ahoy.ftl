@@ -0,0 +1,179 @@
+# Ahoy - Deutsch
+
+## shared
+app-title = AHOY! - Pirate MIDI Firmware-Updater
+settings = Einstellungen
+close = Schließen
+cancel = Abbrechen
+install = Installieren
+loading = Wird geladen...
+or = oder
+local-file = Lokale Datei
+
+## errors
+error-remote-api =
+    Github ist nicht erreichbar!
+    Mehr Details stehen im Protokoll unten. Grund: { $reason }
+error-install = Update konnte nicht installiert werden! Grund: { $reason }
+error-no-device = kein Gerät gefunden
+error-permission = Zugriff verweigert
+error-device = Befehl konnte nicht an das Gerät gesendet werden
+error-retrieval = Versionen konnten nicht abgerufen werden
+error-download = Firmware konnte nicht heruntergeladen werden
+error-checksum = Firmware hat die Integritätsprüfung nicht bestanden
+error-signature = Firmware-Signatur konnte nicht überprüft werden
+error-dfu = Installation konnte nicht durchgeführt werden
+error-verification = Installation konnte nicht überprüft werden
+error-cancelled = Installation abgebrochen - das Gerät ist noch im Bootloader-Modus
+error-timeout = Zeitüberschreitung bei der Installation - das Gerät ist noch im Bootloader-Modus
+error-io = Datei konnte nicht gelesen oder geschrieben werden
+error-update = Aktualisierung fehlgeschlagen
+error-setup = Einrichtung konnte nicht abgeschlossen werden
+error-policy = von der Firmware-Richtlinie abgelehnt
+
+## error hints
+hint-no-device = Prüfe das USB-Kabel und ob das Gerät eingeschaltet ist, und versuche es erneut.
+hint-permission = Dein Benutzer hat keinen Zugriff auf das Gerät. Führe `ahoy doctor` aus, um die USB-Berechtigungen zu beheben.
+hint-device = Das Gerät hat nicht wie erwartet geantwortet. Verbinde es neu und versuche es erneut.
+hint-retrieval = Prüfe deine Internetverbindung oder warte eine Weile, falls Github dich drosselt.
+hint-download = Der Download wurde nicht abgeschlossen. Prüfe deine Internetverbindung und versuche es erneut.
+hint-checksum = Die heruntergeladene Firmware ist beschädigt. Lade sie erneut herunter.
+hint-signature = Die Firmware ist nicht mit einem vertrauenswürdigen Schlüssel signiert oder passt nicht zu ihrer Signatur. Lade sie erneut herunter oder installiere nur Firmware aus einer vertrauenswürdigen Quelle.
+hint-dfu = Verbinde das Gerät neu und schließe die Installation mit Wiederherstellen ab.
+hint-verification = Die Firmware wurde nicht vollständig geschrieben, oder das Gerät führt sie nicht aus. Installiere sie mit Wiederherstellen erneut.
+hint-bootloader = Das Gerät ist möglicherweise noch im Bootloader-Modus. Schließe die Installation mit Wiederherstellen ab.
+hint-io = Prüfe, ob genug Speicherplatz frei ist und die Datei gelesen werden kann.
+hint-update = Lade stattdessen die neueste Ahoy-Version von Github herunter.
+hint-setup = Führe den Befehl mit sudo erneut aus oder folge den Einrichtungsschritten in der README.
+hint-policy = Deine Firmware-Richtlinie erlaubt diese Version nicht. Wähle eine erlaubte Version oder wende dich an die Person, die die Richtliniendatei verwaltet.
+
+## settings
+settings-theme = Design
+settings-language = Sprache
+settings-system-language = Systemsprache
+theme-system = System
+theme-light = Hell
+theme-dark = Dunkel
+
+## waiting for a device
+connect-prompt = Bitte schließe dein Gerät an:
+
+## connected device
+device-connected = VERBUNDEN
+device-uid = UID: { $uid }
+device-up-to-date = Aktuell - { $version }
+device-update-available = Update verfügbar: { $version } (installiert { $installed })
+device-update = Aktualisieren
+
+## release controls
+controls-refresh = Neu laden
+controls-history = Verlauf
+channel-stable = Stabil
+channel-beta = Beta
+channel-draft = Entwurf
+channel-local = Lokal
+
+## release list
+versions-installed = installiert
+versions-draft = Entwurf
+versions-not-allowed = nicht erlaubt
+versions-local-title = Lokale Firmware-Datei installieren
+versions-local-placeholder = Pfad zu einer Firmware-.bin-Datei
+versions-download-install = Herunterladen und installieren
+versions-no-assets = Für dieses Gerät sind keine Downloads verfügbar
+versions-select = Bitte wähle eine Version aus
+
+## install history
+history-empty = Für dieses Gerät wurden noch keine Installationen aufgezeichnet
+history-success = ERFOLGREICH
+history-failed = FEHLGESCHLAGEN
+history-cancelled = ABGEBROCHEN
+history-unknown = unbekannt
+history-via = { $time } über { $client }
+
+## confirming an install
+confirm-ready = Die Firmware wurde heruntergeladen und kann installiert werden!
+confirm-bridge = Verbinde als Nächstes die Flexiports 1 und 2 mit einem TS- oder TRS-Kabel
+confirm-do-not-unplug = BITTE TRENNE DEIN GERÄT NICHT, BEVOR DIE INSTALLATION ABGESCHLOSSEN IST.
+downgrade-warning = Dies ist ein Downgrade von { $installed } auf { $version }. Ältere Firmware unterstützt möglicherweise nicht alles, was du eingerichtet hast.
+signed-by = Signiert von { $name }
+unsigned = Nicht mit einem vertrauenswürdigen Schlüssel signiert
+
+## installing
+download-progress = Firmware wird heruntergeladen - { $downloaded } von { $total } KB
+install-waiting-device = WARTE AUF GERÄT
+install-waiting-bootloader = Warte, bis das Gerät in den Bootloader-Modus wechselt...
+install-cancelling = Wird abgebrochen - der aktuelle Block wird noch geschrieben...
+install-preparing = Gerät wird für die neue Firmware vorbereitet...
+install-writing = Firmware wird installiert...
+install-finalizing = Installation wird abgeschlossen...
+install-done = Fertig!
+install-verifying = Prüfe, ob das Gerät mit der neuen Firmware neu gestartet ist...
+install-stats = { $rate } KB/s - noch { $seconds } s
+install-complete = Installation abgeschlossen!
+install-complete-detail = Trenne dein Gerät und stich in See, tapferer Entdecker!
+
+## recovering a device stuck in bootloader mode
+recover-title = GERÄT WIEDERHERSTELLEN
+recover-detected = Ein Gerät im Bootloader-Modus wurde erkannt
+recover-explanation = Meist wurde dann eine Installation unterbrochen. Wähle dein Gerät und eine Version (oder eine lokale Firmware-Datei), um sie erneut zu installieren.
+recover-model = Modell
+recover-revision = Revision
+recover-release = Version
+recover-select-revision = Revision auswählen
+recover-select-release = Version auswählen
+recover-button = Gerät wiederherstellen
+recover-no-firmware = keine Firmware für das ausgewählte Gerät und die Version gefunden
+
+## log panel
+log-show = Protokoll anzeigen
+log-hide = Protokoll ausblenden
+log-empty = Bisher wurde nichts protokolliert.
+log-export = Diagnose exportieren
+log-exporting = Diagnosedaten werden gesammelt...
+log-exported = Diagnose gespeichert unter { $path }
+log-export-failed = Diagnose konnte nicht exportiert werden: { $reason }
+
+## updating ahoy itself
+update-available-title = Für diese Anwendung ist ein Update verfügbar!
+update-available-version = Version { $version } ist jetzt verfügbar!
+update-dismiss = Später
+update-and-quit = Aktualisieren und beenden
+
+## dry runs
+simulation-banner = Simulation - es werden weder Gerät noch Netzwerk verwendet. Fehler: { $fault }
+simulation-no-fault = keiner
+
+## command line
+cli-entering-bootloader = Bootloader-Modus wird gestartet...
+cli-waiting-for-bootloader = warte 3 Sekunden auf den Bootloader-Modus...
+cli-fetching-release = Version { $tag } wird abgerufen...
+cli-fetching-latest = neueste Version aus { $channels } wird abgerufen...
+cli-downloading = { $name } wird heruntergeladen...
+cli-installing = wird installiert...
+cli-verifying = warte auf den Neustart des Geräts mit der neuen Firmware...
+cli-dry-run = Probelauf - Gerät und Downloads werden simuliert, es wird nichts installiert
+cli-dry-run-fault = simulierter Fehler: { $fault }
+cli-progress-writing = schreibe
+cli-progress-manifesting = schließe ab
+cli-progress-done = fertig
+cli-left-in-bootloader = das Gerät ist noch im Bootloader-Modus - mit `ahoy recover` erneut versuchen
+cli-no-releases = keine Versionen gefunden in: { $channels }
+cli-update-available = Update verfügbar für { $device }: { $installed } -> { $version }
+cli-up-to-date = { $device } ist aktuell ({ $installed })
+cli-no-history = keine Installationen aufgezeichnet
+cli-update-complete = Update abgeschlossen
+cli-udev-installed = udev-Regeln wurden nach { $path } installiert
+cli-udev-reconnect = trenne dein Gerät und schließe es erneut an, damit sie wirksam werden
+cli-udev-save = nicht als root ausgeführt - speichere Folgendes unter { $path }
+cli-udev-reload = danach ausführen
+cli-udev-sudo = (oder diesen Befehl erneut mit sudo ausführen)
+cli-report-written = Bericht gespeichert unter: { $path }
+cli-diagnostics-written = Diagnose gespeichert unter: { $path }
+cli-station-waiting = warte auf das nächste Gerät (Protokoll: { $log }) - ctrl-c zum Beenden
+cli-station-flashing = { $device } { $uid } wird geflasht...
+cli-station-skipped = { $uid } wurde bereits geflasht - trenne es und schließe das nächste Gerät an
+cli-station-pass = PASS { $uid } ({ $seconds }s)
+cli-station-fail = FAIL { $uid }: { $reason }
+cli-serving = API läuft auf http://{ $address } - ctrl-c zum Beenden
+cli-serving-token = jede Anfrage braucht den Header: Authorization: Bearer { $token }
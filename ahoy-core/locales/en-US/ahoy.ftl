@@ -0,0 +1,179 @@
+# Ahoy - English (the fallback for every other catalog)
+
+## shared
+app-title = AHOY! - Pirate MIDI Firmware Updater
+settings = Settings
+close = Close
+cancel = Cancel
+install = Install
+loading = Loading...
+or = or
+local-file = Local file
+
+## errors
+error-remote-api =
+    Error reaching Github!
+    Open the log below to see more details. Reason: { $reason }
+error-install = Unable to install update! Reason: { $reason }
+error-no-device = no device found
+error-permission = permission denied
+error-device = unable to send command to device
+error-retrieval = unable to fetch releases
+error-download = unable to download firmware
+error-checksum = firmware failed its integrity check
+error-signature = firmware signature could not be verified
+error-dfu = unable to perform install
+error-verification = install could not be verified
+error-cancelled = install cancelled - the device is still in bootloader mode
+error-timeout = install timed out - the device is still in bootloader mode
+error-io = unable to read or write a file
+error-update = unable to update
+error-setup = unable to complete setup
+error-policy = refused by the firmware policy
+
+## error hints
+hint-no-device = Check the USB cable and that the device is powered on, then try again.
+hint-permission = Your user can't access the device. Run `ahoy doctor` to see how to fix USB permissions.
+hint-device = The device didn't respond as expected. Reconnect it and try again.
+hint-retrieval = Check your internet connection, or wait a while if Github is rate limiting you.
+hint-download = The download didn't finish. Check your internet connection and try again.
+hint-checksum = The downloaded firmware is damaged. Try the download again.
+hint-signature = The firmware isn't signed by a key you trust, or doesn't match its signature. Download it again, or only install firmware from a trusted source.
+hint-dfu = Reconnect the device and use Recover to finish the install.
+hint-verification = The firmware wasn't fully written, or the device isn't running it. Use Recover to install it again.
+hint-bootloader = The device may be left in bootloader mode. Use Recover to finish the install.
+hint-io = Check there's free disk space and that the file can be read.
+hint-update = Download the latest Ahoy release from Github instead.
+hint-setup = Re-run the command with sudo, or follow the setup steps in the README.
+hint-policy = Your firmware policy doesn't allow this version. Pick an allowed release, or ask whoever manages the policy file.
+
+## settings
+settings-theme = Theme
+settings-language = Language
+settings-system-language = System default
+theme-system = System
+theme-light = Light
+theme-dark = Dark
+
+## waiting for a device
+connect-prompt = Please connect your
+
+## connected device
+device-connected = CONNECTED
+device-uid = UID: { $uid }
+device-up-to-date = Up to date - { $version }
+device-update-available = Update available: { $version } (installed { $installed })
+device-update = Update
+
+## release controls
+controls-refresh = Refresh
+controls-history = History
+channel-stable = Stable
+channel-beta = Beta
+channel-draft = Draft
+channel-local = Local
+
+## release list
+versions-installed = installed
+versions-draft = draft
+versions-not-allowed = not allowed
+versions-local-title = Install a local firmware file
+versions-local-placeholder = path to a firmware .bin file
+versions-download-install = Download and Install
+versions-no-assets = No assets are available for download for this device
+versions-select = Please select a release
+
+## install history
+history-empty = No installs have been recorded for this device
+history-success = SUCCESS
+history-failed = FAILED
+history-cancelled = CANCELLED
+history-unknown = unknown
+history-via = { $time } via { $client }
+
+## confirming an install
+confirm-ready = The binary has been downloaded and is ready to install!
+confirm-bridge = Next, take a TS or TRS cable and bridge Flexiports 1 and 2
+confirm-do-not-unplug = PLEASE DO NOT UNPLUG YOUR DEVICE UNTIL THE INSTALLATION IS FINISHED.
+downgrade-warning = This is a downgrade from { $installed } to { $version }. Older firmware may not support everything you've configured.
+signed-by = Signed by { $name }
+unsigned = Not signed by a trusted key
+
+## installing
+download-progress = Downloading firmware - { $downloaded } of { $total } KB
+install-waiting-device = WAITING FOR DEVICE
+install-waiting-bootloader = Waiting for device to enter bootloader mode...
+install-cancelling = Cancelling - finishing the current block...
+install-preparing = Preparing device for new firmware...
+install-writing = Installing firmware...
+install-finalizing = Finalizing installation...
+install-done = Done!
+install-verifying = Checking the device restarted with the new firmware...
+install-stats = { $rate } KB/s - { $seconds }s remaining
+install-complete = Installation Complete!
+install-complete-detail = Unplug your device and go forth brave explorer!
+
+## recovering a device stuck in bootloader mode
+recover-title = RECOVER DEVICE
+recover-detected = A device in bootloader mode was detected
+recover-explanation = This usually means an installation was interrupted. Select your device and a release (or a local firmware file) to reinstall.
+recover-model = Model
+recover-revision = Revision
+recover-release = Release
+recover-select-revision = Select a revision
+recover-select-release = Select a release
+recover-button = Recover Device
+recover-no-firmware = no firmware found for the selected device and release
+
+## log panel
+log-show = Show log
+log-hide = Hide log
+log-empty = Nothing has been logged yet.
+log-export = Export diagnostics
+log-exporting = Collecting diagnostics...
+log-exported = Diagnostics saved to { $path }
+log-export-failed = Unable to export diagnostics: { $reason }
+
+## updating ahoy itself
+update-available-title = There is an update available for this application!
+update-available-version = Version: { $version } is now available!
+update-dismiss = Dismiss
+update-and-quit = Update and Quit
+
+## dry runs
+simulation-banner = Simulation - no device or network is used. Failure: { $fault }
+simulation-no-fault = none
+
+## command line
+cli-entering-bootloader = entering bootloader mode...
+cli-waiting-for-bootloader = pausing thread for 3 seconds to wait for bootloader mode...
+cli-fetching-release = fetching { $tag } release...
+cli-fetching-latest = fetching latest { $channels } release...
+cli-downloading = downloading { $name }...
+cli-installing = installing...
+cli-verifying = waiting for the device to restart with the new firmware...
+cli-dry-run = dry run - simulating the device and downloads, nothing will be installed
+cli-dry-run-fault = simulating a failure: { $fault }
+cli-progress-writing = writing
+cli-progress-manifesting = manifesting
+cli-progress-done = done
+cli-left-in-bootloader = the device was left in bootloader mode - run `ahoy recover` to try again
+cli-no-releases = no releases found in: { $channels }
+cli-update-available = update available for { $device }: { $installed } -> { $version }
+cli-up-to-date = { $device } is up to date ({ $installed })
+cli-no-history = no installs recorded
+cli-update-complete = update complete
+cli-udev-installed = udev rules installed to { $path }
+cli-udev-reconnect = unplug and reconnect your device for them to take effect
+cli-udev-save = not running as root - save the following to { $path }
+cli-udev-reload = then run
+cli-udev-sudo = (or re-run this command with sudo)
+cli-report-written = report written to: { $path }
+cli-diagnostics-written = diagnostics written to: { $path }
+cli-station-waiting = waiting for the next unit (recording to { $log }) - press ctrl-c to stop
+cli-station-flashing = flashing { $device } { $uid }...
+cli-station-skipped = { $uid } was already flashed - unplug it and connect the next unit
+cli-station-pass = PASS { $uid } ({ $seconds }s)
+cli-station-fail = FAIL { $uid }: { $reason }
+cli-serving = serving the api on http://{ $address } - press ctrl-c to stop
+cli-serving-token = send every request with the header: Authorization: Bearer { $token }
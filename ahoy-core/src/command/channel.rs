@@ -0,0 +1,129 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use super::github::Release;
+use crate::i18n::t;
+
+/// where firmware comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Channel {
+    Stable,
+    Beta,
+    Draft,
+    Local,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] = [
+        Channel::Stable,
+        Channel::Beta,
+        Channel::Draft,
+        Channel::Local,
+    ];
+
+    /// the channel a github release is published to
+    pub fn of(release: &Release) -> Channel {
+        if release.draft {
+            Channel::Draft
+        } else if release.is_prerelease() {
+            Channel::Beta
+        } else {
+            Channel::Stable
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Stable => write!(f, "{}", t!("channel-stable")),
+            Channel::Beta => write!(f, "{}", t!("channel-beta")),
+            Channel::Draft => write!(f, "{}", t!("channel-draft")),
+            Channel::Local => write!(f, "{}", t!("channel-local")),
+        }
+    }
+}
+
+// only release channels can be asked for by name - local files never match a release
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "beta" | "prerelease" => Ok(Channel::Beta),
+            "draft" => Ok(Channel::Draft),
+            _ => Err(format!(
+                "unknown channel: {} (expected stable, beta or draft)",
+                s
+            )),
+        }
+    }
+}
+
+/// the channels a user wants to see - never empty, stable by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channels(BTreeSet<Channel>);
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels(BTreeSet::from([Channel::Stable]))
+    }
+}
+
+impl Channels {
+    pub fn new(channels: impl IntoIterator<Item = Channel>) -> Channels {
+        let channels: BTreeSet<Channel> = channels.into_iter().collect();
+        if channels.is_empty() {
+            Channels::default()
+        } else {
+            Channels(channels)
+        }
+    }
+
+    pub fn contains(&self, channel: Channel) -> bool {
+        self.0.contains(&channel)
+    }
+
+    /// turn a channel on or off - the last selected channel stays on
+    pub fn toggle(&mut self, channel: Channel) {
+        if !self.0.remove(&channel) {
+            self.0.insert(channel);
+        } else if self.0.is_empty() {
+            self.0.insert(channel);
+        }
+    }
+
+    pub fn matches(&self, release: &Release) -> bool {
+        self.contains(Channel::of(release))
+    }
+}
+
+impl fmt::Display for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(|channel| channel.to_string()).collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_keeps_one_channel() {
+        let mut channels = Channels::default();
+        channels.toggle(Channel::Beta);
+        assert!(channels.contains(Channel::Stable) && channels.contains(Channel::Beta));
+
+        channels.toggle(Channel::Stable);
+        channels.toggle(Channel::Beta);
+        assert_eq!(channels, Channels::new([Channel::Beta]));
+        assert_eq!(Channels::new([]), Channels::default());
+    }
+
+    #[test]
+    fn only_release_channels_are_parsed() {
+        assert_eq!("Prerelease".parse(), Ok(Channel::Beta));
+        assert!("local".parse::<Channel>().is_err());
+    }
+}
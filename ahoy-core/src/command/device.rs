@@ -0,0 +1,363 @@
+use std::{
+    env::temp_dir,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_std::task;
+use dfu_libusb::DfuLibusb;
+use log::{error, info, warn};
+use pirate_midi_rs::{check::CheckResponse, Command, ControlArgs, PirateMIDIDevice, Response};
+use rusb::{Context, Device};
+use serialport::SerialPortType;
+
+use crate::{USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_VENDOR_ID};
+
+use super::{
+    signature::signature_path,
+    transfer::{
+        reset_to_idle, Abort, CancelToken, ProgressEvent, ProgressReporter, Timeouts,
+        TransferReader,
+    },
+    CommandError, Context as _, Detail,
+};
+
+/// device models we know how to find firmware for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    #[default]
+    Bridge6,
+    Bridge4,
+}
+
+impl Model {
+    pub const ALL: [Model; 2] = [Model::Bridge6, Model::Bridge4];
+
+    /// the model as it appears in asset names and check responses
+    pub fn id(&self) -> &'static str {
+        match self {
+            Model::Bridge6 => "bridge6",
+            Model::Bridge4 => "bridge4",
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Model::Bridge6 => write!(f, "Bridge 6"),
+            Model::Bridge4 => write!(f, "Bridge 4"),
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.trim().to_lowercase().replace([' ', '-', '_'], "");
+        Model::ALL
+            .into_iter()
+            .find(|model| model.id() == id)
+            .ok_or_else(|| format!("unknown model: {} (expected bridge6 or bridge4)", s))
+    }
+}
+
+/// copy a local firmware file into the temp dir, so it can be cleaned up like a download
+pub fn stage_firmware(path: &Path) -> Result<PathBuf, CommandError> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| CommandError::IO(format!("not a file: {}", path.display()).into()))?;
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let staged = temp_dir().join(format!("{time}-{}", name.to_string_lossy()));
+
+    info!("staging {} to: {}", path.display(), staged.display());
+    std::fs::copy(path, &staged).context(CommandError::IO, "could not copy firmware file")?;
+
+    // its signature comes along, so the staged copy can still be verified
+    let signature = signature_path(path);
+    if signature.exists() {
+        std::fs::copy(&signature, signature_path(&staged))
+            .context(CommandError::IO, "could not copy firmware signature")?;
+    }
+    Ok(staged)
+}
+
+pub async fn install_binary(
+    binary_path: PathBuf,
+    progress: Option<impl FnMut(ProgressEvent) + Send + 'static>,
+    raw_device: Option<Device<Context>>,
+    token: CancelToken,
+    timeouts: Timeouts,
+) -> Result<(), CommandError> {
+    let (context, vid, pid) = dfu_target(raw_device)?;
+
+    // the transfer blocks on usb i/o, so it runs on its own worker thread
+    let mut worker = task::spawn_blocking({
+        let token = token.clone();
+        let context = context.clone();
+        move || transfer(binary_path, progress, context, vid, pid, token, timeouts)
+    });
+
+    match async_std::future::timeout(timeouts.overall + timeouts.transfer, &mut worker).await {
+        Ok(result) => result,
+        Err(_) => {
+            // the worker is stuck in a usb call - stop it at the next block, and give it
+            // that long to let go of the device before putting it back in a clean state
+            token.cancel();
+            if async_std::future::timeout(timeouts.transfer, worker)
+                .await
+                .is_err()
+            {
+                warn!("transfer did not stop within {:?}", timeouts.transfer);
+            }
+            task::spawn_blocking(move || {
+                if let Err(err) = reset_to_idle(&context, vid, pid, timeouts.transfer) {
+                    warn!("unable to return device to dfu idle: {}", err);
+                }
+            })
+            .await;
+            Err(CommandError::Timeout(
+                format!(
+                    "install did not finish within {} seconds",
+                    timeouts.overall.as_secs()
+                )
+                .into(),
+            ))
+        }
+    }
+}
+
+/// the usb context, VID and PID to open the DFU interface with
+fn dfu_target(raw_device: Option<Device<Context>>) -> Result<(Context, u16, u16), CommandError> {
+    match raw_device {
+        Some(device) => {
+            // get device descriptor
+            let (vid, pid) = match device.device_descriptor() {
+                Ok(desc) => (desc.vendor_id(), desc.product_id()),
+                Err(err) => panic!(
+                    "unable to get device descriptors from usb device! - error: {}",
+                    err
+                ),
+            };
+            Ok((device.context().clone(), vid, pid))
+        }
+        // if we didn't pass in a device, just try to guess via VID and PID
+        None => {
+            // create new usb context
+            info!("device was not passed in - creating new usb context");
+            let context = rusb::Context::new()
+                .context(CommandError::Device, "unable to create usb context")?;
+            Ok((context, USB_VENDOR_ID, USB_PRODUCT_DFU_ID))
+        }
+    }
+}
+
+fn transfer(
+    binary_path: PathBuf,
+    progress: Option<impl FnMut(ProgressEvent) + Send + 'static>,
+    context: Context,
+    vid: u16,
+    pid: u16,
+    token: CancelToken,
+    timeouts: Timeouts,
+) -> Result<(), CommandError> {
+    // open the binary file and get the file size
+    let file = std::fs::File::open(binary_path)
+        .context(CommandError::IO, "could not open firmware file")?;
+    let length = file
+        .metadata()
+        .context(CommandError::IO, "could not read firmware file size")?
+        .len() as u32;
+
+    // open the DFU interface
+    info!("opening interface: {:#06x}:{:#06x}", vid, pid);
+    let mut dfu_iface = DfuLibusb::open(&context, vid, pid, 0, 0).map_err(open_error)?;
+
+    // setup our progress reporting - if available
+    let reporter = ProgressReporter::new(length as u64, {
+        let mut progress = progress;
+        move |event| {
+            if let Some(progress) = progress.as_mut() {
+                progress(event)
+            }
+        }
+    });
+    dfu_iface.with_progress({
+        let reporter = reporter.clone();
+        move |count| reporter.block_written(count)
+    });
+
+    // PERFORM THE INSTALL
+    let mut reader = TransferReader::new(file, token, timeouts);
+    let result = match dfu_iface.download(&mut reader, length) {
+        Ok(_) => Ok(()),
+        // the device detaches once manifestation completes
+        Err(dfu_libusb::Error::LibUsb(rusb::Error::Io)) => Ok(()),
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(_) if reporter.bytes_written() != length as u64 => {
+            error!(
+                "incomplete transfer: wrote {} of {} bytes",
+                reporter.bytes_written(),
+                length
+            );
+            Err(CommandError::Verification(
+                format!(
+                    "only {} of {} bytes were written",
+                    reporter.bytes_written(),
+                    length
+                )
+                .into(),
+            ))
+        }
+        Ok(_) => {
+            reporter.emit(ProgressEvent::Done);
+            Ok(())
+        }
+        Err(err) => match reader.abort_reason() {
+            Some(abort) => {
+                // release our interface before putting the device back into a clean state
+                drop(dfu_iface);
+                if let Err(err) = reset_to_idle(&context, vid, pid, timeouts.transfer) {
+                    warn!("unable to return device to dfu idle: {}", err);
+                }
+                Err(match abort {
+                    Abort::Cancelled => CommandError::Cancelled,
+                    Abort::TransferTimeout(timeout) => CommandError::Timeout(
+                        format!(
+                            "device did not respond within {} seconds",
+                            timeout.as_secs()
+                        )
+                        .into(),
+                    ),
+                    Abort::OverallTimeout(timeout) => CommandError::Timeout(
+                        format!(
+                            "install did not finish within {} seconds",
+                            timeout.as_secs()
+                        )
+                        .into(),
+                    ),
+                })
+            }
+            None => {
+                error!("dfu download error: {}", err);
+                Err(CommandError::Dfu(
+                    Detail::new("firmware transfer failed").because(err),
+                ))
+            }
+        },
+    }
+}
+
+/// permission errors are common on linux, so they get their own hint
+fn open_error(err: dfu_libusb::Error) -> CommandError {
+    match err {
+        dfu_libusb::Error::LibUsb(rusb::Error::Access) => CommandError::Permission(
+            Detail::new("unable to open the device in bootloader mode").because(err),
+        ),
+        dfu_libusb::Error::LibUsb(rusb::Error::NoDevice | rusb::Error::NotFound) => {
+            CommandError::NoDevice(
+                Detail::new("no device in bootloader mode was found").because(err),
+            )
+        }
+        err => CommandError::Dfu(Detail::new("unable to open the device").because(err)),
+    }
+}
+
+/// build a device handle - bound to a specific serial port when one is known.
+/// without one, whichever device answers first is used - only the command line does that,
+/// when it's run without `--port`. anything that picked out a device needs its port.
+pub fn pirate_device(port: Option<&str>) -> PirateMIDIDevice {
+    match port {
+        Some(port) => {
+            info!("using serial port: {}", port);
+            PirateMIDIDevice::new().with_port_name(port)
+        }
+        None => {
+            warn!("no serial port specified - letting the device pick the first match");
+            PirateMIDIDevice::new()
+        }
+    }
+}
+
+/// find the serial port that belongs to a specific usb device.
+/// if the serial number is unknown, we only return a port when there is exactly one candidate.
+pub fn find_serial_port(
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: Option<&str>,
+) -> Option<String> {
+    let ports = match serialport::available_ports() {
+        Ok(ports) => ports,
+        Err(err) => {
+            error!("unable to enumerate serial ports: {}", err);
+            return None;
+        }
+    };
+
+    let candidates: Vec<(String, Option<String>)> = ports
+        .into_iter()
+        .filter_map(|port| match port.port_type {
+            SerialPortType::UsbPort(info) if info.vid == vendor_id && info.pid == product_id => {
+                Some((port.port_name, info.serial_number))
+            }
+            _ => None,
+        })
+        .collect();
+
+    match serial_number {
+        Some(serial) => candidates
+            .into_iter()
+            .find(|(_, port_serial)| port_serial.as_deref() == Some(serial))
+            .map(|(name, _)| name),
+        None if candidates.len() == 1 => candidates.into_iter().next().map(|(name, _)| name),
+        None => {
+            warn!(
+                "found {} matching serial ports, but no serial number to tell them apart",
+                candidates.len()
+            );
+            None
+        }
+    }
+}
+
+/// the serial port of the only pirate midi device plugged in - with more than one,
+/// its port has to be named, since any of them could answer first
+pub fn only_port() -> Result<String, CommandError> {
+    find_serial_port(USB_VENDOR_ID, USB_PRODUCT_ID, None).ok_or_else(|| {
+        CommandError::NoDevice(
+            "there isn't exactly one device plugged in to pick - name its port".into(),
+        )
+    })
+}
+
+/// ask the device about itself
+pub fn check_device(port: Option<&str>) -> Result<CheckResponse, CommandError> {
+    match pirate_device(port).send(Command::Check) {
+        Ok(Response::Check(details)) => Ok(details),
+        Ok(response) => Err(CommandError::Device(
+            format!("unexpected response to check: {:?}", response).into(),
+        )),
+        // nothing answering on the serial port almost always means nothing is plugged in
+        Err(err) => Err(CommandError::NoDevice(
+            format!("unable to retrieve device details: {}", err).into(),
+        )),
+    }
+}
+
+pub async fn enter_bootloader(port: Option<String>) -> Result<(), CommandError> {
+    match pirate_device(port.as_deref()).send(Command::Control(ControlArgs::EnterBootloader)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(CommandError::Device(
+            format!("UNABLE TO ENTER BOOTLOADER: {}", err).into(),
+        )),
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use zip::{write::FileOptions, ZipWriter};
+
+use super::{
+    doctor::Report, history::journal_path, logging, settings::settings_path, CommandError, Context,
+    Detail,
+};
+
+/// a name for the bundle in the downloads folder, so it's easy to find and attach
+pub fn default_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join(format!("ahoy-diagnostics-{}.zip", timestamp))
+}
+
+/// zip up the logs, settings, install journal and a doctor report (which covers the device)
+pub fn export(report: &Report, destination: &Path) -> Result<(), CommandError> {
+    info!("exporting diagnostics to: {}", destination.display());
+
+    // make sure the log file has everything up to this point
+    log::logger().flush();
+
+    let io_error =
+        |e: io::Error| CommandError::IO(Detail::new("could not write diagnostics").because(e));
+    let zip_error = |e: zip::result::ZipError| {
+        CommandError::IO(Detail::new("could not write diagnostics").because(e))
+    };
+
+    let mut zip = ZipWriter::new(File::create(destination).map_err(io_error)?);
+    let options = FileOptions::default();
+
+    let json = serde_json::to_string_pretty(report)
+        .context(CommandError::IO, "could not serialize report")?;
+    zip.start_file("doctor.json", options).map_err(zip_error)?;
+    zip.write_all(json.as_bytes()).map_err(io_error)?;
+    zip.start_file("doctor.txt", options).map_err(zip_error)?;
+    zip.write_all(report.to_string().as_bytes())
+        .map_err(io_error)?;
+
+    // everything else is copied as it is on disk, where it exists
+    let mut files = vec![
+        ("settings.json".to_string(), settings_path()?),
+        ("history.jsonl".to_string(), journal_path()?),
+    ];
+    files.extend(logging::log_files()?.into_iter().map(|path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        (format!("logs/{}", name), path)
+    }));
+    for (name, path) in files.into_iter().filter(|(_, path)| path.exists()) {
+        match File::open(&path) {
+            Ok(mut file) => {
+                zip.start_file(name, options).map_err(zip_error)?;
+                io::copy(&mut file, &mut zip).map_err(io_error)?;
+            }
+            Err(err) => warn!("leaving {} out of diagnostics: {}", path.display(), err),
+        }
+    }
+
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
@@ -0,0 +1,656 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use pirate_midi_rs::{check::CheckResponse, Command, Response};
+use rusb::UsbContext;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPortType;
+
+use crate::{GITHUB_API_URL, USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_VENDOR_ID};
+
+use super::device::{find_serial_port, pirate_device};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Status::Ok => "OK",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+        };
+        write!(f, "{:>4}", label)
+    }
+}
+
+/// a single diagnostic result, with an optional hint on how to fix it
+#[derive(Serialize, Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: Status, detail: impl Into<String>) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: impl Into<String>) -> Check {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       -> {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// everything we usually have to ask a user for when an install fails
+#[derive(Serialize, Debug, Clone)]
+pub struct Report {
+    pub generated_at: u64,
+    pub app_version: String,
+    pub latest_app_version: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub libusb_version: String,
+    pub hotplug: bool,
+    pub devices: Vec<DetectedDevice>,
+    pub serial_ports: Vec<SerialPort>,
+    pub github: GithubStatus,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DetectedDevice {
+    pub mode: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub bus: u8,
+    pub address: u8,
+    pub serial_number: Option<String>,
+    pub details: Option<DeviceDetails>,
+}
+
+/// the subset of a device's check response that is useful when debugging
+#[derive(Serialize, Debug, Clone)]
+pub struct DeviceDetails {
+    pub uid: String,
+    pub device_name: String,
+    pub device_model: String,
+    pub firmware_version: String,
+    pub hardware_version: String,
+}
+
+impl From<CheckResponse> for DeviceDetails {
+    fn from(details: CheckResponse) -> DeviceDetails {
+        DeviceDetails {
+            uid: details.uid,
+            device_name: details.device_name,
+            device_model: details.device_model,
+            firmware_version: details.firmware_version,
+            hardware_version: details.hardware_version,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SerialPort {
+    pub name: String,
+    pub kind: String,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub serial_number: Option<String>,
+    pub product: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct GithubStatus {
+    pub reachable: bool,
+    pub error: Option<String>,
+    pub rate_limit: Option<u64>,
+    pub rate_remaining: Option<u64>,
+    pub rate_reset: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResources {
+    core: RateLimit,
+}
+
+#[derive(Deserialize)]
+struct RateLimit {
+    limit: u64,
+    remaining: u64,
+    reset: u64,
+}
+
+/// gather the full diagnostic report - the app says which version it is, and whether there's a newer one
+pub async fn report(
+    port: Option<String>,
+    app_version: &str,
+    latest_app_version: Option<String>,
+) -> Report {
+    info!("generating diagnostic report...");
+
+    let github = github_status().await;
+
+    let devices = detect_devices(port.as_deref());
+    let serial_ports = serial_ports();
+
+    let mut checks = vec![];
+    checks.push(if rusb::has_hotplug() {
+        Check::new("hotplug", Status::Ok, "supported")
+    } else {
+        Check::new(
+            "hotplug",
+            Status::Warn,
+            "not supported - falling back to polling for devices",
+        )
+    });
+    checks.push(if devices.is_empty() {
+        Check::new("devices", Status::Warn, "no Pirate MIDI device detected")
+            .with_hint("check the USB cable (some are power only) and try another port")
+    } else {
+        Check::new(
+            "devices",
+            Status::Ok,
+            format!("{} Pirate MIDI device(s) detected", devices.len()),
+        )
+    });
+    checks.push(match (&github.error, github.rate_remaining) {
+        (Some(err), _) => Check::new("github", Status::Fail, err.clone())
+            .with_hint("check your internet connection, proxy or firewall settings"),
+        (None, Some(0)) => Check::new("github", Status::Fail, "api rate limit exhausted")
+            .with_hint("wait for the rate limit to reset and try again"),
+        (None, _) => Check::new("github", Status::Ok, "reachable"),
+    });
+    if let Some(latest) = &latest_app_version {
+        checks.push(
+            Check::new(
+                "app version",
+                Status::Warn,
+                format!("v{} is available", latest),
+            )
+            .with_hint("run `ahoy update`"),
+        );
+    }
+    checks.extend(permission_checks());
+
+    Report {
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        app_version: app_version.to_string(),
+        latest_app_version,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        libusb_version: libusb_version(),
+        hotplug: rusb::has_hotplug(),
+        devices,
+        serial_ports,
+        github,
+        checks,
+    }
+}
+
+impl Report {
+    pub fn has_failures(&self) -> bool {
+        self.checks.iter().any(|check| check.status == Status::Fail)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== ahoy doctor ==")?;
+        writeln!(f, "generated at:   {}", self.generated_at)?;
+        writeln!(
+            f,
+            "ahoy:           v{} (latest: {})",
+            self.app_version,
+            self.latest_app_version
+                .as_ref()
+                .map(|v| format!("v{}", v))
+                .unwrap_or_else(|| "up to date".to_string())
+        )?;
+        writeln!(f, "platform:       {} {}", self.os, self.arch)?;
+        writeln!(f, "libusb:         {}", self.libusb_version)?;
+        writeln!(f, "hotplug:        {}", self.hotplug)?;
+
+        writeln!(f, "\n== devices ==")?;
+        if self.devices.is_empty() {
+            writeln!(f, "none")?;
+        }
+        for device in &self.devices {
+            writeln!(
+                f,
+                "{:04x}:{:04x} [{}] bus {:03} address {:03} serial {}",
+                device.vendor_id,
+                device.product_id,
+                device.mode,
+                device.bus,
+                device.address,
+                device.serial_number.as_deref().unwrap_or("unknown")
+            )?;
+            if let Some(details) = &device.details {
+                writeln!(
+                    f,
+                    "  {} ({}) uid {} - firmware {} - hardware {}",
+                    details.device_name,
+                    details.device_model,
+                    details.uid,
+                    details.firmware_version,
+                    details.hardware_version
+                )?;
+            }
+        }
+
+        writeln!(f, "\n== serial ports ==")?;
+        if self.serial_ports.is_empty() {
+            writeln!(f, "none")?;
+        }
+        for port in &self.serial_ports {
+            write!(f, "{} [{}]", port.name, port.kind)?;
+            if let (Some(vid), Some(pid)) = (port.vendor_id, port.product_id) {
+                write!(f, " {:04x}:{:04x}", vid, pid)?;
+            }
+            if let Some(serial) = &port.serial_number {
+                write!(f, " serial {}", serial)?;
+            }
+            if let Some(product) = &port.product {
+                write!(f, " ({})", product)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n== github ==")?;
+        writeln!(f, "reachable:      {}", self.github.reachable)?;
+        if let (Some(limit), Some(remaining)) = (self.github.rate_limit, self.github.rate_remaining)
+        {
+            writeln!(
+                f,
+                "rate limit:     {}/{} remaining (resets at {})",
+                remaining,
+                limit,
+                self.github.rate_reset.unwrap_or_default()
+            )?;
+        }
+
+        writeln!(f, "\n== checks ==")?;
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+/// human readable libusb version
+pub fn libusb_version() -> String {
+    let version = rusb::version();
+    format!(
+        "v{}.{}.{}.{}{}",
+        version.major(),
+        version.minor(),
+        version.micro(),
+        version.nano(),
+        version.rc().unwrap_or("")
+    )
+}
+
+fn detect_devices(port: Option<&str>) -> Vec<DetectedDevice> {
+    let devices = match rusb::Context::new().and_then(|ctx| ctx.devices()) {
+        Ok(devices) => devices,
+        Err(err) => {
+            warn!("unable to enumerate usb devices: {}", err);
+            return vec![];
+        }
+    };
+
+    devices
+        .iter()
+        .filter_map(|device| {
+            let desc = device.device_descriptor().ok()?;
+            let mode = match (desc.vendor_id(), desc.product_id()) {
+                (USB_VENDOR_ID, USB_PRODUCT_ID) => "serial",
+                (USB_VENDOR_ID, USB_PRODUCT_DFU_ID) => "dfu",
+                _ => return None,
+            };
+
+            // reading string descriptors requires opening the device, which may not be permitted
+            let serial_number = device
+                .open()
+                .and_then(|handle| handle.read_serial_number_string_ascii(&desc))
+                .ok();
+
+            let details = if mode == "serial" {
+                let port = port.map(String::from).or_else(|| {
+                    find_serial_port(
+                        desc.vendor_id(),
+                        desc.product_id(),
+                        serial_number.as_deref(),
+                    )
+                });
+                device_details(port.as_deref())
+            } else {
+                None
+            };
+
+            Some(DetectedDevice {
+                mode: mode.to_string(),
+                vendor_id: desc.vendor_id(),
+                product_id: desc.product_id(),
+                bus: device.bus_number(),
+                address: device.address(),
+                serial_number,
+                details,
+            })
+        })
+        .collect()
+}
+
+fn device_details(port: Option<&str>) -> Option<DeviceDetails> {
+    match pirate_device(port).send(Command::Check) {
+        Ok(Response::Check(details)) => Some(details.into()),
+        Ok(_) => None,
+        Err(err) => {
+            warn!("unable to retrieve device details: {:?}", err);
+            None
+        }
+    }
+}
+
+fn serial_ports() -> Vec<SerialPort> {
+    match serialport::available_ports() {
+        Ok(ports) => ports
+            .into_iter()
+            .map(|port| match port.port_type {
+                SerialPortType::UsbPort(info) => SerialPort {
+                    name: port.port_name,
+                    kind: "usb".to_string(),
+                    vendor_id: Some(info.vid),
+                    product_id: Some(info.pid),
+                    serial_number: info.serial_number,
+                    product: info.product,
+                },
+                other => SerialPort {
+                    name: port.port_name,
+                    kind: match other {
+                        SerialPortType::PciPort => "pci",
+                        SerialPortType::BluetoothPort => "bluetooth",
+                        _ => "unknown",
+                    }
+                    .to_string(),
+                    vendor_id: None,
+                    product_id: None,
+                    serial_number: None,
+                    product: None,
+                },
+            })
+            .collect(),
+        Err(err) => {
+            warn!("unable to enumerate serial ports: {}", err);
+            vec![]
+        }
+    }
+}
+
+async fn github_status() -> GithubStatus {
+    let request = surf::get(format!("{}/rate_limit", GITHUB_API_URL))
+        .recv_json::<RateLimitResponse>()
+        .await;
+
+    match request {
+        Ok(res) => GithubStatus {
+            reachable: true,
+            error: None,
+            rate_limit: Some(res.resources.core.limit),
+            rate_remaining: Some(res.resources.core.remaining),
+            rate_reset: Some(res.resources.core.reset),
+        },
+        Err(err) => GithubStatus {
+            reachable: false,
+            error: Some(err.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// inspect everything that commonly stops a non-root user from talking to a device
+#[cfg(target_os = "linux")]
+pub fn permission_checks() -> Vec<Check> {
+    let mut checks = vec![linux::udev_rules()];
+    checks.extend(linux::usb_nodes());
+    checks.extend(linux::serial_nodes());
+    checks.push(linux::modem_manager());
+    checks
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn permission_checks() -> Vec<Check> {
+    vec![Check::new(
+        "permissions",
+        Status::Skip,
+        "device permission checks are only needed on linux",
+    )]
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{ffi::CString, fs, os::unix::fs::MetadataExt, path::Path};
+
+    use rusb::UsbContext;
+    use serialport::SerialPortType;
+
+    use super::{Check, Status};
+    use crate::{
+        command::setup::UDEV_RULES_PATH, USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_VENDOR_ID,
+    };
+
+    const SETUP_HINT: &str = "run `sudo ahoy setup udev`, then unplug and reconnect the device";
+
+    pub(super) fn udev_rules() -> Check {
+        if Path::new(UDEV_RULES_PATH).exists() {
+            Check::new("udev rules", Status::Ok, UDEV_RULES_PATH)
+        } else {
+            Check::new(
+                "udev rules",
+                Status::Warn,
+                format!("{} is not installed", UDEV_RULES_PATH),
+            )
+            .with_hint(SETUP_HINT)
+        }
+    }
+
+    /// libusb needs read/write on /dev/bus/usb/BBB/DDD for both serial and DFU mode
+    pub(super) fn usb_nodes() -> Vec<Check> {
+        let devices = match rusb::Context::new().and_then(|ctx| ctx.devices()) {
+            Ok(devices) => devices,
+            Err(err) => {
+                return vec![Check::new(
+                    "usb access",
+                    Status::Fail,
+                    format!("unable to enumerate usb devices: {}", err),
+                )]
+            }
+        };
+
+        let checks: Vec<Check> = devices
+            .iter()
+            .filter_map(|device| {
+                let desc = device.device_descriptor().ok()?;
+                let mode = match (desc.vendor_id(), desc.product_id()) {
+                    (USB_VENDOR_ID, USB_PRODUCT_ID) => "serial",
+                    (USB_VENDOR_ID, USB_PRODUCT_DFU_ID) => "dfu",
+                    _ => return None,
+                };
+                let node = format!(
+                    "/dev/bus/usb/{:03}/{:03}",
+                    device.bus_number(),
+                    device.address()
+                );
+                let name = format!("usb node ({})", mode);
+                Some(node_check(&name, &node))
+            })
+            .collect();
+
+        if checks.is_empty() {
+            vec![Check::new(
+                "usb access",
+                Status::Skip,
+                "no Pirate MIDI device is connected",
+            )]
+        } else {
+            checks
+        }
+    }
+
+    /// the CDC-ACM port is what the bootloader and check commands are sent over
+    pub(super) fn serial_nodes() -> Vec<Check> {
+        let ports = match serialport::available_ports() {
+            Ok(ports) => ports,
+            Err(err) => {
+                return vec![Check::new(
+                    "serial access",
+                    Status::Fail,
+                    format!("unable to enumerate serial ports: {}", err),
+                )]
+            }
+        };
+
+        ports
+            .into_iter()
+            .filter(|port| match &port.port_type {
+                SerialPortType::UsbPort(info) => {
+                    info.vid == USB_VENDOR_ID && info.pid == USB_PRODUCT_ID
+                }
+                _ => false,
+            })
+            .map(|port| node_check("serial port", &port.port_name))
+            .collect()
+    }
+
+    /// ModemManager probes new CDC-ACM ports and holds them open while it does
+    pub(super) fn modem_manager() -> Check {
+        if !is_process_running("ModemManager") {
+            return Check::new("ModemManager", Status::Ok, "not running");
+        }
+
+        if Path::new(UDEV_RULES_PATH).exists() {
+            Check::new(
+                "ModemManager",
+                Status::Ok,
+                "running, but Pirate MIDI devices are ignored by the udev rules",
+            )
+        } else {
+            Check::new(
+                "ModemManager",
+                Status::Warn,
+                "running - it may grab the device's serial port and block commands",
+            )
+            .with_hint(format!(
+                "{} (this tells ModemManager to ignore the device), or run `sudo systemctl stop ModemManager`",
+                SETUP_HINT
+            ))
+        }
+    }
+
+    fn node_check(name: &str, node: &str) -> Check {
+        let meta = match fs::metadata(node) {
+            Ok(meta) => meta,
+            Err(err) => {
+                return Check::new(name, Status::Fail, format!("{}: {}", node, err))
+                    .with_hint(SETUP_HINT)
+            }
+        };
+
+        let group = group_name(meta.gid()).unwrap_or_else(|| meta.gid().to_string());
+        let detail = format!("{} (mode {:o}, group {})", node, meta.mode() & 0o777, group);
+
+        if can_access(node) {
+            return Check::new(name, Status::Ok, detail);
+        }
+
+        let check = Check::new(
+            name,
+            Status::Fail,
+            format!("no read/write access to {}", detail),
+        );
+        if meta.mode() & 0o060 == 0o060 && !user_groups().contains(&meta.gid()) {
+            check.with_hint(format!(
+                "add yourself to the `{}` group with `sudo usermod -aG {} $USER`, then log out and back in - or {}",
+                group, group, SETUP_HINT
+            ))
+        } else {
+            check.with_hint(SETUP_HINT)
+        }
+    }
+
+    fn can_access(path: &str) -> bool {
+        match CString::new(path) {
+            Ok(path) => unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) == 0 },
+            Err(_) => false,
+        }
+    }
+
+    fn user_groups() -> Vec<u32> {
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        let mut groups = vec![0 as libc::gid_t; count.max(0) as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        groups.truncate(count.max(0) as usize);
+        groups.push(unsafe { libc::getegid() });
+        groups
+    }
+
+    fn group_name(gid: u32) -> Option<String> {
+        fs::read_to_string("/etc/group")
+            .ok()?
+            .lines()
+            .find_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?;
+                let id = fields.nth(1)?.parse::<u32>().ok()?;
+                (id == gid).then(|| name.to_string())
+            })
+    }
+
+    fn is_process_running(name: &str) -> bool {
+        match fs::read_dir("/proc") {
+            Ok(entries) => entries.flatten().any(|entry| {
+                fs::read_to_string(entry.path().join("comm"))
+                    .map(|comm| comm.trim() == name)
+                    .unwrap_or(false)
+            }),
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! releases and assets for tests, with nothing filled in but what they're looked up by
+
+use super::github::{Asset, Release};
+
+pub(crate) fn release(id: u64, tag: &str) -> Release {
+    Release {
+        url: String::new(),
+        html_url: String::new(),
+        assets_url: String::new(),
+        upload_url: String::new(),
+        tarball_url: None,
+        zipball_url: None,
+        discussion_url: None,
+        id,
+        node_id: String::new(),
+        tag_name: tag.to_string(),
+        target_commitish: String::new(),
+        name: None,
+        body: None,
+        draft: false,
+        prerelease: false,
+        created_at: String::new(),
+        published_at: None,
+        assets: vec![],
+    }
+}
+
+pub(crate) fn asset(name: &str) -> Asset {
+    Asset {
+        url: String::new(),
+        browser_download_url: String::new(),
+        id: 0,
+        node_id: String::new(),
+        name: name.to_string(),
+        label: None,
+        state: String::new(),
+        content_type: String::new(),
+        size: 0,
+        download_count: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
@@ -0,0 +1,586 @@
+use async_std::fs::OpenOptions;
+use futures::{AsyncReadExt, AsyncWriteExt};
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::env::{self, temp_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use surf::StatusCode;
+
+use crate::{GITHUB_API_URL, GITHUB_ORG, GITHUB_REPO};
+
+use super::{
+    channel::Channels,
+    signature::{signature_path, SIGNATURE_EXTENSION},
+    version::Version,
+    CommandError, Context, Detail,
+};
+
+/// how many times a download is resumed before giving up on it
+const DOWNLOAD_ATTEMPTS: usize = 3;
+const DOWNLOAD_CHUNK_SIZE: usize = 16 * 1024;
+/// github sends downloads on to its storage host
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Release {
+    pub url: String,
+    pub html_url: String,
+    pub assets_url: String,
+    pub upload_url: String,
+    pub tarball_url: Option<String>,
+    pub zipball_url: Option<String>,
+    pub discussion_url: Option<String>,
+    pub id: u64,
+    pub node_id: String,
+    pub tag_name: String,
+    pub target_commitish: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub created_at: String,
+    pub published_at: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Asset {
+    pub url: String,
+    pub browser_download_url: String,
+    pub id: u64,
+    pub node_id: String,
+    pub name: String,
+    pub label: Option<String>,
+    pub state: String,
+    pub content_type: String,
+    pub size: u64,
+    pub download_count: u64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Release {
+    /// find the firmware asset for a specific device model and hardware revision
+    pub fn find_asset(&self, model: &str, revision: char) -> Option<&Asset> {
+        // this is kind of brittle... :-/
+        // assume format "bridgeX_vX.X.X.X.bin" or "bridgeX_vX.X.X.X-beta.X.bin"
+        // check both the device type, and the hardware revision!
+        // ^bridge6_v\d\.\d\.\d\.1.+$
+        // ^{model}_v\d\.\d\.\d\.{revision}.+$
+        let model = model.trim().to_lowercase();
+        let regex = Regex::new(format!(r"^{model}_v\d\.\d\.\d\.{revision}.+$").as_str())
+            .expect("unable to parse regex pattern");
+
+        // determine if we have a match
+        self.assets.iter().find(|asset| regex.is_match(&asset.name))
+    }
+
+    /// every hardware revision this release has an asset for
+    pub fn revisions(&self, model: &str) -> Vec<char> {
+        let model = model.trim().to_lowercase();
+        let regex = Regex::new(format!(r"^{model}_v\d\.\d\.\d\.(\d).+$").as_str())
+            .expect("unable to parse regex pattern");
+
+        let mut revisions: Vec<char> = self
+            .assets
+            .iter()
+            .filter_map(|asset| regex.captures(&asset.name)?.get(1)?.as_str().chars().next())
+            .collect();
+        revisions.sort_unstable();
+        revisions.dedup();
+        revisions
+    }
+
+    /// the version this release is tagged with, if the tag is a version at all
+    pub fn version(&self) -> Option<Version> {
+        self.tag_name.parse().ok()
+    }
+
+    /// marked as a prerelease on github, or tagged like one
+    pub fn is_prerelease(&self) -> bool {
+        self.prerelease || matches!(self.version(), Some(version) if version.is_prerelease())
+    }
+
+    /// whether this release is the firmware version a device reports
+    pub fn is_version(&self, firmware_version: &str) -> bool {
+        match (self.version(), firmware_version.parse::<Version>()) {
+            (Some(version), Ok(installed)) => version.matches_installed(&installed),
+            _ => false,
+        }
+    }
+}
+
+/// sort releases newest first - tags that aren't versions go last, in their original order
+pub fn sort_releases(releases: &mut [Release]) {
+    releases.sort_by_cached_key(|release| Reverse(release.version()));
+}
+
+/// the newest release that isn't a draft or prerelease
+pub fn latest_stable(releases: &[Release]) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && !release.is_prerelease())
+        .max_by_key(|release| release.version())
+}
+
+/// the newest release in any of the given channels
+pub fn latest_in<'a>(releases: &'a [Release], channels: &Channels) -> Option<&'a Release> {
+    releases
+        .iter()
+        .filter(|release| channels.matches(release))
+        .max_by_key(|release| release.version())
+}
+
+/// the newest prerelease
+pub fn latest_prerelease(releases: &[Release]) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && release.is_prerelease())
+        .max_by_key(|release| release.version())
+}
+
+/// releases newer than the installed firmware, newest first
+pub fn newer_than<'a>(releases: &'a [Release], installed_version: &str) -> Vec<&'a Release> {
+    let installed = match installed_version.parse::<Version>() {
+        Ok(installed) => installed,
+        Err(_) => return vec![],
+    };
+
+    let mut newer: Vec<&Release> = releases
+        .iter()
+        .filter(|release| {
+            matches!(release.version(), Some(version) if version > installed)
+                && !release.is_version(installed_version)
+        })
+        .collect();
+    newer.sort_by_cached_key(|release| Reverse(release.version()));
+    newer
+}
+
+/// the newest release in the given channels with firmware for this device, if it's newer than what's installed.
+/// an installed version that can't be read is an error, since nothing can be said to be newer than it.
+pub fn update_for<'a>(
+    releases: &'a [Release],
+    channels: &Channels,
+    model: &str,
+    revision: char,
+    installed_version: &str,
+) -> Result<Option<&'a Release>, CommandError> {
+    if let Err(err) = installed_version.parse::<Version>() {
+        return Err(CommandError::Device(
+            Detail::new(format!(
+                "the installed firmware {} can't be compared with releases",
+                installed_version
+            ))
+            .because(err),
+        ));
+    }
+    Ok(newer_than(releases, installed_version)
+        .into_iter()
+        .find(|release| channels.matches(release) && release.find_asset(model, revision).is_some()))
+}
+
+/// every release after the installed version, up to and including the selected one, newest first.
+/// prereleases are only included when a prerelease is selected.
+/// if the installed version is unknown, or this is a downgrade, only the selected release is returned.
+pub fn changelog<'a>(
+    releases: &'a [Release],
+    installed_version: &str,
+    selected: &'a Release,
+) -> Vec<&'a Release> {
+    let selected_version = match selected.version() {
+        Some(version) => version,
+        None => return vec![selected],
+    };
+
+    let mut changes: Vec<&Release> = newer_than(releases, installed_version)
+        .into_iter()
+        .filter(|release| {
+            release.id == selected.id
+                || (!release.draft
+                    && (selected.is_prerelease() || !release.is_prerelease())
+                    && matches!(release.version(), Some(version) if version <= selected_version))
+        })
+        .collect();
+
+    if !changes.iter().any(|release| release.id == selected.id) {
+        changes = vec![selected];
+    }
+    changes
+}
+
+/// whether installing the selected release would go back to an older version
+pub fn is_downgrade(installed_version: &str, selected: &Release) -> bool {
+    match (installed_version.parse::<Version>(), selected.version()) {
+        (Ok(installed), Some(version)) => {
+            version < installed && !selected.is_version(installed_version)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Query {
+    per_page: u32,
+    page: u32,
+}
+
+/// drafts are only visible to authenticated users with access to the repo
+fn github_token() -> Option<String> {
+    env::var("GITHUB_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
+
+/// retrieve all available github releases
+pub async fn fetch_releases() -> Result<Vec<Release>, CommandError> {
+    info!("fetching releases from github...");
+    let mut request = surf::get(format!(
+        "{}/repos/{}/{}/releases",
+        GITHUB_API_URL, GITHUB_ORG, GITHUB_REPO
+    ))
+    .query(&Query {
+        per_page: 50,
+        page: 1,
+    })
+    .unwrap();
+
+    if let Some(token) = github_token() {
+        info!("using GITHUB_TOKEN - drafts will be included");
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = request.recv_json::<Vec<Release>>();
+
+    match request.await {
+        Ok(mut res) => {
+            sort_releases(&mut res);
+            Ok(res)
+        }
+        Err(err) => Err(CommandError::Retrieval(
+            Detail::new("github did not return a release list").because(err),
+        )),
+    }
+}
+
+/// how much of a release asset has been downloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub bytes: u64,
+    pub total: u64,
+}
+
+/// where unfinished downloads are kept, so they can be resumed - even after a restart
+fn download_cache() -> Result<PathBuf, CommandError> {
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(temp_dir)
+        .join("ahoy")
+        .join("downloads");
+    fs::create_dir_all(&dir).context(
+        CommandError::IO,
+        format!("could not create {}", dir.display()),
+    )?;
+    Ok(dir)
+}
+
+/// download a release asset into the temp dir, reporting progress as it arrives.
+/// an interrupted download is resumed where it left off - here, or the next time it's fetched.
+pub async fn fetch_asset(
+    asset: Asset,
+    mut progress: impl FnMut(DownloadProgress) + Send,
+) -> Result<PathBuf, CommandError> {
+    info!("fetching asset from github: {}", asset.browser_download_url);
+    // assets get a new id whenever they're replaced, so a stale download is never resumed
+    let partial = download_cache()?.join(format!("{}-{}.part", asset.id, asset.name));
+
+    let mut attempt = 1;
+    while let Err(err) = download_into(&asset, &partial, &mut progress).await {
+        match err {
+            CommandError::Download(_) if attempt < DOWNLOAD_ATTEMPTS => {
+                warn!(
+                    "download attempt {} failed, resuming: {}",
+                    attempt,
+                    err.report()
+                );
+                attempt += 1;
+            }
+            err => return Err(err),
+        }
+    }
+
+    // finished downloads go to the temp dir, to be cleaned up like before
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = temp_dir().join(format!("{time}-{}", asset.name));
+    fs::rename(&partial, &path)
+        .or_else(|_| fs::copy(&partial, &path).and_then(|_| fs::remove_file(&partial)))
+        .context(CommandError::IO, "could not save firmware")?;
+    info!(
+        "successfully downloaded {} bytes to: {}",
+        asset.size,
+        path.display()
+    );
+
+    fetch_signature(&asset, &path).await;
+    Ok(path)
+}
+
+/// download whatever's missing from the partial file - all of it, if the server can't resume
+async fn download_into(
+    asset: &Asset,
+    partial: &Path,
+    progress: &mut impl FnMut(DownloadProgress),
+) -> Result<(), CommandError> {
+    let mut bytes = fs::metadata(partial).map(|file| file.len()).unwrap_or(0);
+    if bytes > asset.size {
+        warn!(
+            "discarding oversized partial download: {}",
+            partial.display()
+        );
+        bytes = 0;
+    } else if bytes > 0 && bytes == asset.size {
+        info!("already downloaded: {}", partial.display());
+        progress(DownloadProgress {
+            bytes,
+            total: asset.size,
+        });
+        return Ok(());
+    }
+
+    let mut response = request_asset(asset, bytes).await?;
+    match response.status() {
+        StatusCode::PartialContent => info!("resuming download from byte {}", bytes),
+        // the partial download doesn't fit the asset any more - start over on the next attempt
+        StatusCode::RequestedRangeNotSatisfiable => {
+            let _ = fs::remove_file(partial);
+            return Err(CommandError::Download(
+                format!("could not resume {}", asset.name).into(),
+            ));
+        }
+        status if status.is_success() => bytes = 0,
+        status => {
+            return Err(CommandError::Download(
+                format!("github responded with {} for {}", status, asset.name).into(),
+            ))
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(bytes > 0)
+        .truncate(bytes == 0)
+        .open(partial)
+        .await
+        .context(CommandError::IO, "could not create firmware file")?;
+    progress(DownloadProgress {
+        bytes,
+        total: asset.size,
+    });
+
+    let mut buffer = vec![0; DOWNLOAD_CHUNK_SIZE];
+    loop {
+        let read = response
+            .read(&mut buffer)
+            .await
+            .context(CommandError::Download, "download was interrupted")?;
+        if read == 0 {
+            break;
+        }
+        bytes += read as u64;
+        // github tells us the size, so a download that runs over is caught here
+        if bytes > asset.size {
+            drop(file);
+            let _ = fs::remove_file(partial);
+            return Err(CommandError::Checksum(Detail::new(format!(
+                "downloaded more than the expected {} bytes of {}",
+                asset.size, asset.name
+            ))));
+        }
+        file.write_all(&buffer[..read])
+            .await
+            .context(CommandError::IO, "could not save firmware")?;
+        progress(DownloadProgress {
+            bytes,
+            total: asset.size,
+        });
+    }
+    file.flush()
+        .await
+        .context(CommandError::IO, "could not save firmware")?;
+
+    // a download that stops short is kept, to be resumed
+    if bytes < asset.size {
+        return Err(CommandError::Download(Detail::new(format!(
+            "download stopped after {} of {} bytes",
+            bytes, asset.size
+        ))));
+    }
+    Ok(())
+}
+
+/// ask for an asset, from `offset` onwards
+async fn request_asset(asset: &Asset, offset: u64) -> Result<surf::Response, CommandError> {
+    // draft assets can only be downloaded through the api, which redirects to a signed url
+    let token = github_token();
+    let url = match token {
+        Some(_) => &asset.url,
+        None => &asset.browser_download_url,
+    };
+    get(url, token, offset).await.map_err(|err| {
+        CommandError::Download(
+            Detail::new(format!("could not reach {}", asset.browser_download_url)).because(err),
+        )
+    })
+}
+
+/// get a url from `offset` onwards, following redirects by hand - the token is only sent to
+/// the first host, and each request is only made once, unlike with surf's redirect middleware
+async fn get(url: &str, mut token: Option<String>, offset: u64) -> surf::Result<surf::Response> {
+    let mut url = surf::Url::parse(url)?;
+    for _ in 0..MAX_REDIRECTS {
+        let mut request = surf::get(url.clone());
+        if let Some(token) = token.take() {
+            request = request
+                .header("Accept", "application/octet-stream")
+                .header("Authorization", format!("Bearer {}", token));
+        }
+        if offset > 0 {
+            request = request.header("Range", format!("bytes={}-", offset));
+        }
+
+        let mut response = request.await?;
+        match response.header("Location") {
+            Some(location) if response.status().is_redirection() => {
+                url = url.join(location.as_str())?;
+                // finish with the redirect, so its connection can be reused
+                response.body_bytes().await?;
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err(surf::Error::from_str(
+        StatusCode::LoopDetected,
+        format!("more than {} redirects", MAX_REDIRECTS),
+    ))
+}
+
+/// save the signature published alongside an asset, if there is one, next to its download.
+/// a missing signature just leaves the download unsigned - whether that's allowed is up to the
+/// [`super::signature::Verifier`]. drafts can't be fetched this way, so they're always unsigned.
+async fn fetch_signature(asset: &Asset, firmware: &Path) {
+    let url = format!("{}.{}", asset.browser_download_url, SIGNATURE_EXTENSION);
+    let mut response = match get(&url, None, 0).await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            info!("no signature for {}: {}", asset.name, response.status());
+            return;
+        }
+        Err(err) => {
+            warn!("could not fetch the signature for {}: {}", asset.name, err);
+            return;
+        }
+    };
+
+    let path = signature_path(firmware);
+    match response.body_bytes().await {
+        Ok(body) => match fs::write(&path, body) {
+            Ok(()) => info!("saved signature to: {}", path.display()),
+            Err(err) => warn!("could not save {}: {}", path.display(), err),
+        },
+        Err(err) => warn!("could not fetch the signature for {}: {}", asset.name, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{
+        channel::Channel,
+        fixtures::{asset, release},
+    };
+
+    #[test]
+    fn changelog_spans_installed_to_selected() {
+        let mut releases = vec![
+            release(2, "v1.2.0"),
+            release(5, "v1.3.0-beta.1"),
+            release(4, "v1.3.0"),
+            release(1, "v1.1.0"),
+            release(3, "v1.2.1"),
+        ];
+        sort_releases(&mut releases);
+        let tags = |selected: &str, installed: &str| -> Vec<String> {
+            let selected = releases.iter().find(|r| r.tag_name == selected).unwrap();
+            changelog(&releases, installed, selected)
+                .into_iter()
+                .map(|release| release.tag_name.clone())
+                .collect()
+        };
+
+        assert_eq!(tags("v1.3.0", "1.2.0"), vec!["v1.3.0", "v1.2.1"]);
+        assert_eq!(tags("v1.3.0", "1.2.0.1"), vec!["v1.3.0", "v1.2.1"]);
+        assert_eq!(
+            tags("v1.3.0-beta.1", "1.2.0"),
+            vec!["v1.3.0-beta.1", "v1.2.1"]
+        );
+        assert_eq!(tags("v1.1.0", "1.2.0"), vec!["v1.1.0"]);
+        assert_eq!(tags("v1.2.1", "garbage"), vec!["v1.2.1"]);
+
+        assert_eq!(latest_stable(&releases).unwrap().tag_name, "v1.3.0");
+        assert_eq!(
+            latest_prerelease(&releases).unwrap().tag_name,
+            "v1.3.0-beta.1"
+        );
+
+        assert!(is_downgrade("1.2.0", &releases[4]));
+        assert!(!is_downgrade("1.2.0", &releases[0]));
+        assert!(!is_downgrade("1.2.0.1", &releases[3]));
+    }
+
+    #[test]
+    fn update_for_needs_firmware_for_the_device() {
+        let update = |releases: &[Release], installed: &str, channels: &Channels| {
+            update_for(releases, channels, "bridge6", '1', installed)
+                .unwrap()
+                .map(|release| release.tag_name.clone())
+        };
+        let bare = vec![release(2, "v1.2.1"), release(1, "v1.2.0")];
+        assert_eq!(update(&bare, "1.2.0", &Channels::default()), None);
+        // nothing is newer than a version that can't be read, but it isn't up to date either
+        assert!(update_for(&bare, &Channels::default(), "bridge6", '1', "unknown").is_err());
+
+        // assets carry the hardware revision ahead of any prerelease suffix
+        let releases = vec![
+            Release {
+                assets: vec![asset("bridge6_v1.3.0.1-beta.1.bin")],
+                ..release(3, "v1.3.0-beta.1")
+            },
+            Release {
+                assets: vec![asset("bridge6_v1.2.1.1.bin")],
+                ..release(2, "v1.2.1")
+            },
+            Release {
+                assets: vec![asset("bridge6_v1.2.0.1.bin")],
+                ..release(1, "v1.2.0")
+            },
+        ];
+        let beta = Channels::new([Channel::Stable, Channel::Beta]);
+        assert_eq!(
+            update(&releases, "1.2.0", &Channels::default()).as_deref(),
+            Some("v1.2.1")
+        );
+        assert_eq!(update(&releases, "1.2.1", &Channels::default()), None);
+        assert_eq!(
+            update(&releases, "1.2.0", &beta).as_deref(),
+            Some("v1.3.0-beta.1")
+        );
+    }
+}
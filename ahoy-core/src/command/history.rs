@@ -0,0 +1,265 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use pirate_midi_rs::check::CheckResponse;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{data_dir, policy::file_version, version::Version, CommandError, Context, Detail};
+use crate::i18n::t;
+
+const JOURNAL_FILE: &str = "history.jsonl";
+
+/// where the firmware came from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Source {
+    Release { tag: String },
+    File { name: String, sha256: String },
+}
+
+impl Source {
+    /// identify a local file by name and content hash
+    pub fn from_file(path: &Path) -> Result<Source, CommandError> {
+        Ok(Source::File {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            sha256: sha256(path)?,
+        })
+    }
+
+    /// the version a device should report once it runs this firmware, when it's known
+    pub fn version(&self) -> Option<Version> {
+        match self {
+            Source::Release { tag } => tag.parse().ok(),
+            Source::File { name, .. } => file_version(name),
+        }
+    }
+}
+
+/// a firmware file's sha256, as lowercase hex
+pub fn sha256(path: &Path) -> Result<String, CommandError> {
+    let mut file = File::open(path).context(CommandError::IO, "could not open firmware file")?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .context(CommandError::IO, "could not read firmware file")?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failed,
+    Cancelled,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "{}", t!("history-success")),
+            Outcome::Failed => write!(f, "{}", t!("history-failed")),
+            Outcome::Cancelled => write!(f, "{}", t!("history-cancelled")),
+        }
+    }
+}
+
+/// a single install attempt
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub timestamp: u64,
+    pub client: String,
+    pub device_uid: Option<String>,
+    pub model: Option<String>,
+    pub hardware_version: Option<String>,
+    pub previous_version: Option<String>,
+    pub new_version: Option<String>,
+    pub source: Source,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(client: &str, source: Source) -> JournalEntry {
+        let new_version = match &source {
+            Source::Release { tag } => Some(tag.clone()),
+            Source::File { .. } => None,
+        };
+
+        JournalEntry {
+            timestamp: 0,
+            client: client.to_string(),
+            device_uid: None,
+            model: None,
+            hardware_version: None,
+            previous_version: None,
+            new_version,
+            source,
+            outcome: Outcome::Failed,
+            error: None,
+        }
+    }
+
+    /// fill in what the device told us about itself before the install
+    pub fn with_device(mut self, details: &CheckResponse) -> JournalEntry {
+        self.device_uid = Some(details.uid.clone());
+        self.model = Some(details.device_model.trim().to_lowercase());
+        self.hardware_version = Some(details.hardware_version.clone());
+        self.previous_version = Some(details.firmware_version.clone());
+        self
+    }
+
+    /// devices in DFU mode can't tell us anything, so use what the user selected
+    pub fn with_model(mut self, model: &str, revision: char) -> JournalEntry {
+        self.model = Some(model.to_string());
+        self.hardware_version = Some(revision.to_string());
+        self
+    }
+
+    pub fn finish(mut self, result: &Result<(), CommandError>) -> JournalEntry {
+        self.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        (self.outcome, self.error) = match result {
+            Ok(_) => (Outcome::Success, None),
+            Err(CommandError::Cancelled) => (Outcome::Cancelled, None),
+            Err(err) => (Outcome::Failed, Some(err.report().to_string())),
+        };
+        self
+    }
+}
+
+/// render a unix timestamp as a UTC date and time, without pulling in a date crate
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // civil from days - http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+pub fn journal_path() -> Result<PathBuf, CommandError> {
+    Ok(data_dir()?.join(JOURNAL_FILE))
+}
+
+/// append an attempt to the journal
+pub fn record(entry: &JournalEntry) -> Result<(), CommandError> {
+    let path = journal_path()?;
+    info!("recording install attempt to: {}", path.display());
+
+    let line = serde_json::to_string(entry)
+        .context(CommandError::IO, "could not serialize journal entry")?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context(CommandError::IO, "could not open install journal")?;
+    writeln!(file, "{}", line).context(CommandError::IO, "could not write install journal")
+}
+
+/// read the journal, optionally for a single device - newest first
+pub fn read(device_uid: Option<&str>) -> Result<Vec<JournalEntry>, CommandError> {
+    let path = journal_path()?;
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => {
+            return Err(CommandError::IO(
+                Detail::new("could not open install journal").because(err),
+            ))
+        }
+    };
+
+    let mut entries: Vec<JournalEntry> = BufReader::new(file)
+        .lines()
+        .flatten()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("skipping unreadable journal entry: {}", err);
+                None
+            }
+        })
+        .filter(|entry| match device_uid {
+            Some(uid) => entry.device_uid.as_deref() == Some(uid),
+            None => true,
+        })
+        .collect();
+
+    entries.reverse();
+    Ok(entries)
+}
+
+/// record an attempt, but never let the journal get in the way of an install
+pub fn try_record(entry: &JournalEntry) {
+    if let Err(err) = record(entry) {
+        warn!("unable to record install attempt: {}", err.report());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::CommandError;
+
+    use super::{format_timestamp, JournalEntry, Outcome, Source};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_674_000_000), "2023-01-18 00:00:00 UTC");
+    }
+
+    #[test]
+    fn test_entry_outcome() {
+        let source = Source::Release {
+            tag: "v1.2.0.1".to_string(),
+        };
+        let entry = JournalEntry::new("cli", source.clone()).finish(&Ok(()));
+        assert_eq!(entry.outcome, Outcome::Success);
+        assert_eq!(entry.new_version.as_deref(), Some("v1.2.0.1"));
+
+        let entry = JournalEntry::new("cli", source).finish(&Err(CommandError::Cancelled));
+        assert_eq!(entry.outcome, Outcome::Cancelled);
+        assert!(entry.error.is_none());
+    }
+}
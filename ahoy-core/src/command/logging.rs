@@ -0,0 +1,271 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use super::{data_dir, history::format_timestamp, CommandError, Context};
+
+const LOG_DIR: &str = "logs";
+const LOG_FILE: &str = "ahoy.log";
+/// lines kept in memory for the log panel
+const BUFFER_LINES: usize = 1_000;
+/// the log file is rotated once it grows past this many bytes
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// how many rotated files are kept, as ahoy.1.log (newest) to ahoy.3.log (oldest)
+const ROTATED_FILES: usize = 3;
+
+/// the crates we log for, besides the app and this one
+const MODULES: [&str; 5] = ["pirate_midi_rs", "dfu_libusb", "dfu_core", "rusb", "surf"];
+
+lazy_static! {
+    static ref BUFFER: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::with_capacity(BUFFER_LINES));
+    static ref LOG: Mutex<Option<LogFile>> = Mutex::new(None);
+}
+
+/// a captured log record
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub timestamp: u64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}: {}",
+            format_timestamp(self.timestamp),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// the current log file, rotated when it gets too big
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { path, file, size })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size >= MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// ahoy.log becomes ahoy.1.log, ahoy.2.log, ...
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("{}.log", index))
+}
+
+/// keep the newest lines, dropping the oldest once the buffer is full
+fn push(buffer: &mut VecDeque<LogLine>, line: LogLine, capacity: usize) {
+    while buffer.len() >= capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(line);
+}
+
+/// whether a record comes from one of our modules
+fn captured(modules: &[&str], target: &str) -> bool {
+    modules.iter().any(|module| {
+        target == *module
+            || matches!(target.strip_prefix(module), Some(rest) if rest.starts_with("::"))
+    })
+}
+
+/// writes to stderr as before, and keeps a copy of everything in memory and on disk.
+/// the copy is always kept at debug level, since GUI users on windows never see stderr.
+struct Logger {
+    stderr: stderrlog::StdErrLog,
+    capture: LevelFilter,
+    /// the app, this crate, and the crates we talk to the device with
+    modules: Vec<&'static str>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata)
+            || (metadata.level() <= self.capture && captured(&self.modules, metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        self.stderr.log(record);
+
+        if record.level() > self.capture || !captured(&self.modules, record.target()) {
+            return;
+        }
+
+        let line = LogLine {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        // logging must never bring the app down, so a poisoned lock just skips the line
+        if let Ok(mut log) = LOG.lock() {
+            if let Some(file) = log.as_mut() {
+                if let Err(err) = file.write(&line.to_string()) {
+                    eprintln!("unable to write log file: {}", err);
+                    *log = None;
+                }
+            }
+        }
+        if let Ok(mut buffer) = BUFFER.lock() {
+            push(&mut buffer, line, BUFFER_LINES);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Ok(mut log) = LOG.lock() {
+            if let Some(file) = log.as_mut() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+pub fn log_dir() -> Result<PathBuf, CommandError> {
+    let dir = data_dir()?.join(LOG_DIR);
+    fs::create_dir_all(&dir).context(
+        CommandError::IO,
+        format!("could not create {}", dir.display()),
+    )?;
+    Ok(dir)
+}
+
+/// the log files that exist right now, oldest first
+pub fn log_files() -> Result<Vec<PathBuf>, CommandError> {
+    let current = log_dir()?.join(LOG_FILE);
+    let mut files: Vec<PathBuf> = (1..=ROTATED_FILES)
+        .rev()
+        .map(|index| rotated_path(&current, index))
+        .collect();
+    files.push(current);
+    Ok(files.into_iter().filter(|path| path.exists()).collect())
+}
+
+/// the lines captured since startup, oldest first
+pub fn recent() -> Vec<LogLine> {
+    BUFFER
+        .lock()
+        .map(|buffer| buffer.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// set up logging for an app (its crate name) with a verbosity of -v, -vv, etc.
+pub fn init(app: &'static str, verbosity: usize) {
+    let modules: Vec<&'static str> = [app, env!("CARGO_CRATE_NAME")]
+        .into_iter()
+        .chain(MODULES)
+        .collect();
+    let mut stderr = stderrlog::new();
+    stderr
+        .modules(modules.iter().copied())
+        .verbosity(verbosity)
+        .timestamp(stderrlog::Timestamp::Second);
+
+    let capture = LevelFilter::Debug;
+    let level = match verbosity {
+        0 => LevelFilter::Error,
+        1 => LevelFilter::Warn,
+        2 => LevelFilter::Info,
+        3 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let file = log_dir().and_then(|dir| {
+        LogFile::open(dir.join(LOG_FILE)).context(CommandError::IO, "could not open log file")
+    });
+    let file_error = match file {
+        Ok(file) => {
+            *LOG.lock().unwrap() = Some(file);
+            None
+        }
+        Err(err) => Some(err),
+    };
+
+    log::set_boxed_logger(Box::new(Logger {
+        stderr,
+        capture,
+        modules,
+    }))
+    .unwrap();
+    log::set_max_level(level.max(capture));
+
+    if let Some(err) = file_error {
+        log::warn!("logging to memory only: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(message: &str) -> LogLine {
+        LogLine {
+            timestamp: 0,
+            level: Level::Info,
+            target: "ahoy".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn buffer_keeps_newest_lines() {
+        let mut buffer = VecDeque::new();
+        for message in ["one", "two", "three"] {
+            push(&mut buffer, line(message), 2);
+        }
+        let messages: Vec<&str> = buffer.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["two", "three"]);
+
+        assert_eq!(
+            rotated_path(Path::new("logs/ahoy.log"), 2),
+            Path::new("logs/ahoy.2.log")
+        );
+        assert!(captured(&MODULES, "rusb::context"));
+        assert!(!captured(&MODULES, "rusbx"));
+    }
+}
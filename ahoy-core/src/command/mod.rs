@@ -0,0 +1,252 @@
+pub mod channel;
+pub mod device;
+pub mod diagnostics;
+pub mod doctor;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod github;
+pub mod history;
+pub mod logging;
+pub mod policy;
+pub mod session;
+pub mod settings;
+pub mod setup;
+pub mod signature;
+pub mod simulate;
+pub mod station;
+pub mod transfer;
+pub mod version;
+
+use std::{error::Error, fmt, path::PathBuf, sync::Arc};
+
+use crate::i18n::t;
+
+/// where ahoy keeps its own files - created if it doesn't exist yet
+pub fn data_dir() -> Result<PathBuf, CommandError> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| CommandError::IO("unable to determine data directory".into()))?
+        .join("ahoy");
+    std::fs::create_dir_all(&dir).context(
+        CommandError::IO,
+        format!("could not create {}", dir.display()),
+    )?;
+    Ok(dir)
+}
+
+/// the underlying error, shared so a `CommandError` can still be cloned into GUI messages
+#[derive(Debug, Clone)]
+pub struct Cause(Arc<dyn Error + Send + Sync>);
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for Cause {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// what went wrong, and optionally the error that caused it.
+/// the cause is its `source`, and only printed as part of a `Report`.
+#[derive(Debug, Clone)]
+pub struct Detail {
+    message: String,
+    cause: Option<Cause>,
+}
+
+impl Detail {
+    pub fn new(message: impl Into<String>) -> Detail {
+        Detail {
+            message: message.into(),
+            cause: None,
+        }
+    }
+
+    pub fn because(mut self, cause: impl Into<Box<dyn Error + Send + Sync>>) -> Detail {
+        self.cause = Some(Cause(Arc::from(cause.into())));
+        self
+    }
+}
+
+impl fmt::Display for Detail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for Detail {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause
+            .as_ref()
+            .map(|cause| cause as &(dyn Error + 'static))
+    }
+}
+
+// causes can't be compared, so their messages stand in for them
+impl PartialEq for Detail {
+    fn eq(&self, other: &Detail) -> bool {
+        Report(self).to_string() == Report(other).to_string()
+    }
+}
+
+/// an error followed by every cause under it, e.g. `unable to read or write a file: could not
+/// open firmware file: no such file`. an error's own `Display` only says what went wrong at
+/// its level, so this is how errors are shown to people.
+pub struct Report<'a>(pub &'a (dyn Error + 'static));
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(cause) = source {
+            write!(f, ": {}", cause)?;
+            source = cause.source();
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for Detail {
+    fn from(message: String) -> Detail {
+        Detail::new(message)
+    }
+}
+
+impl From<&str> for Detail {
+    fn from(message: &str) -> Detail {
+        Detail::new(message)
+    }
+}
+
+/// every way a command can fail, grouped by what the user can do about it
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum CommandError {
+    #[error("{}", t!("error-no-device"))]
+    NoDevice(#[source] Detail),
+    #[error("{}", t!("error-permission"))]
+    Permission(#[source] Detail),
+    #[error("{}", t!("error-device"))]
+    Device(#[source] Detail),
+    #[error("{}", t!("error-retrieval"))]
+    Retrieval(#[source] Detail),
+    #[error("{}", t!("error-download"))]
+    Download(#[source] Detail),
+    #[error("{}", t!("error-checksum"))]
+    Checksum(#[source] Detail),
+    #[error("{}", t!("error-signature"))]
+    Signature(#[source] Detail),
+    #[error("{}", t!("error-dfu"))]
+    Dfu(#[source] Detail),
+    #[error("{}", t!("error-verification"))]
+    Verification(#[source] Detail),
+    #[error("{}", t!("error-cancelled"))]
+    Cancelled,
+    #[error("{}", t!("error-timeout"))]
+    Timeout(#[source] Detail),
+    #[error("{}", t!("error-io"))]
+    IO(#[source] Detail),
+    #[error("{}", t!("error-update"))]
+    Update(#[source] Detail),
+    #[error("{}", t!("error-setup"))]
+    Setup(#[source] Detail),
+    #[error("{}", t!("error-policy"))]
+    Policy(#[source] Detail),
+}
+
+impl CommandError {
+    /// the error with everything that caused it, for showing to people
+    pub fn report(&self) -> Report<'_> {
+        Report(self)
+    }
+
+    /// the process exit code for this kind of failure.
+    /// scripts depend on these, so they must never be renumbered - see the README.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommandError::NoDevice(_) => 10,
+            CommandError::Permission(_) => 11,
+            CommandError::Device(_) => 12,
+            CommandError::Retrieval(_) => 20,
+            CommandError::Download(_) => 21,
+            CommandError::Checksum(_) => 22,
+            CommandError::Signature(_) => 23,
+            CommandError::Dfu(_) => 30,
+            CommandError::Verification(_) => 31,
+            CommandError::Cancelled => 32,
+            CommandError::Timeout(_) => 33,
+            CommandError::IO(_) => 40,
+            CommandError::Update(_) => 50,
+            CommandError::Setup(_) => 51,
+            CommandError::Policy(_) => 60,
+        }
+    }
+
+    /// what the user can do about it
+    pub fn hint(&self) -> String {
+        match self {
+            CommandError::NoDevice(_) => t!("hint-no-device"),
+            CommandError::Permission(_) => t!("hint-permission"),
+            CommandError::Device(_) => t!("hint-device"),
+            CommandError::Retrieval(_) => t!("hint-retrieval"),
+            CommandError::Download(_) => t!("hint-download"),
+            CommandError::Checksum(_) => t!("hint-checksum"),
+            CommandError::Signature(_) => t!("hint-signature"),
+            CommandError::Dfu(_) => t!("hint-dfu"),
+            CommandError::Verification(_) => t!("hint-verification"),
+            CommandError::Cancelled | CommandError::Timeout(_) => t!("hint-bootloader"),
+            CommandError::IO(_) => t!("hint-io"),
+            CommandError::Update(_) => t!("hint-update"),
+            CommandError::Setup(_) => t!("hint-setup"),
+            CommandError::Policy(_) => t!("hint-policy"),
+        }
+    }
+}
+
+/// attach a message and a kind of failure to any error, e.g.
+/// `fs::write(..).context(CommandError::IO, "could not write settings")?`
+pub trait Context<T> {
+    fn context(
+        self,
+        kind: fn(Detail) -> CommandError,
+        message: impl Into<String>,
+    ) -> Result<T, CommandError>;
+}
+
+impl<T, E: Into<Box<dyn Error + Send + Sync>>> Context<T> for Result<T, E> {
+    fn context(
+        self,
+        kind: fn(Detail) -> CommandError,
+        message: impl Into<String>,
+    ) -> Result<T, CommandError> {
+        self.map_err(|err| kind(Detail::new(message).because(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn causes_are_chained() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = Err::<(), _>(io)
+            .context(CommandError::IO, "could not open firmware file")
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "unable to read or write a file");
+        assert_eq!(
+            err.report().to_string(),
+            "unable to read or write a file: could not open firmware file: no such file"
+        );
+        let detail = err.source().expect("detail");
+        assert_eq!(detail.source().expect("cause").to_string(), "no such file");
+        assert_eq!(err.exit_code(), 40);
+        assert_eq!(
+            CommandError::IO("could not open firmware file: no such file".into()),
+            err
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use log::info;
+use pirate_midi_rs::check::CheckResponse;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    github::Release, history::sha256, settings, version::Version, CommandError, Context, Detail,
+};
+
+/// the firmware a managed fleet may run, e.g.
+///
+/// ```json
+/// {
+///   "allowed_versions": { "bridge6": ["1.3.0"], "bridge4/2": ["1.2.1", "1.3.0"] },
+///   "minimum_version": "1.2.0",
+///   "forbid_prereleases": true,
+///   "forbid_downgrades": true,
+///   "allowed_files": ["<sha256 of an approved firmware file>"]
+/// }
+/// ```
+///
+/// every field is optional - an empty policy allows everything, but an unknown one is an error.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// the only versions allowed, by model (`bridge6`) or model and hardware revision (`bridge6/1`).
+    /// a revision's list takes precedence over its model's, and devices without one allow any version.
+    pub allowed_versions: BTreeMap<String, Vec<String>>,
+    /// nothing older than this may be installed
+    pub minimum_version: Option<String>,
+    pub forbid_prereleases: bool,
+    /// nothing older than what the device is already running may be installed
+    pub forbid_downgrades: bool,
+    /// the sha256 of each local file that may be installed. a file's version only comes from
+    /// its name, so under any other restriction a file that isn't listed here is refused
+    pub allowed_files: Vec<String>,
+}
+
+/// the device firmware is being installed to - as much as is known about it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Target {
+    pub model: Option<String>,
+    pub revision: Option<char>,
+    /// the firmware version the device reports
+    pub installed: Option<String>,
+}
+
+impl Target {
+    pub fn device(details: &CheckResponse) -> Target {
+        Target {
+            model: Some(details.device_model.trim().to_lowercase()),
+            revision: details.hardware_version.chars().last(),
+            installed: Some(details.firmware_version.clone()),
+        }
+    }
+
+    /// a device that can't be asked - one stuck in bootloader mode
+    pub fn model(model: &str, revision: char) -> Target {
+        Target {
+            model: Some(model.trim().to_lowercase()),
+            revision: Some(revision),
+            installed: None,
+        }
+    }
+}
+
+/// the policy in force - the given file, or the one named in settings, or none at all.
+/// a policy that's configured but can't be read is an error, never a reason to allow everything.
+pub fn load(file: Option<&Path>) -> Result<Policy, CommandError> {
+    match file.map(Path::to_path_buf).or(settings::load().policy) {
+        Some(path) => Policy::read(&path),
+        None => Ok(Policy::default()),
+    }
+}
+
+impl Policy {
+    pub fn read(path: &Path) -> Result<Policy, CommandError> {
+        info!("loading firmware policy from: {}", path.display());
+        let contents = fs::read_to_string(path).context(
+            CommandError::IO,
+            format!("could not read policy {}", path.display()),
+        )?;
+        let policy: Policy = serde_json::from_str(&contents).map_err(|err| {
+            CommandError::Policy(
+                Detail::new(format!("{} is not a valid policy", path.display())).because(err),
+            )
+        })?;
+
+        // a typo shouldn't quietly allow, or refuse, every version
+        let versions = policy
+            .allowed_versions
+            .values()
+            .flatten()
+            .chain(&policy.minimum_version);
+        for version in versions {
+            version.parse::<Version>().map_err(|err| {
+                CommandError::Policy(format!("{} in {}", err, path.display()).into())
+            })?;
+        }
+        for hash in &policy.allowed_files {
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(CommandError::Policy(
+                    format!("{} in {} is not a sha256", hash, path.display()).into(),
+                ));
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Policy::default()
+    }
+
+    /// whether a release may be installed on the target
+    pub fn check_release(&self, release: &Release, target: &Target) -> Result<(), CommandError> {
+        self.check(
+            &release.tag_name,
+            release.version(),
+            release.is_prerelease(),
+            target,
+        )
+    }
+
+    /// whether a local file may be installed on the target.
+    /// files are identified by their name, the way release assets are named - and since a name
+    /// is easy to change, a restrictive policy also has to list the file's sha256.
+    pub fn check_file(&self, path: &Path, target: &Target) -> Result<(), CommandError> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !self.is_empty() {
+            let hash = sha256(path)?;
+            if !self
+                .allowed_files
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&hash))
+            {
+                return Err(CommandError::Policy(
+                    format!(
+                        "{} is not allowed - its sha256 {} is not in allowed_files",
+                        name, hash
+                    )
+                    .into(),
+                ));
+            }
+        }
+        let version = file_version(&name);
+        let prerelease = matches!(&version, Some(version) if version.is_prerelease());
+        self.check(&name, version, prerelease, target)
+    }
+
+    /// the versions allowed on the target, if they're restricted at all
+    fn allowed_for(&self, target: &Target) -> Option<Result<&Vec<String>, CommandError>> {
+        if self.allowed_versions.is_empty() {
+            return None;
+        }
+        let model = match &target.model {
+            Some(model) => model,
+            None => {
+                return Some(Err(CommandError::Policy(
+                    "the device model is unknown, so its allowed versions can't be checked".into(),
+                )))
+            }
+        };
+        let revision = target.revision.and_then(|revision| {
+            self.allowed_versions
+                .get(&format!("{}/{}", model, revision))
+        });
+        revision
+            .or_else(|| self.allowed_versions.get(model))
+            .map(Ok)
+    }
+
+    fn check(
+        &self,
+        name: &str,
+        version: Option<Version>,
+        prerelease: bool,
+        target: &Target,
+    ) -> Result<(), CommandError> {
+        let refuse = |reason: String| {
+            Err(CommandError::Policy(
+                format!("{} is not allowed - {}", name, reason).into(),
+            ))
+        };
+
+        if self.forbid_prereleases && prerelease {
+            return refuse("prereleases are forbidden".to_string());
+        }
+
+        let allowed = self.allowed_for(target).transpose()?;
+        let installed = target
+            .installed
+            .as_ref()
+            .and_then(|installed| installed.parse::<Version>().ok())
+            .filter(|_| self.forbid_downgrades);
+        let version = match version {
+            Some(version) => version,
+            None if allowed.is_some() || self.minimum_version.is_some() || installed.is_some() => {
+                return refuse("its version can't be identified".to_string())
+            }
+            None => return Ok(()),
+        };
+
+        // versions are compared loosely, since devices report a fourth part that tags leave out
+        let same =
+            |other: &Version| version.matches_installed(other) || other.matches_installed(&version);
+        let older = |other: &Version| version < *other && !same(other);
+
+        if let Some(minimum) = self
+            .minimum_version
+            .as_ref()
+            .and_then(|minimum| minimum.parse::<Version>().ok())
+        {
+            if older(&minimum) {
+                return refuse(format!("the minimum version is {}", minimum));
+            }
+        }
+        if let Some(allowed) = allowed {
+            let listed = allowed
+                .iter()
+                .filter_map(|allowed| allowed.parse::<Version>().ok())
+                .any(|allowed| same(&allowed));
+            if !listed {
+                return refuse(format!("only {} may be installed", allowed.join(", ")));
+            }
+        }
+        if let Some(installed) = installed {
+            if older(&installed) {
+                return refuse(format!("downgrades from {} are forbidden", installed));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the firmware version in a file named like a release asset - `bridge6_v1.2.0.1.bin`.
+/// the last part before any prerelease suffix is the hardware revision, not part of the version.
+pub fn file_version(name: &str) -> Option<Version> {
+    let regex = Regex::new(r"^[a-z0-9]+_v(\d+\.\d+\.\d+)\.\d+(-[^.]+(?:\.\d+)*)?\.bin$")
+        .expect("unable to parse regex pattern");
+    let name = name.to_lowercase();
+    let captures = regex.captures(&name)?;
+    let version = format!(
+        "{}{}",
+        captures.get(1)?.as_str(),
+        captures.get(2).map(|pre| pre.as_str()).unwrap_or_default()
+    );
+    version.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::simulate;
+
+    fn policy(json: &str) -> Policy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn allowed(policy: &Policy, tag: &str, target: &Target) -> bool {
+        let release = simulate::releases()
+            .into_iter()
+            .find(|release| release.tag_name == tag)
+            .unwrap();
+        policy.check_release(&release, target).is_ok()
+    }
+
+    #[test]
+    fn releases_are_checked_against_the_device() {
+        // the simulated releases are v1.4.0-beta.1, v1.3.0 and v1.2.0
+        let device = Target::device(&simulate::device_details());
+        assert_eq!(device.model.as_deref(), Some("bridge6"));
+
+        let empty = Policy::default();
+        assert!(empty.is_empty());
+        assert!(allowed(&empty, "v1.2.0", &Target::default()));
+
+        let pinned =
+            policy(r#"{"allowed_versions": {"bridge6": ["1.3.0"], "bridge6/2": ["1.2.0"]}}"#);
+        assert!(allowed(&pinned, "v1.3.0", &device));
+        assert!(!allowed(&pinned, "v1.2.0", &device));
+        assert!(allowed(&pinned, "v1.2.0", &Target::model("Bridge6", '2')));
+        // models without a list are unrestricted, and unknown models can't be checked
+        assert!(allowed(&pinned, "v1.2.0", &Target::model("bridge4", '1')));
+        assert!(!allowed(&pinned, "v1.3.0", &Target::default()));
+
+        let strict = policy(
+            r#"{"minimum_version": "1.3.0", "forbid_prereleases": true, "forbid_downgrades": true}"#,
+        );
+        assert!(allowed(&strict, "v1.3.0", &device));
+        assert!(!allowed(&strict, "v1.2.0", &Target::model("bridge6", '1')));
+        assert!(!allowed(&strict, "v1.4.0-beta.1", &device));
+
+        // the simulated device runs 1.2.0.1, which is 1.2.0 - reinstalling it isn't a downgrade
+        let downgrades = policy(r#"{"forbid_downgrades": true}"#);
+        assert!(allowed(&downgrades, "v1.2.0", &device));
+        let device = Target {
+            installed: Some("1.3.0.1".to_string()),
+            ..device
+        };
+        assert!(!allowed(&downgrades, "v1.2.0", &device));
+        assert!(allowed(
+            &downgrades,
+            "v1.2.0",
+            &Target::model("bridge6", '1')
+        ));
+    }
+
+    #[test]
+    fn misspelled_keys_are_rejected() {
+        let path = std::env::temp_dir().join("ahoy-policy-typo.json");
+        fs::write(&path, r#"{"forbid_downgrade": true}"#).unwrap();
+        assert!(matches!(Policy::read(&path), Err(CommandError::Policy(_))));
+
+        fs::write(&path, r#"{"forbid_downgrades": true}"#).unwrap();
+        assert!(Policy::read(&path).unwrap().forbid_downgrades);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn files_are_identified_by_name_and_hash() {
+        assert_eq!(
+            file_version("bridge6_v1.2.0.1.bin").map(|version| version.to_string()),
+            Some("1.2.0".to_string())
+        );
+        assert_eq!(
+            file_version("Bridge4_v1.3.0.2-beta.1.bin").map(|version| version.to_string()),
+            Some("1.3.0-beta.1".to_string())
+        );
+        assert!(file_version("firmware.bin").is_none());
+
+        let dir = std::env::temp_dir().join("ahoy-policy-files");
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, contents: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+        let approved = file("bridge6_v1.3.0.1.bin", b"approved firmware");
+        let renamed = file("bridge6_v1.3.0.2.bin", b"some other firmware");
+        let unnamed = file("firmware.bin", b"approved firmware");
+
+        let target = Target::model("bridge6", '1');
+        let pinned = policy(&format!(
+            r#"{{"allowed_versions": {{"bridge6": ["1.3.0"]}}, "allowed_files": ["{}"]}}"#,
+            sha256(&approved).unwrap()
+        ));
+        assert!(pinned.check_file(&approved, &target).is_ok());
+        // the right name isn't enough, and neither is the right content without one
+        for path in [&renamed, &unnamed] {
+            assert!(matches!(
+                pinned.check_file(path, &target),
+                Err(CommandError::Policy(_))
+            ));
+        }
+        assert!(Policy::default().check_file(&renamed, &target).is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
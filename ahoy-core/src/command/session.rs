@@ -0,0 +1,706 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::mpsc,
+    future::{BoxFuture, Either},
+    stream, FutureExt, StreamExt,
+};
+use log::{info, warn};
+use rusb::{Context, Device};
+
+use super::{
+    device::{check_device, enter_bootloader, install_binary},
+    github::{fetch_asset, Asset, DownloadProgress},
+    signature::{Signer, Verifier},
+    transfer::{CancelToken, ProgressEvent, Timeouts},
+    version::Version,
+    CommandError, Detail,
+};
+
+/// how long a device takes to come back up in bootloader mode
+const BOOTLOADER_DELAY: Duration = Duration::from_secs(3);
+
+/// how long a device gets to restart with its new firmware and answer on its serial port
+const RESTART_TIMEOUT: Duration = Duration::from_secs(20);
+const RESTART_POLL: Duration = Duration::from_secs(1);
+
+/// where an install is up to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Stage {
+    #[default]
+    Idle,
+    Downloading,
+    /// the firmware is ready - `requested` once the device has been told to restart into DFU mode
+    AwaitingBootloader {
+        requested: bool,
+    },
+    Flashing,
+    /// the device is manifesting the new firmware, and restarts once it's done
+    Rebooting,
+    /// the device restarted - checking it runs the firmware that was installed
+    Verifying,
+    Done,
+    Failed(CommandError),
+}
+
+/// what to install - a release asset that still has to be fetched, or a file on disk
+#[derive(Debug, Clone)]
+pub enum Firmware {
+    Asset(Box<Asset>),
+    File(PathBuf),
+}
+
+/// something that happened, reported by whoever drives the session
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// `in_bootloader` when the device is already in DFU mode, like during a recovery.
+    /// `version` is what the device should report once it restarts, when it's known.
+    Start {
+        firmware: Firmware,
+        in_bootloader: bool,
+        version: Option<Version>,
+    },
+    DownloadProgress(DownloadProgress),
+    Downloaded(Result<PathBuf, CommandError>),
+    /// the go-ahead to restart the device into its bootloader
+    Confirmed,
+    BootloaderRequested(Result<(), CommandError>),
+    /// a device in DFU mode showed up
+    BootloaderReady(Result<(), CommandError>),
+    Progress(ProgressEvent),
+    Flashed(Result<(), CommandError>),
+    /// the restarted device reported the firmware it runs, and it's the right one
+    Verified(Result<(), CommandError>),
+}
+
+/// what the driver has to do next
+#[derive(Debug, Clone)]
+pub enum Step {
+    Download(Box<Asset>),
+    /// check with the user before restarting their device
+    Confirm(PathBuf),
+    EnterBootloader,
+    WaitForBootloader,
+    Flash(PathBuf),
+    /// wait for the device to restart, and check it runs this version
+    Verify(Option<Version>),
+    /// nothing to do until the next event
+    Wait,
+    Finished(Result<(), CommandError>),
+}
+
+/// a single install, from fetching the firmware to the device restarting with it.
+/// it doesn't touch the device or network itself, so the GUI and CLI can drive it their own way.
+#[derive(Debug, Default)]
+pub struct InstallSession {
+    stage: Stage,
+    in_bootloader: bool,
+    version: Option<Version>,
+    firmware: Option<PathBuf>,
+    verifier: Verifier,
+    signer: Option<Signer>,
+}
+
+impl InstallSession {
+    pub fn new() -> InstallSession {
+        InstallSession::default()
+    }
+
+    /// check every firmware file's signature before it's installed
+    pub fn verifying(mut self, verifier: Verifier) -> InstallSession {
+        self.verifier = verifier;
+        self
+    }
+
+    pub fn stage(&self) -> &Stage {
+        &self.stage
+    }
+
+    /// what the device should report once it restarts, when it's known
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    /// the downloaded or staged firmware file
+    pub fn firmware(&self) -> Option<&Path> {
+        self.firmware.as_deref()
+    }
+
+    /// who signed the firmware, once it's ready
+    pub fn signer(&self) -> Option<&Signer> {
+        self.signer.as_ref()
+    }
+
+    /// start over, handing back the firmware file so it can be cleaned up
+    pub fn reset(&mut self) -> Option<PathBuf> {
+        self.stage = Stage::Idle;
+        self.in_bootloader = false;
+        self.version = None;
+        self.signer = None;
+        self.firmware.take()
+    }
+
+    /// hand back the firmware file once it's no longer needed, keeping the outcome
+    pub fn take_firmware(&mut self) -> Option<PathBuf> {
+        self.firmware.take()
+    }
+
+    pub fn handle(&mut self, event: Event) -> Step {
+        match (&self.stage, event) {
+            (
+                Stage::Idle | Stage::Done | Stage::Failed(_),
+                Event::Start {
+                    firmware,
+                    in_bootloader,
+                    version,
+                },
+            ) => {
+                self.in_bootloader = in_bootloader;
+                self.version = version;
+                self.firmware = None;
+                self.signer = None;
+                match firmware {
+                    Firmware::Asset(asset) => {
+                        self.stage = Stage::Downloading;
+                        Step::Download(asset)
+                    }
+                    Firmware::File(path) => self.ready(path),
+                }
+            }
+            (Stage::Downloading, Event::DownloadProgress(_)) => Step::Wait,
+            (Stage::Downloading, Event::Downloaded(Ok(path))) => self.ready(path),
+            (Stage::AwaitingBootloader { requested: false }, Event::Confirmed) => {
+                self.stage = Stage::AwaitingBootloader { requested: true };
+                Step::EnterBootloader
+            }
+            (Stage::AwaitingBootloader { requested: true }, Event::BootloaderRequested(Ok(()))) => {
+                Step::WaitForBootloader
+            }
+            // the device may also have been put in DFU mode by hand
+            (Stage::AwaitingBootloader { .. }, Event::BootloaderReady(Ok(()))) => self.flash(),
+            (Stage::Flashing, Event::Progress(ProgressEvent::Manifesting)) => {
+                self.stage = Stage::Rebooting;
+                Step::Wait
+            }
+            (Stage::Flashing | Stage::Rebooting, Event::Progress(_)) => Step::Wait,
+            (Stage::Flashing | Stage::Rebooting, Event::Flashed(Ok(()))) => {
+                self.stage = Stage::Verifying;
+                Step::Verify(self.version.clone())
+            }
+            (Stage::Verifying, Event::Verified(Ok(()))) => {
+                info!("install complete");
+                self.stage = Stage::Done;
+                Step::Finished(Ok(()))
+            }
+            (Stage::Downloading, Event::Downloaded(Err(err)))
+            | (
+                Stage::AwaitingBootloader { .. },
+                Event::BootloaderRequested(Err(err)) | Event::BootloaderReady(Err(err)),
+            )
+            | (Stage::Flashing | Stage::Rebooting, Event::Flashed(Err(err)))
+            | (Stage::Verifying, Event::Verified(Err(err))) => {
+                warn!("install failed while {:?}: {}", self.stage, err.report());
+                self.stage = Stage::Failed(err.clone());
+                Step::Finished(Err(err))
+            }
+            (stage, event) => {
+                warn!("ignoring {:?} while {:?}", event, stage);
+                Step::Wait
+            }
+        }
+    }
+
+    fn ready(&mut self, firmware: PathBuf) -> Step {
+        info!("firmware ready: {}", firmware.display());
+        self.firmware = Some(firmware.clone());
+        match self.verifier.verify(&firmware) {
+            Ok(signer) => self.signer = Some(signer),
+            Err(err) => {
+                warn!("refusing {}: {}", firmware.display(), err.report());
+                self.stage = Stage::Failed(err.clone());
+                return Step::Finished(Err(err));
+            }
+        }
+        if self.in_bootloader {
+            self.flash()
+        } else {
+            self.stage = Stage::AwaitingBootloader { requested: false };
+            Step::Confirm(firmware)
+        }
+    }
+
+    fn flash(&mut self) -> Step {
+        match self.firmware.clone() {
+            Some(firmware) => {
+                self.stage = Stage::Flashing;
+                Step::Flash(firmware)
+            }
+            None => {
+                let err = CommandError::IO("firmware file went missing".into());
+                self.stage = Stage::Failed(err.clone());
+                Step::Finished(Err(err))
+            }
+        }
+    }
+}
+
+/// whether the firmware version a device reports is the one that was installed.
+/// without a version to compare against, a device that answers at all will do.
+pub fn check_installed(expected: Option<&Version>, reported: &str) -> Result<(), CommandError> {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => {
+            info!("device restarted with firmware {}", reported);
+            return Ok(());
+        }
+    };
+    match reported.parse::<Version>() {
+        Ok(installed) if expected.matches_installed(&installed) => {
+            info!("device restarted with firmware {}", installed);
+            Ok(())
+        }
+        _ => Err(CommandError::Verification(
+            format!(
+                "device reports firmware {} instead of {}",
+                reported, expected
+            )
+            .into(),
+        )),
+    }
+}
+
+/// give up on a device that hasn't come back after its install, for drivers that wait on it themselves
+pub async fn restart_deadline() -> Result<(), CommandError> {
+    async_std::task::sleep(RESTART_TIMEOUT).await;
+    Err(CommandError::Verification(not_restarted()))
+}
+
+fn not_restarted() -> Detail {
+    Detail::new(format!(
+        "device did not come back within {} seconds",
+        RESTART_TIMEOUT.as_secs()
+    ))
+}
+
+/// where firmware comes from
+pub trait ReleaseBackend {
+    fn download(
+        &self,
+        asset: Asset,
+        progress: Box<dyn FnMut(DownloadProgress) + Send>,
+    ) -> BoxFuture<'static, Result<PathBuf, CommandError>>;
+}
+
+/// the device being installed to
+pub trait DeviceBackend {
+    fn enter_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>>;
+    fn wait_for_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>>;
+    fn flash(
+        &self,
+        firmware: PathBuf,
+        progress: Box<dyn FnMut(ProgressEvent) + Send>,
+    ) -> BoxFuture<'static, Result<(), CommandError>>;
+    /// wait for the device to restart, and check it runs `expected`
+    fn verify(&self, expected: Option<Version>) -> BoxFuture<'static, Result<(), CommandError>>;
+}
+
+/// release assets from github
+pub struct Github;
+
+impl ReleaseBackend for Github {
+    fn download(
+        &self,
+        asset: Asset,
+        progress: Box<dyn FnMut(DownloadProgress) + Send>,
+    ) -> BoxFuture<'static, Result<PathBuf, CommandError>> {
+        fetch_asset(asset, progress).boxed()
+    }
+}
+
+/// a pirate midi device - over its serial port, then over usb once it's in DFU mode
+pub struct PirateDevice {
+    pub port: Option<String>,
+    /// the DFU device, when we've seen it connect - otherwise it's found by VID and PID
+    pub raw_device: Option<Device<Context>>,
+    pub token: CancelToken,
+    pub timeouts: Timeouts,
+}
+
+impl DeviceBackend for PirateDevice {
+    fn enter_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+        enter_bootloader(self.port.clone()).boxed()
+    }
+
+    fn wait_for_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+        async {
+            async_std::task::sleep(BOOTLOADER_DELAY).await;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn flash(
+        &self,
+        firmware: PathBuf,
+        progress: Box<dyn FnMut(ProgressEvent) + Send>,
+    ) -> BoxFuture<'static, Result<(), CommandError>> {
+        install_binary(
+            firmware,
+            Some(progress),
+            self.raw_device.clone(),
+            self.token.clone(),
+            self.timeouts,
+        )
+        .boxed()
+    }
+
+    fn verify(&self, expected: Option<Version>) -> BoxFuture<'static, Result<(), CommandError>> {
+        let port = self.port.clone();
+        let token = self.token.clone();
+        async move {
+            let started = Instant::now();
+            // the serial port is gone until the device has restarted, so keep asking
+            loop {
+                async_std::task::sleep(RESTART_POLL).await;
+                if token.is_cancelled() {
+                    return Err(CommandError::Cancelled);
+                }
+                let port = port.clone();
+                match async_std::task::spawn_blocking(move || check_device(port.as_deref())).await {
+                    Ok(details) => {
+                        return check_installed(expected.as_ref(), &details.firmware_version)
+                    }
+                    Err(err) if started.elapsed() >= RESTART_TIMEOUT => {
+                        return Err(CommandError::Verification(not_restarted().because(err)))
+                    }
+                    Err(_) => (),
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// drive a session until the install finishes, with nobody to ask for confirmation.
+/// the observer sees every event, after the session has handled it.
+pub async fn run(
+    session: &mut InstallSession,
+    firmware: Firmware,
+    in_bootloader: bool,
+    version: Option<Version>,
+    releases: &impl ReleaseBackend,
+    device: &impl DeviceBackend,
+    mut observer: impl FnMut(&Event, &InstallSession),
+) -> Result<(), CommandError> {
+    let mut event = Event::Start {
+        firmware,
+        in_bootloader,
+        version,
+    };
+
+    loop {
+        let step = session.handle(event.clone());
+        observer(&event, session);
+
+        event = match step {
+            Step::Download(asset) => Event::Downloaded(
+                with_progress(
+                    session,
+                    &mut observer,
+                    |progress| releases.download(*asset, progress),
+                    Event::DownloadProgress,
+                )
+                .await,
+            ),
+            Step::Confirm(_) => Event::Confirmed,
+            Step::EnterBootloader => Event::BootloaderRequested(device.enter_bootloader().await),
+            Step::WaitForBootloader => Event::BootloaderReady(device.wait_for_bootloader().await),
+            Step::Flash(firmware) => Event::Flashed(
+                with_progress(
+                    session,
+                    &mut observer,
+                    |progress| device.flash(firmware, progress),
+                    Event::Progress,
+                )
+                .await,
+            ),
+            Step::Verify(version) => Event::Verified(device.verify(version).await),
+            Step::Wait => {
+                return Err(CommandError::Dfu(
+                    format!("install stalled while {:?}", session.stage()).into(),
+                ))
+            }
+            Step::Finished(result) => return result,
+        };
+    }
+}
+
+/// run a download or transfer to the end, handing its progress to the session as it arrives
+async fn with_progress<P: Send + 'static, T>(
+    session: &mut InstallSession,
+    observer: &mut impl FnMut(&Event, &InstallSession),
+    start: impl FnOnce(Box<dyn FnMut(P) + Send>) -> BoxFuture<'static, T>,
+    event: fn(P) -> Event,
+) -> T {
+    let (tx, rx) = mpsc::unbounded();
+    let running = start(Box::new(move |progress| {
+        let _ = tx.unbounded_send(progress);
+    }));
+    let mut updates = stream::select(
+        rx.map(Either::Left),
+        stream::once(running).map(Either::Right),
+    );
+
+    let mut result = None;
+    while let Some(update) = updates.next().await {
+        match update {
+            Either::Left(progress) => {
+                let event = event(progress);
+                session.handle(event.clone());
+                observer(&event, session);
+            }
+            Either::Right(finished) => result = Some(finished),
+        }
+    }
+    result.expect("finished without a result")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::fixtures::asset;
+    use std::sync::{Arc, Mutex};
+
+    struct FakeReleases(Result<PathBuf, CommandError>);
+
+    impl ReleaseBackend for FakeReleases {
+        fn download(
+            &self,
+            asset: Asset,
+            mut progress: Box<dyn FnMut(DownloadProgress) + Send>,
+        ) -> BoxFuture<'static, Result<PathBuf, CommandError>> {
+            progress(DownloadProgress {
+                bytes: asset.size,
+                total: asset.size,
+            });
+            futures::future::ready(self.0.clone()).boxed()
+        }
+    }
+
+    /// records what was asked of it, and fails the flash if told to
+    #[derive(Default)]
+    struct FakeDevice {
+        calls: Arc<Mutex<Vec<&'static str>>>,
+        flash_error: Option<CommandError>,
+        /// the firmware it reports after restarting
+        restarts_with: String,
+    }
+
+    impl DeviceBackend for FakeDevice {
+        fn enter_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+            self.calls.lock().unwrap().push("enter_bootloader");
+            futures::future::ready(Ok(())).boxed()
+        }
+
+        fn wait_for_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+            self.calls.lock().unwrap().push("wait_for_bootloader");
+            futures::future::ready(Ok(())).boxed()
+        }
+
+        fn flash(
+            &self,
+            _: PathBuf,
+            mut progress: Box<dyn FnMut(ProgressEvent) + Send>,
+        ) -> BoxFuture<'static, Result<(), CommandError>> {
+            self.calls.lock().unwrap().push("flash");
+            let result = match &self.flash_error {
+                Some(err) => Err(err.clone()),
+                None => {
+                    progress(ProgressEvent::Writing { bytes: 4, total: 4 });
+                    progress(ProgressEvent::Manifesting);
+                    progress(ProgressEvent::Done);
+                    Ok(())
+                }
+            };
+            futures::future::ready(result).boxed()
+        }
+
+        fn verify(
+            &self,
+            expected: Option<Version>,
+        ) -> BoxFuture<'static, Result<(), CommandError>> {
+            self.calls.lock().unwrap().push("verify");
+            futures::future::ready(check_installed(expected.as_ref(), &self.restarts_with)).boxed()
+        }
+    }
+
+    /// run a session to the end, returning the result and every stage it went through
+    fn drive(
+        firmware: Firmware,
+        in_bootloader: bool,
+        version: Option<Version>,
+        releases: FakeReleases,
+        device: &FakeDevice,
+    ) -> (Result<(), CommandError>, Vec<Stage>) {
+        let mut session = InstallSession::new();
+        let mut stages = vec![];
+        let result = async_std::task::block_on(run(
+            &mut session,
+            firmware,
+            in_bootloader,
+            version,
+            &releases,
+            device,
+            |_, session| {
+                if stages.last() != Some(session.stage()) {
+                    stages.push(session.stage().clone());
+                }
+            },
+        ));
+        (result, stages)
+    }
+
+    #[test]
+    fn download_and_install() {
+        let device = FakeDevice {
+            restarts_with: "1.3.0.1".to_string(),
+            ..Default::default()
+        };
+        let (result, stages) = drive(
+            Firmware::Asset(Box::new(asset("bridge6_r1.bin"))),
+            false,
+            "v1.3.0".parse().ok(),
+            FakeReleases(Ok(PathBuf::from("firmware.bin"))),
+            &device,
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stages,
+            [
+                Stage::Downloading,
+                Stage::AwaitingBootloader { requested: false },
+                Stage::AwaitingBootloader { requested: true },
+                Stage::Flashing,
+                Stage::Rebooting,
+                Stage::Verifying,
+                Stage::Done,
+            ]
+        );
+        assert_eq!(
+            *device.calls.lock().unwrap(),
+            ["enter_bootloader", "wait_for_bootloader", "flash", "verify"]
+        );
+    }
+
+    #[test]
+    fn the_wrong_firmware_fails_verification() {
+        let device = FakeDevice {
+            restarts_with: "1.2.0.1".to_string(),
+            ..Default::default()
+        };
+        let (result, stages) = drive(
+            Firmware::File(PathBuf::from("firmware.bin")),
+            true,
+            "v1.3.0".parse().ok(),
+            FakeReleases(Err(CommandError::Download("unused".into()))),
+            &device,
+        );
+        assert!(matches!(result, Err(CommandError::Verification(_))));
+        assert!(matches!(
+            &stages[stages.len() - 2..],
+            [
+                Stage::Verifying,
+                Stage::Failed(CommandError::Verification(_))
+            ]
+        ));
+
+        assert!(check_installed("v1.3.0-beta.1".parse().ok().as_ref(), "1.3.0.2-beta.1").is_ok());
+        assert!(check_installed(None, "1.2.0.1").is_ok());
+        assert!(check_installed("v1.3.0".parse().ok().as_ref(), "garbage").is_err());
+    }
+
+    #[test]
+    fn recovery_skips_the_bootloader() {
+        let device = FakeDevice::default();
+        let (result, stages) = drive(
+            Firmware::File(PathBuf::from("firmware.bin")),
+            true,
+            None,
+            FakeReleases(Err(CommandError::Download("unused".into()))),
+            &device,
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(stages.first(), Some(&Stage::Flashing));
+        assert_eq!(*device.calls.lock().unwrap(), ["flash", "verify"]);
+    }
+
+    #[test]
+    fn failures_end_the_session() {
+        let err = CommandError::Download("offline".into());
+        let device = FakeDevice::default();
+        let (result, stages) = drive(
+            Firmware::Asset(Box::new(asset("bridge6_r1.bin"))),
+            false,
+            None,
+            FakeReleases(Err(err.clone())),
+            &device,
+        );
+        assert_eq!(result, Err(err.clone()));
+        assert_eq!(stages, [Stage::Downloading, Stage::Failed(err)]);
+        assert!(device.calls.lock().unwrap().is_empty());
+
+        let device = FakeDevice {
+            flash_error: Some(CommandError::Cancelled),
+            ..Default::default()
+        };
+        let (result, stages) = drive(
+            Firmware::File(PathBuf::from("firmware.bin")),
+            true,
+            None,
+            FakeReleases(Ok(PathBuf::new())),
+            &device,
+        );
+        assert_eq!(result, Err(CommandError::Cancelled));
+        assert_eq!(stages.last(), Some(&Stage::Failed(CommandError::Cancelled)));
+    }
+
+    #[test]
+    fn unsigned_firmware_is_refused() {
+        let start = Event::Start {
+            firmware: Firmware::File(PathBuf::from("firmware.bin")),
+            in_bootloader: true,
+            version: None,
+        };
+        let mut session = InstallSession::new();
+        assert!(matches!(session.handle(start.clone()), Step::Flash(_)));
+        assert_eq!(session.signer(), Some(&Signer::Unsigned));
+
+        let mut session = InstallSession::new().verifying(Verifier::new(vec![], true));
+        assert!(matches!(
+            session.handle(start),
+            Step::Finished(Err(CommandError::Signature(_)))
+        ));
+        assert_eq!(session.signer(), None);
+    }
+
+    #[test]
+    fn unexpected_events_are_ignored() {
+        let mut session = InstallSession::new();
+        assert!(matches!(session.handle(Event::Confirmed), Step::Wait));
+        assert!(matches!(session.handle(Event::Flashed(Ok(()))), Step::Wait));
+        assert_eq!(session.stage(), &Stage::Idle);
+
+        session.handle(Event::Start {
+            firmware: Firmware::File(PathBuf::from("firmware.bin")),
+            in_bootloader: false,
+            version: None,
+        });
+        assert_eq!(session.reset(), Some(PathBuf::from("firmware.bin")));
+        assert_eq!(session.stage(), &Stage::Idle);
+    }
+}
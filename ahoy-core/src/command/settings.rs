@@ -0,0 +1,108 @@
+use std::{fmt, fs, path::PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{data_dir, signature::TrustedKey, CommandError, Context};
+use crate::i18n::t;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreference {
+    /// follow the operating system's light or dark mode
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemePreference {
+    pub const ALL: [ThemePreference; 3] = [
+        ThemePreference::System,
+        ThemePreference::Light,
+        ThemePreference::Dark,
+    ];
+}
+
+impl fmt::Display for ThemePreference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemePreference::System => write!(f, "{}", t!("theme-system")),
+            ThemePreference::Light => write!(f, "{}", t!("theme-light")),
+            ThemePreference::Dark => write!(f, "{}", t!("theme-dark")),
+        }
+    }
+}
+
+/// user preferences that outlive a session.
+/// every field has a default, so settings written by older versions still load.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemePreference,
+    /// a catalog like `de`, or none to follow the system locale
+    pub language: Option<String>,
+    /// a firmware policy file to hold every install to - see [`super::policy::Policy`]
+    pub policy: Option<PathBuf>,
+    /// minisign keys to trust on top of the bundled ones - see [`super::signature`]
+    pub trusted_keys: Vec<TrustedKey>,
+    /// refuse firmware that no trusted key has signed
+    pub require_signature: bool,
+}
+
+pub fn settings_path() -> Result<PathBuf, CommandError> {
+    Ok(data_dir()?.join(SETTINGS_FILE))
+}
+
+/// read the saved settings - anything missing or unreadable falls back to the defaults
+pub fn load() -> Settings {
+    let path = match settings_path() {
+        Ok(path) => path,
+        Err(err) => {
+            warn!("using default settings: {}", err.report());
+            return Settings::default();
+        }
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+            warn!(
+                "ignoring unreadable settings in {}: {}",
+                path.display(),
+                err
+            );
+            Settings::default()
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+        Err(err) => {
+            warn!("could not read {}: {}", path.display(), err);
+            Settings::default()
+        }
+    }
+}
+
+pub fn save(settings: &Settings) -> Result<(), CommandError> {
+    let path = settings_path()?;
+    info!("saving settings to: {}", path.display());
+
+    let contents = serde_json::to_string_pretty(settings)
+        .context(CommandError::IO, "could not serialize settings")?;
+    fs::write(&path, contents).context(CommandError::IO, "could not write settings")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, Settings::default());
+        assert_eq!(settings.theme, ThemePreference::System);
+
+        let settings: Settings = serde_json::from_str(r#"{"theme":"light"}"#).unwrap();
+        assert_eq!(settings.theme, ThemePreference::Light);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{fs, io::ErrorKind, path::Path, process};
+
+use log::{info, warn};
+
+use crate::{USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_VENDOR_ID};
+
+use super::{CommandError, Detail};
+
+/// where the udev rules get written to
+pub const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/69-pirate-midi.rules";
+
+pub enum SetupOutcome {
+    /// rules were written to disk and udev was reloaded
+    Installed,
+    /// we don't have permission to write the rules, so they were only rendered
+    Printed(String),
+}
+
+/// build the udev rules for both the serial and DFU modes of a device
+pub fn udev_rules() -> String {
+    let vendor = format!("{:04x}", USB_VENDOR_ID);
+    let serial = format!("{:04x}", USB_PRODUCT_ID);
+    let dfu = format!("{:04x}", USB_PRODUCT_DFU_ID);
+
+    format!(
+        "# Pirate MIDI devices - generated by ahoy v{version}\n\
+        # serial (CDC-ACM) mode\n\
+        SUBSYSTEM==\"usb\", ATTRS{{idVendor}}==\"{vendor}\", ATTRS{{idProduct}}==\"{serial}\", MODE=\"0666\", TAG+=\"uaccess\"\n\
+        SUBSYSTEM==\"tty\", ATTRS{{idVendor}}==\"{vendor}\", ATTRS{{idProduct}}==\"{serial}\", MODE=\"0666\", TAG+=\"uaccess\", ENV{{ID_MM_DEVICE_IGNORE}}=\"1\"\n\
+        # bootloader (DFU) mode\n\
+        SUBSYSTEM==\"usb\", ATTRS{{idVendor}}==\"{vendor}\", ATTRS{{idProduct}}==\"{dfu}\", MODE=\"0666\", TAG+=\"uaccess\"\n",
+        version = env!("CARGO_PKG_VERSION"),
+    )
+}
+
+/// are we running with root privileges?
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+    false
+}
+
+/// write the udev rules and reload udev - or hand back the rules if we can't
+pub fn install_udev_rules() -> Result<SetupOutcome, CommandError> {
+    if !cfg!(target_os = "linux") {
+        return Err(CommandError::Setup(
+            "udev rules are only applicable on linux".into(),
+        ));
+    }
+
+    let rules = udev_rules();
+
+    if !is_root() {
+        info!("not running as root - printing rules instead of writing them");
+        return Ok(SetupOutcome::Printed(rules));
+    }
+
+    info!("writing udev rules to: {}", UDEV_RULES_PATH);
+    match fs::write(Path::new(UDEV_RULES_PATH), &rules) {
+        Ok(_) => (),
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            warn!("permission denied writing udev rules: {}", err);
+            return Ok(SetupOutcome::Printed(rules));
+        }
+        Err(err) => {
+            return Err(CommandError::Setup(
+                Detail::new(format!("unable to write {}", UDEV_RULES_PATH)).because(err),
+            ))
+        }
+    }
+
+    // reload the rules and apply them to anything already plugged in
+    for args in [
+        vec!["control", "--reload-rules"],
+        vec!["trigger", "--subsystem-match=usb", "--subsystem-match=tty"],
+    ] {
+        match process::Command::new("udevadm").args(&args).status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => warn!("`udevadm {}` exited with: {}", args.join(" "), status),
+            Err(err) => {
+                return Err(CommandError::Setup(
+                    Detail::new("rules written, but unable to run udevadm").because(err),
+                ))
+            }
+        }
+    }
+
+    Ok(SetupOutcome::Installed)
+}
@@ -0,0 +1,215 @@
+//! detached minisign signatures for firmware, checked against the keys ahoy trusts.
+//! a signature lives next to what it signs - `bridge6_v1.3.0.1.bin.minisig`.
+
+use std::{fmt, fs, path::Path, path::PathBuf};
+
+use log::{info, warn};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+use super::{settings, CommandError, Context, Detail};
+use crate::i18n::t;
+
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// keys trusted without being configured, as a name and a minisign public key.
+/// releases aren't signed yet - the release signing key belongs here once it's published.
+const BUNDLED_KEYS: &[(&str, &str)] = &[];
+
+/// a minisign public key, and who it belongs to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    pub name: String,
+    /// the base64 line of a `minisign.pub` file
+    pub public_key: String,
+}
+
+/// who vouches for a firmware file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signer {
+    /// signed by the trusted key with this name
+    Trusted(String),
+    /// no signature, or none that any trusted key made
+    Unsigned,
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signer::Trusted(name) => write!(f, "{}", t!("signed-by", name = name.as_str())),
+            Signer::Unsigned => write!(f, "{}", t!("unsigned")),
+        }
+    }
+}
+
+/// where the signature for a firmware file would be
+pub fn signature_path(firmware: &Path) -> PathBuf {
+    let mut name = firmware.as_os_str().to_os_string();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// the verifier installs are held to - the bundled keys and those in settings.
+/// signatures are required if asked for here, or in settings.
+pub fn load(require: bool) -> Verifier {
+    let settings = settings::load();
+    let keys = BUNDLED_KEYS
+        .iter()
+        .map(|(name, public_key)| TrustedKey {
+            name: name.to_string(),
+            public_key: public_key.to_string(),
+        })
+        .chain(settings.trusted_keys)
+        .collect();
+    Verifier::new(keys, require || settings.require_signature)
+}
+
+/// checks firmware against a set of trusted keys before it's installed
+#[derive(Debug, Clone, Default)]
+pub struct Verifier {
+    keys: Vec<TrustedKey>,
+    require: bool,
+}
+
+impl Verifier {
+    pub fn new(keys: Vec<TrustedKey>, require: bool) -> Verifier {
+        Verifier { keys, require }
+    }
+
+    /// who signed a firmware file.
+    /// a signature from a trusted key that doesn't match the file is always an error -
+    /// anything else unsigned is only an error when signatures are required.
+    pub fn verify(&self, firmware: &Path) -> Result<Signer, CommandError> {
+        let name = firmware
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let unsigned = |reason: String| {
+            if self.require {
+                Err(CommandError::Signature(reason.into()))
+            } else {
+                info!("installing unsigned firmware: {}", reason);
+                Ok(Signer::Unsigned)
+            }
+        };
+
+        let path = signature_path(firmware);
+        if !path.exists() {
+            return unsigned(format!("{} has no signature", name));
+        }
+        let signature = fs::read_to_string(&path).context(
+            CommandError::IO,
+            format!("could not read {}", path.display()),
+        )?;
+        let signature = Signature::decode(&signature).map_err(|err| {
+            CommandError::Signature(
+                Detail::new(format!("{} is not a valid signature", path.display())).because(err),
+            )
+        })?;
+        let contents = fs::read(firmware).context(
+            CommandError::IO,
+            format!("could not read {}", firmware.display()),
+        )?;
+
+        for key in &self.keys {
+            let public_key = match PublicKey::from_base64(&key.public_key) {
+                Ok(public_key) => public_key,
+                Err(err) => {
+                    warn!("ignoring trusted key {}: {}", key.name, err);
+                    continue;
+                }
+            };
+            match public_key.verify(&contents, &signature, false) {
+                Ok(()) => {
+                    info!("{} is signed by {}", name, key.name);
+                    return Ok(Signer::Trusted(key.name.clone()));
+                }
+                // made by some other key
+                Err(minisign_verify::Error::UnexpectedKeyId) => continue,
+                Err(err) => {
+                    return Err(CommandError::Signature(
+                        Detail::new(format!("{} does not match its signature", name)).because(err),
+                    ))
+                }
+            }
+        }
+        unsigned(format!("{} is not signed by a trusted key", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    // made with minisign's format from a throwaway key, over `FIRMWARE`
+    const PUBLIC_KEY: &str = "RWQx6xHz17HkNgSqUkzwef8kMKEmgstQZpBhxpkhycAF55hxYOQzjIkj";
+    const OTHER_KEY: &str = "RWS14L4Y6GXYK+IJaHXCaKCZPyD6S2bhX/ZR6eVc0sCN+7JOH0B5wUW6";
+    const FIRMWARE: &[u8] = b"not really firmware\n";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQx6xHz17HkNl7jXUsNmfJXhbPNaExOcn+14yXrTRr23aj99PCdcNyJS/qzptSvpUsQ9HJA2HNAby1Whq7d36eoYQMK8rM/zwE=
+trusted comment: timestamp:1666137600\tfile:bridge6_v1.3.0.1.bin
+bcuny36YkV6EhhosVKRVwVVanvESrQBQOShXOGMcb66V9sZphV2Eukvs+fhF3/Sflr+80ytLNyHfknatKwnHAw==
+";
+
+    fn key(name: &str, public_key: &str) -> TrustedKey {
+        TrustedKey {
+            name: name.to_string(),
+            public_key: public_key.to_string(),
+        }
+    }
+
+    /// a firmware file, with a signature next to it if given
+    fn firmware(name: &str, contents: &[u8], signature: Option<&str>) -> PathBuf {
+        let path = temp_dir().join(format!("ahoy-signature-{}.bin", name));
+        fs::write(&path, contents).unwrap();
+        let _ = fs::remove_file(signature_path(&path));
+        if let Some(signature) = signature {
+            fs::write(signature_path(&path), signature).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn signatures_are_checked_against_trusted_keys() {
+        let signed = firmware("signed", FIRMWARE, Some(SIGNATURE));
+        let tampered = firmware("tampered", b"definitely not firmware\n", Some(SIGNATURE));
+        let unsigned = firmware("unsigned", FIRMWARE, None);
+        assert_eq!(
+            signature_path(&signed).file_name().unwrap(),
+            "ahoy-signature-signed.bin.minisig"
+        );
+
+        let trusting = Verifier::new(
+            vec![key("other", OTHER_KEY), key("pirate", PUBLIC_KEY)],
+            false,
+        );
+        assert_eq!(
+            trusting.verify(&signed),
+            Ok(Signer::Trusted("pirate".to_string()))
+        );
+        assert!(matches!(
+            trusting.verify(&tampered),
+            Err(CommandError::Signature(_))
+        ));
+        assert_eq!(trusting.verify(&unsigned), Ok(Signer::Unsigned));
+
+        // a signature nobody trusts is as good as none
+        let stranger = Verifier::new(vec![key("other", OTHER_KEY)], false);
+        assert_eq!(stranger.verify(&signed), Ok(Signer::Unsigned));
+        assert_eq!(Verifier::default().verify(&tampered), Ok(Signer::Unsigned));
+
+        let requiring = Verifier::new(vec![key("pirate", PUBLIC_KEY)], true);
+        assert!(requiring.verify(&signed).is_ok());
+        assert!(requiring.verify(&unsigned).is_err());
+        assert!(Verifier::new(vec![key("other", OTHER_KEY)], true)
+            .verify(&signed)
+            .is_err());
+
+        for path in [signed, tampered, unsigned] {
+            let _ = fs::remove_file(signature_path(&path));
+            let _ = fs::remove_file(path);
+        }
+    }
+}
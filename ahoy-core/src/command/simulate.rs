@@ -0,0 +1,368 @@
+//! a pretend device and release host, for walking through an install without either.
+//! faults can be injected, to see how everything around the install copes with them.
+
+use std::{
+    env::temp_dir,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_std::task::sleep;
+use futures::{future::BoxFuture, FutureExt};
+use log::info;
+use pirate_midi_rs::check::CheckResponse;
+
+use super::{
+    github::{Asset, DownloadProgress, Release},
+    session::{DeviceBackend, ReleaseBackend},
+    transfer::{CancelToken, ProgressEvent},
+    version::Version,
+    CommandError, Context, Detail,
+};
+
+// slow enough to watch, quick enough not to get in the way
+const STEP_DELAY: Duration = Duration::from_millis(750);
+const BLOCK_DELAY: Duration = Duration::from_millis(20);
+const BLOCK_SIZE: u64 = 2048;
+const FIRMWARE_SIZE: u64 = 96 * 1024;
+
+/// something to go wrong during a simulated install
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// the download doesn't match what github said it would be
+    CorruptDownload,
+    /// the device is unplugged halfway through the transfer
+    DeviceVanishes,
+    /// the device stops answering halfway through the transfer
+    DfuStall,
+}
+
+impl Fault {
+    /// the fault after this one, then none, then round again
+    pub fn cycle(fault: Option<Fault>) -> Option<Fault> {
+        match fault {
+            None => Some(Fault::CorruptDownload),
+            Some(Fault::CorruptDownload) => Some(Fault::DeviceVanishes),
+            Some(Fault::DeviceVanishes) => Some(Fault::DfuStall),
+            Some(Fault::DfuStall) => None,
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "corrupt-download" => Ok(Fault::CorruptDownload),
+            "device-vanishes" => Ok(Fault::DeviceVanishes),
+            "dfu-stall" => Ok(Fault::DfuStall),
+            _ => Err(format!(
+                "unknown fault: {} (expected corrupt-download, device-vanishes or dfu-stall)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fault::CorruptDownload => "corrupt-download",
+            Fault::DeviceVanishes => "device-vanishes",
+            Fault::DfuStall => "dfu-stall",
+        })
+    }
+}
+
+/// a dry run, and whatever should go wrong in it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Simulation {
+    pub fault: Option<Fault>,
+}
+
+impl Simulation {
+    pub fn releases(&self) -> SimulatedReleases {
+        SimulatedReleases { fault: self.fault }
+    }
+
+    pub fn device(&self, token: CancelToken) -> SimulatedDevice {
+        SimulatedDevice {
+            fault: self.fault,
+            token,
+        }
+    }
+}
+
+/// the device a simulation pretends is connected - one release behind the latest stable
+pub fn device_details() -> CheckResponse {
+    CheckResponse {
+        uid: "SIMULATED000000000000000".to_string(),
+        device_model: "Bridge6".to_string(),
+        firmware_version: "1.2.0.1".to_string(),
+        hardware_version: "v1".to_string(),
+        device_name: "Simulated Bridge6".to_string(),
+        profile_id: "0".to_string(),
+    }
+}
+
+/// releases to pick from in a simulation, newest first
+pub fn releases() -> Vec<Release> {
+    vec![
+        release(4, "v1.4.0-beta.1", true),
+        release(3, "v1.3.0", false),
+        release(2, "v1.2.0", false),
+    ]
+}
+
+fn release(id: u64, tag: &str, prerelease: bool) -> Release {
+    // assets carry the hardware revision ahead of any prerelease suffix
+    let (version, pre) = match tag.split_once('-') {
+        Some((version, pre)) => (version, format!("-{}", pre)),
+        None => (tag, String::new()),
+    };
+    let assets = ["bridge6", "bridge4"]
+        .iter()
+        .enumerate()
+        .map(|(index, model)| Asset {
+            url: String::new(),
+            browser_download_url: String::new(),
+            id: id * 10 + index as u64,
+            node_id: String::new(),
+            name: format!("{}_{}.1{}.bin", model, version, pre),
+            label: None,
+            state: "uploaded".to_string(),
+            content_type: "application/octet-stream".to_string(),
+            size: FIRMWARE_SIZE,
+            download_count: 0,
+            created_at: String::new(),
+            updated_at: String::new(),
+        })
+        .collect();
+
+    Release {
+        url: String::new(),
+        html_url: String::new(),
+        assets_url: String::new(),
+        upload_url: String::new(),
+        tarball_url: None,
+        zipball_url: None,
+        discussion_url: None,
+        id,
+        node_id: String::new(),
+        tag_name: tag.to_string(),
+        target_commitish: String::new(),
+        name: Some(format!("Simulated {}", tag)),
+        body: Some("Nothing to see here - this release only exists in a dry run.".to_string()),
+        draft: false,
+        prerelease,
+        created_at: "2022-01-01T00:00:00Z".to_string(),
+        published_at: Some("2022-01-01T00:00:00Z".to_string()),
+        assets,
+    }
+}
+
+/// release assets that are made up on the spot, instead of downloaded
+pub struct SimulatedReleases {
+    pub fault: Option<Fault>,
+}
+
+impl ReleaseBackend for SimulatedReleases {
+    fn download(
+        &self,
+        asset: Asset,
+        mut progress: Box<dyn FnMut(DownloadProgress) + Send>,
+    ) -> BoxFuture<'static, Result<PathBuf, CommandError>> {
+        let fault = self.fault;
+        async move {
+            info!("simulating download of {}", asset.name);
+            let mut bytes = 0;
+            while bytes < asset.size {
+                // a corrupt download is caught where a real one would be - on its size
+                if fault == Some(Fault::CorruptDownload) && bytes >= asset.size / 2 {
+                    return Err(CommandError::Checksum(Detail::new(format!(
+                        "downloaded more than the expected {} bytes of {}",
+                        asset.size, asset.name
+                    ))));
+                }
+                bytes = asset.size.min(bytes + BLOCK_SIZE);
+                progress(DownloadProgress {
+                    bytes,
+                    total: asset.size,
+                });
+                sleep(BLOCK_DELAY).await;
+            }
+
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            let path = temp_dir().join(format!("{time}-simulated-{}", asset.name));
+            std::fs::write(&path, vec![0xFF; asset.size as usize]).context(
+                CommandError::IO,
+                format!("could not create {}", path.display()),
+            )?;
+            Ok(path)
+        }
+        .boxed()
+    }
+}
+
+/// a device that goes through the motions of an install, without any usb involved
+pub struct SimulatedDevice {
+    pub fault: Option<Fault>,
+    pub token: CancelToken,
+}
+
+impl DeviceBackend for SimulatedDevice {
+    fn enter_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+        async {
+            info!("simulating bootloader request");
+            sleep(STEP_DELAY).await;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn wait_for_bootloader(&self) -> BoxFuture<'static, Result<(), CommandError>> {
+        sleep(STEP_DELAY).map(Ok).boxed()
+    }
+
+    fn flash(
+        &self,
+        firmware: PathBuf,
+        mut progress: Box<dyn FnMut(ProgressEvent) + Send>,
+    ) -> BoxFuture<'static, Result<(), CommandError>> {
+        let fault = self.fault;
+        let token = self.token.clone();
+        async move {
+            let total = std::fs::metadata(&firmware)
+                .context(
+                    CommandError::IO,
+                    format!("could not read {}", firmware.display()),
+                )?
+                .len();
+            info!("simulating install of {} bytes", total);
+
+            let mut bytes = 0;
+            while bytes < total {
+                if token.is_cancelled() {
+                    return Err(CommandError::Cancelled);
+                }
+                // faults strike halfway, once the device is good and stuck in its bootloader
+                if bytes >= total / 2 {
+                    match fault {
+                        Some(Fault::DeviceVanishes) => {
+                            return Err(CommandError::NoDevice(
+                                "device disconnected during the transfer".into(),
+                            ))
+                        }
+                        Some(Fault::DfuStall) => {
+                            sleep(STEP_DELAY).await;
+                            return Err(CommandError::Timeout(
+                                "device did not respond within 5 seconds".into(),
+                            ));
+                        }
+                        _ => (),
+                    }
+                }
+                bytes = total.min(bytes + BLOCK_SIZE);
+                progress(ProgressEvent::Writing { bytes, total });
+                sleep(BLOCK_DELAY).await;
+            }
+
+            progress(ProgressEvent::Manifesting);
+            sleep(STEP_DELAY).await;
+            progress(ProgressEvent::Done);
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn verify(&self, _: Option<Version>) -> BoxFuture<'static, Result<(), CommandError>> {
+        async {
+            info!("simulating restart");
+            sleep(STEP_DELAY).await;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::session::{self, Firmware, InstallSession};
+    use std::sync::{Arc, Mutex};
+
+    fn install(fault: Option<Fault>) -> Result<(), CommandError> {
+        let release = &releases()[1];
+        let asset = release.find_asset("bridge6", '1').unwrap().clone();
+        let simulation = Simulation { fault };
+        async_std::task::block_on(session::run(
+            &mut InstallSession::new(),
+            Firmware::Asset(Box::new(asset)),
+            false,
+            release.version(),
+            &simulation.releases(),
+            &simulation.device(CancelToken::new()),
+            |_, _| (),
+        ))
+    }
+
+    #[test]
+    fn faults_fail_the_install() {
+        assert!(install(None).is_ok());
+        assert!(matches!(
+            install(Some(Fault::CorruptDownload)),
+            Err(CommandError::Checksum(_))
+        ));
+        assert!(matches!(
+            install(Some(Fault::DeviceVanishes)),
+            Err(CommandError::NoDevice(_))
+        ));
+        assert!(matches!(
+            install(Some(Fault::DfuStall)),
+            Err(CommandError::Timeout(_))
+        ));
+    }
+
+    #[test]
+    fn downloads_report_progress() {
+        let asset = releases()[1].find_asset("bridge6", '1').unwrap().clone();
+        let downloaded = Arc::new(Mutex::new(vec![]));
+        let path = async_std::task::block_on(Simulation::default().releases().download(
+            asset,
+            Box::new({
+                let downloaded = downloaded.clone();
+                move |progress| downloaded.lock().unwrap().push(progress)
+            }),
+        ))
+        .unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let downloaded = downloaded.lock().unwrap();
+        assert_eq!(
+            downloaded.last(),
+            Some(&DownloadProgress {
+                bytes: FIRMWARE_SIZE,
+                total: FIRMWARE_SIZE
+            })
+        );
+        assert!(downloaded
+            .windows(2)
+            .all(|pair| pair[0].bytes < pair[1].bytes));
+    }
+
+    #[test]
+    fn faults_parse_as_displayed() {
+        let mut fault = Fault::cycle(None);
+        while let Some(current) = fault {
+            assert_eq!(current.to_string().parse::<Fault>(), Ok(current));
+            fault = Fault::cycle(fault);
+        }
+        assert!("meteor-strike".parse::<Fault>().is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::info;
+use serde::Serialize;
+
+use super::{
+    history::{format_timestamp, JournalEntry, Outcome, Source},
+    CommandError, Context,
+};
+
+const CSV_HEADER: &str =
+    "time,result,device_uid,model,hardware_version,previous_version,firmware,seconds,error";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+/// a unit that went through the station
+#[derive(Serialize, Debug, Clone)]
+pub struct StationRecord {
+    pub timestamp: u64,
+    pub result: Verdict,
+    pub device_uid: Option<String>,
+    pub model: Option<String>,
+    pub hardware_version: Option<String>,
+    pub previous_version: Option<String>,
+    pub firmware: String,
+    pub seconds: u64,
+    pub error: Option<String>,
+}
+
+impl StationRecord {
+    /// everything but the duration is already in the journal entry for the install
+    pub fn new(entry: &JournalEntry, duration: Duration) -> StationRecord {
+        StationRecord {
+            timestamp: entry.timestamp,
+            result: match entry.outcome {
+                Outcome::Success => Verdict::Pass,
+                Outcome::Failed | Outcome::Cancelled => Verdict::Fail,
+            },
+            device_uid: entry.device_uid.clone(),
+            model: entry.model.clone(),
+            hardware_version: entry.hardware_version.clone(),
+            previous_version: entry.previous_version.clone(),
+            firmware: match &entry.source {
+                Source::Release { tag } => tag.clone(),
+                Source::File { name, .. } => name.clone(),
+            },
+            seconds: duration.as_secs(),
+            error: match entry.outcome {
+                Outcome::Cancelled => Some(CommandError::Cancelled.to_string()),
+                _ => entry.error.clone(),
+            },
+        }
+    }
+
+    fn csv_row(&self) -> String {
+        [
+            format_timestamp(self.timestamp),
+            format!("{:?}", self.result).to_uppercase(),
+            self.device_uid.clone().unwrap_or_default(),
+            self.model.clone().unwrap_or_default(),
+            self.hardware_version.clone().unwrap_or_default(),
+            self.previous_version.clone().unwrap_or_default(),
+            self.firmware.clone(),
+            self.seconds.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<String>>()
+        .join(",")
+    }
+}
+
+/// quote a field when it would otherwise break the row
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Csv,
+    /// one JSON object per line
+    Json,
+}
+
+impl LogFormat {
+    /// picked by extension - anything that isn't .json or .jsonl is written as CSV
+    pub fn of(path: &Path) -> LogFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("jsonl") => {
+                LogFormat::Json
+            }
+            _ => LogFormat::Csv,
+        }
+    }
+}
+
+/// where the station writes a line for every unit
+#[derive(Debug, Clone)]
+pub struct StationLog {
+    pub path: PathBuf,
+    pub format: LogFormat,
+}
+
+impl StationLog {
+    pub fn new(path: PathBuf) -> StationLog {
+        let format = LogFormat::of(&path);
+        StationLog { path, format }
+    }
+
+    /// append a record, reopening the file each time so it can be read while the station runs
+    pub fn append(&self, record: &StationRecord) -> Result<(), CommandError> {
+        info!("recording unit to: {}", self.path.display());
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(CommandError::IO, "could not open station log")?;
+
+        let line = match self.format {
+            LogFormat::Csv => {
+                let empty = file
+                    .metadata()
+                    .map(|metadata| metadata.len() == 0)
+                    .unwrap_or(true);
+                if empty {
+                    format!("{}\n{}", CSV_HEADER, record.csv_row())
+                } else {
+                    record.csv_row()
+                }
+            }
+            LogFormat::Json => serde_json::to_string(record)
+                .context(CommandError::IO, "could not serialize station record")?,
+        };
+        writeln!(file, "{}", line).context(CommandError::IO, "could not write station log")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use crate::command::{
+        history::{JournalEntry, Source},
+        CommandError,
+    };
+
+    use super::{LogFormat, StationRecord, Verdict};
+
+    #[test]
+    fn records_become_csv_rows() {
+        let entry = JournalEntry::new(
+            "station",
+            Source::Release {
+                tag: "1.2.0.1".to_string(),
+            },
+        )
+        .finish(&Err(CommandError::Dfu("stalled, \"badly\"".into())));
+        let mut record = StationRecord::new(&entry, Duration::from_secs(42));
+        record.timestamp = 0;
+
+        assert_eq!(record.result, Verdict::Fail);
+        assert_eq!(
+            record.csv_row(),
+            "1970-01-01 00:00:00 UTC,FAIL,,,,,1.2.0.1,42,\"unable to perform install: stalled, \"\"badly\"\"\""
+        );
+        assert_eq!(LogFormat::of(Path::new("units.JSONL")), LogFormat::Json);
+        assert_eq!(LogFormat::of(Path::new("units.csv")), LogFormat::Csv);
+    }
+}
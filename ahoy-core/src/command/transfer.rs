@@ -0,0 +1,284 @@
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use rusb::UsbContext;
+
+// DFU class requests - see the USB DFU 1.1 spec, section 3
+const DFU_REQUEST_OUT: u8 = 0x21;
+const DFU_REQUEST_IN: u8 = 0xA1;
+const DFU_CLRSTATUS: u8 = 4;
+const DFU_GETSTATUS: u8 = 3;
+const DFU_ABORT: u8 = 6;
+const DFU_STATE_IDLE: u8 = 2;
+const DFU_STATE_ERROR: u8 = 10;
+
+/// shared flag used to abort a transfer between DFU blocks
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    /// cancelling the parent cancels this token too, but not the other way around
+    parent: Option<Box<CancelToken>>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// a token for one part of a longer job - cancelled along with this one,
+    /// without being able to cancel it
+    pub fn child(&self) -> CancelToken {
+        CancelToken {
+            cancelled: Arc::default(),
+            parent: Some(Box::new(self.clone())),
+        }
+    }
+
+    pub fn cancel(&self) {
+        info!("install cancellation requested");
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || matches!(&self.parent, Some(parent) if parent.is_cancelled())
+    }
+}
+
+/// how long we're willing to wait on a device during an install
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// maximum time between two DFU blocks
+    pub transfer: Duration,
+    /// maximum time for the entire install
+    pub overall: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            transfer: Duration::from_secs(5),
+            overall: Duration::from_secs(300),
+        }
+    }
+}
+
+/// why a transfer was stopped before it finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Abort {
+    Cancelled,
+    TransferTimeout(Duration),
+    OverallTimeout(Duration),
+}
+
+/// what the DFU layer is currently doing.
+/// dfu-libusb erases each page inside its download loop without saying so, and can't read the
+/// firmware back, so there's no erase or verify phase to report - the install session checks
+/// the firmware version once the device has restarted instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    Writing { bytes: u64, total: u64 },
+    Manifesting,
+    Done,
+}
+
+/// turns the raw DFU callbacks into typed progress events
+#[derive(Clone)]
+pub struct ProgressReporter {
+    sink: Arc<Mutex<Box<dyn FnMut(ProgressEvent) + Send>>>,
+    written: Arc<AtomicU64>,
+    total: u64,
+}
+
+impl ProgressReporter {
+    pub fn new(total: u64, sink: impl FnMut(ProgressEvent) + Send + 'static) -> ProgressReporter {
+        ProgressReporter {
+            sink: Arc::new(Mutex::new(Box::new(sink))),
+            written: Arc::new(AtomicU64::new(0)),
+            total,
+        }
+    }
+
+    pub fn emit(&self, event: ProgressEvent) {
+        match self.sink.lock() {
+            Ok(mut sink) => sink(event),
+            Err(err) => warn!("unable to report progress: {}", err),
+        }
+    }
+
+    /// dfu-core reports the size of each block after it's written
+    pub fn block_written(&self, count: usize) {
+        let bytes = self.written.fetch_add(count as u64, Ordering::SeqCst) + count as u64;
+        self.emit(ProgressEvent::Writing {
+            bytes,
+            total: self.total,
+        });
+
+        // the last block is followed by the zero length download that starts manifestation
+        if bytes == self.total {
+            self.emit(ProgressEvent::Manifesting);
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.written.load(Ordering::SeqCst)
+    }
+}
+
+/// wraps the firmware file - dfu-core reads one block at a time,
+/// so this is our chance to stop between blocks.
+pub struct TransferReader<R: Read> {
+    inner: R,
+    token: CancelToken,
+    timeouts: Timeouts,
+    started: Instant,
+    last_block: Instant,
+    abort: Option<Abort>,
+}
+
+impl<R: Read> TransferReader<R> {
+    pub fn new(inner: R, token: CancelToken, timeouts: Timeouts) -> TransferReader<R> {
+        let now = Instant::now();
+        TransferReader {
+            inner,
+            token,
+            timeouts,
+            started: now,
+            last_block: now,
+            abort: None,
+        }
+    }
+
+    /// the reason the transfer was aborted - if it was
+    pub fn abort_reason(&self) -> Option<Abort> {
+        self.abort.clone()
+    }
+
+    fn check(&self) -> Option<Abort> {
+        if self.token.is_cancelled() {
+            Some(Abort::Cancelled)
+        } else if self.started.elapsed() > self.timeouts.overall {
+            Some(Abort::OverallTimeout(self.timeouts.overall))
+        } else if self.last_block.elapsed() > self.timeouts.transfer {
+            Some(Abort::TransferTimeout(self.timeouts.transfer))
+        } else {
+            None
+        }
+    }
+}
+
+impl<R: Read> Read for TransferReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(abort) = self.check() {
+            warn!("aborting transfer: {:?}", abort);
+            self.abort = Some(abort);
+            return Err(io::Error::new(io::ErrorKind::Other, "transfer aborted"));
+        }
+
+        let read = self.inner.read(buf)?;
+        self.last_block = Instant::now();
+        Ok(read)
+    }
+}
+
+/// abort whatever the device was doing, and clear any error so it sits in dfuIDLE.
+/// this leaves the device ready for another attempt rather than half-way through a download.
+pub fn reset_to_idle<T: UsbContext>(
+    context: &T,
+    vendor_id: u16,
+    product_id: u16,
+    timeout: Duration,
+) -> rusb::Result<()> {
+    let handle = context
+        .open_device_with_vid_pid(vendor_id, product_id)
+        .ok_or(rusb::Error::NoDevice)?;
+    handle.claim_interface(0)?;
+
+    handle.write_control(DFU_REQUEST_OUT, DFU_ABORT, 0, 0, &[], timeout)?;
+
+    // status: [bStatus, bwPollTimeout x3, bState, iString]
+    let mut status = [0u8; 6];
+    handle.read_control(DFU_REQUEST_IN, DFU_GETSTATUS, 0, 0, &mut status, timeout)?;
+    if status[4] == DFU_STATE_ERROR {
+        handle.write_control(DFU_REQUEST_OUT, DFU_CLRSTATUS, 0, 0, &[], timeout)?;
+        handle.read_control(DFU_REQUEST_IN, DFU_GETSTATUS, 0, 0, &mut status, timeout)?;
+    }
+
+    handle.release_interface(0)?;
+
+    if status[4] == DFU_STATE_IDLE {
+        info!("device returned to dfuIDLE");
+        Ok(())
+    } else {
+        warn!("device is in unexpected dfu state: {}", status[4]);
+        Err(rusb::Error::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use std::sync::{Arc, Mutex};
+
+    use super::{Abort, CancelToken, ProgressEvent, ProgressReporter, Timeouts, TransferReader};
+
+    #[test]
+    fn test_cancel_between_blocks() {
+        let token = CancelToken::new();
+        let mut reader = TransferReader::new(&[0u8; 8][..], token.clone(), Timeouts::default());
+        let mut block = [0u8; 4];
+
+        assert_eq!(reader.read(&mut block).unwrap(), 4);
+        token.cancel();
+        assert!(reader.read(&mut block).is_err());
+        assert_eq!(reader.abort_reason(), Some(Abort::Cancelled));
+    }
+
+    #[test]
+    fn test_child_tokens() {
+        let parent = CancelToken::new();
+        let child = parent.child();
+        child.cancel();
+        assert!(child.is_cancelled());
+        assert!(!parent.is_cancelled());
+
+        let child = parent.child();
+        parent.cancel();
+        assert!(child.is_cancelled());
+    }
+
+    #[test]
+    fn test_progress_events() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let reporter = ProgressReporter::new(8, {
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        });
+        let mut reader =
+            TransferReader::new(&[0u8; 8][..], CancelToken::new(), Timeouts::default());
+        let mut block = [0u8; 4];
+
+        for _ in 0..2 {
+            let read = reader.read(&mut block).unwrap();
+            reporter.block_written(read);
+        }
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ProgressEvent::Writing { bytes: 4, total: 8 },
+                ProgressEvent::Writing { bytes: 8, total: 8 },
+                ProgressEvent::Manifesting,
+            ]
+        );
+        assert_eq!(reporter.bytes_written(), 8);
+    }
+}
@@ -0,0 +1,180 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// a BridgeOS firmware version - up to four numeric parts, plus an optional prerelease suffix.
+/// e.g. `v1.2.0`, `1.2.0.1` or `v1.3.0-beta.2`
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub parts: [u64; 4],
+    pub pre: Option<String>,
+    // how many parts were written, so we display the version the way it was given
+    precision: usize,
+}
+
+impl Version {
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
+    }
+
+    /// whether a version reported by a device is this release.
+    /// devices may report a fourth part that releases tagged with three parts leave out.
+    pub fn matches_installed(&self, installed: &Version) -> bool {
+        if self == installed {
+            return true;
+        }
+        self.precision < installed.precision
+            && self.parts[..self.precision] == installed.parts[..self.precision]
+            && self.pre == installed.pre
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().trim_start_matches(['v', 'V']);
+        let (numbers, pre) = match trimmed.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_lowercase())),
+            Some(_) => return Err(format!("empty prerelease suffix in version: {}", s)),
+            None => (trimmed, None),
+        };
+
+        let numbers = numbers
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|_| format!("not a version: {}", s))?;
+        if numbers.is_empty() || numbers.len() > 4 {
+            return Err(format!("expected one to four version parts: {}", s));
+        }
+
+        let mut parts = [0; 4];
+        parts[..numbers.len()].copy_from_slice(&numbers);
+        Ok(Version {
+            parts,
+            pre,
+            precision: numbers.len(),
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.parts[..self.precision]
+            .iter()
+            .map(|part| part.to_string())
+            .collect();
+        write!(f, "{}", parts.join("."))?;
+        match &self.pre {
+            Some(pre) => write!(f, "-{}", pre),
+            None => Ok(()),
+        }
+    }
+}
+
+/// compare prerelease suffixes the way semver does - numbers numerically, and before words
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.parts
+            .cmp(&other.parts)
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                // a release is newer than any of its prereleases
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_bridgeos_versions() {
+        assert_eq!(v("v1.2.0").parts, [1, 2, 0, 0]);
+        assert_eq!(v("1.2.0.3").parts, [1, 2, 0, 3]);
+        assert_eq!(v("v1.3.0-Beta.2").pre.as_deref(), Some("beta.2"));
+        assert_eq!(v("v1.3.0-beta.2").to_string(), "1.3.0-beta.2");
+        assert!("bridge6".parse::<Version>().is_err());
+        assert!("1.2.0-".parse::<Version>().is_err());
+        assert!("1.2.3.4.5".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn orders_versions() {
+        let mut versions = [
+            v("1.3.0-beta.10"),
+            v("1.2.0"),
+            v("1.3.0"),
+            v("1.3.0-beta.2"),
+            v("1.3.0-alpha"),
+            v("1.2.0.1"),
+            v("1.10.0"),
+        ];
+        versions.sort();
+
+        let sorted: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "1.2.0",
+                "1.2.0.1",
+                "1.3.0-alpha",
+                "1.3.0-beta.2",
+                "1.3.0-beta.10",
+                "1.3.0",
+                "1.10.0"
+            ]
+        );
+        assert_eq!(v("v1.2.0"), v("1.2.0.0"));
+    }
+
+    #[test]
+    fn matches_installed_versions() {
+        assert!(v("v1.2.0").matches_installed(&v("1.2.0")));
+        assert!(v("v1.2.0").matches_installed(&v("1.2.0.1")));
+        assert!(!v("v1.2.0.2").matches_installed(&v("1.2.0.1")));
+        assert!(!v("v1.2.0").matches_installed(&v("1.2.1")));
+        assert!(!v("v1.2.0-beta.1").matches_installed(&v("1.2.0")));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub use fluent_bundle::FluentArgs;
+use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use unic_langid::LanguageIdentifier;
+
+/// look up a translated message, with optional `name = value` arguments
+#[macro_export]
+macro_rules! t {
+    ($id:literal) => {
+        $crate::i18n::translate($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::translate($id, Some(&args))
+    }};
+}
+pub use crate::t;
+
+/// a language we ship a catalog for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub code: &'static str,
+    pub name: &'static str,
+    catalog: &'static str,
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// the first locale is the fallback for anything missing from the others
+pub static LOCALES: [Locale; 2] = [
+    Locale {
+        code: "en-US",
+        name: "English",
+        catalog: include_str!("../locales/en-US/ahoy.ftl"),
+    },
+    Locale {
+        code: "de",
+        name: "Deutsch",
+        catalog: include_str!("../locales/de/ahoy.ftl"),
+    },
+];
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    static ref BUNDLES: Vec<FluentBundle<FluentResource>> = LOCALES.iter().map(bundle).collect();
+}
+
+fn langid(code: &str) -> Option<LanguageIdentifier> {
+    code.parse().ok()
+}
+
+fn bundle(locale: &Locale) -> FluentBundle<FluentResource> {
+    let resource =
+        FluentResource::try_new(locale.catalog.to_string()).unwrap_or_else(|(resource, errors)| {
+            warn!("errors in the {} catalog: {:?}", locale.code, errors);
+            resource
+        });
+
+    let mut bundle = FluentBundle::new_concurrent(langid(locale.code).into_iter().collect());
+    // the unicode isolation marks show up as garbage in terminals
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        warn!("errors loading the {} catalog: {:?}", locale.code, errors);
+    }
+    bundle
+}
+
+/// turn a posix locale like `de_DE.UTF-8` into a language tag like `de-DE`
+fn normalize(locale: &str) -> String {
+    locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-")
+}
+
+/// pick the catalog that best matches the requested locales, in order of preference
+pub fn negotiate(requested: &[String]) -> &'static Locale {
+    let requested: Vec<LanguageIdentifier> = requested
+        .iter()
+        .filter_map(|locale| langid(&normalize(locale)))
+        .collect();
+    let available: Vec<LanguageIdentifier> = LOCALES
+        .iter()
+        .filter_map(|locale| langid(locale.code))
+        .collect();
+
+    negotiate_languages(
+        &requested,
+        &available,
+        available.first(),
+        NegotiationStrategy::Lookup,
+    )
+    .first()
+    .and_then(|chosen| available.iter().position(|locale| locale == *chosen))
+    .map(|index| &LOCALES[index])
+    .unwrap_or(&LOCALES[0])
+}
+
+/// switch languages - a saved preference wins over the system locale
+pub fn init(preference: Option<&str>) {
+    let requested: Vec<String> = preference
+        .map(str::to_string)
+        .into_iter()
+        .chain(sys_locale::get_locale())
+        .collect();
+    let locale = negotiate(&requested);
+    debug!("using the {} catalog for {:?}", locale.code, requested);
+
+    let index = LOCALES
+        .iter()
+        .position(|candidate| candidate == locale)
+        .unwrap_or_default();
+    ACTIVE.store(index, Ordering::Relaxed);
+}
+
+pub fn current() -> &'static Locale {
+    &LOCALES[ACTIVE.load(Ordering::Relaxed)]
+}
+
+/// format a message from the active catalog, falling back to english, then to the id itself
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    let active = &BUNDLES[ACTIVE.load(Ordering::Relaxed)];
+    for bundle in [active, &BUNDLES[0]] {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = vec![];
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                warn!("unable to format {}: {:?}", id, errors);
+            }
+            return text.into_owned();
+        }
+    }
+
+    warn!("missing translation: {}", id);
+    id.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_system_locales() {
+        assert_eq!(negotiate(&["de_DE.UTF-8".to_string()]).code, "de");
+        assert_eq!(negotiate(&["de-AT".to_string()]).code, "de");
+        assert_eq!(
+            negotiate(&["fr-FR".to_string(), "de".to_string()]).code,
+            "de"
+        );
+        assert_eq!(negotiate(&["ja-JP".to_string()]).code, "en-US");
+        assert_eq!(negotiate(&[]).code, "en-US");
+    }
+
+    #[test]
+    fn catalogs_are_complete() {
+        // every message in the english catalog needs a translation
+        let ids: Vec<&str> = LOCALES[0]
+            .catalog
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| line.split_once('=').map(|(id, _)| id.trim()))
+            .collect();
+        assert!(!ids.is_empty());
+
+        for (locale, bundle) in LOCALES.iter().zip(BUNDLES.iter()) {
+            let missing: Vec<&&str> = ids.iter().filter(|id| !bundle.has_message(id)).collect();
+            assert!(
+                missing.is_empty(),
+                "{} is missing {:?}",
+                locale.code,
+                missing
+            );
+        }
+    }
+
+    #[test]
+    fn formats_arguments() {
+        let mut args = FluentArgs::new();
+        args.set("version", "v1.2.0");
+        let text = translate("update-available-version", Some(&args));
+        assert!(text.contains("v1.2.0"), "{}", text);
+        assert_eq!(translate("no-such-message", None), "no-such-message");
+    }
+}
@@ -0,0 +1,50 @@
+//! Firmware updating for Pirate MIDI devices, without the app around it.
+//!
+//! This is everything the `ahoy` command line and GUI are built on, for embedding in other tools:
+//!
+//! - [`usb::observer`] finds Pirate MIDI devices on the bus, and watches them come and go
+//! - [`command::device`] talks to a device over its serial port - asking about itself, or
+//!   restarting into its bootloader - and installs firmware over DFU once it has
+//! - [`command::github`] lists firmware releases and downloads their assets, and
+//!   [`command::channel`] groups them into stable, beta and draft
+//! - [`command::history`] identifies firmware images and keeps a journal of every install
+//! - [`command::signature`] checks firmware against the minisign keys ahoy trusts
+//! - [`command::session`] ties these together into a single install, driven by whoever embeds it
+//! - [`command::simulate`] stands in for the device and github, for dry runs
+//!
+//! Every fallible call returns a [`command::CommandError`], with a stable exit code and a
+//! translated hint on what to do about it.
+//!
+//! ```no_run
+//! use ahoy_core::command::{
+//!     session::{self, Firmware, Github, InstallSession, PirateDevice},
+//!     transfer::{CancelToken, Timeouts},
+//! };
+//!
+//! # async fn example() -> Result<(), ahoy_core::command::CommandError> {
+//! let device = PirateDevice {
+//!     port: None,
+//!     raw_device: None,
+//!     token: CancelToken::new(),
+//!     timeouts: Timeouts::default(),
+//! };
+//! let firmware = Firmware::File("bridge6_v1.2.0.1.bin".into());
+//! let version = "v1.2.0".parse().ok();
+//! let mut session = InstallSession::new();
+//! session::run(&mut session, firmware, false, version, &Github, &device, |_, _| ()).await
+//! # }
+//! ```
+
+use std::time::Duration;
+
+pub mod command;
+pub mod i18n;
+pub mod usb;
+
+pub const USB_VENDOR_ID: u16 = 0x0483;
+pub const USB_PRODUCT_ID: u16 = 0x5740;
+pub const USB_PRODUCT_DFU_ID: u16 = 0xDF11;
+pub const USB_TIMEOUT: Duration = Duration::from_secs(1);
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+pub const GITHUB_ORG: &str = "Pirate-MIDI";
+pub const GITHUB_REPO: &str = "Pirate-MIDI-BridgeOS";
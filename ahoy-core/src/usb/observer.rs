@@ -5,7 +5,10 @@ use std::{collections::HashSet, hash::Hash, thread, time::Duration};
 
 use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 
-use crate::{USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_TIMEOUT, USB_VENDOR_ID};
+use crate::{
+    command::device::find_serial_port, USB_PRODUCT_DFU_ID, USB_PRODUCT_ID, USB_TIMEOUT,
+    USB_VENDOR_ID,
+};
 
 // HOT PLUG HANDLER
 
@@ -47,6 +50,7 @@ pub struct UsbDevice {
     pub raw_device: Option<Device<Context>>,
     pub vendor_id: u16,
     pub product_id: u16,
+    pub serial_number: Option<String>,
 }
 
 impl PartialEq for UsbDevice {
@@ -64,11 +68,29 @@ impl Hash for UsbDevice {
 
 impl UsbDevice {
     pub fn new(device: Device<Context>, device_desc: &DeviceDescriptor) -> UsbDevice {
-        UsbDevice {
-            raw_device: Some(device),
+        let mut usb_device = UsbDevice {
+            raw_device: None,
             vendor_id: device_desc.vendor_id(),
             product_id: device_desc.product_id(),
+            serial_number: None,
+        };
+
+        // only open our own devices to read the serial number - it's used to find the serial port
+        if usb_device.is_stm_device() || usb_device.is_dfu_device() {
+            usb_device.serial_number = match device
+                .open()
+                .and_then(|handle| handle.read_serial_number_string_ascii(device_desc))
+            {
+                Ok(serial) => Some(serial),
+                Err(err) => {
+                    warn!("unable to read device serial number: {}", err);
+                    None
+                }
+            };
         }
+
+        usb_device.raw_device = Some(device);
+        usb_device
     }
 
     pub fn is_stm_device(&self) -> bool {
@@ -78,6 +100,15 @@ impl UsbDevice {
     pub fn is_dfu_device(&self) -> bool {
         self.vendor_id == USB_VENDOR_ID && self.product_id == USB_PRODUCT_DFU_ID
     }
+
+    /// the serial port that belongs to this specific device
+    pub fn serial_port(&self) -> Option<String> {
+        find_serial_port(
+            self.vendor_id,
+            self.product_id,
+            self.serial_number.as_deref(),
+        )
+    }
 }
 
 #[derive(Clone)]
@@ -2,7 +2,6 @@ use async_std::task;
 use futures::channel::mpsc;
 use futures::channel::mpsc::Receiver;
 use futures::SinkExt;
-use iced_futures::futures;
 use log::*;
 
 use super::observer::{Event, Observer};
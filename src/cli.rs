@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use clap::{Parser, Subcommand};
 
+use ahoy_core::command::{
+    channel::Channel,
+    device::Model,
+    simulate::{Fault, Simulation},
+    transfer::Timeouts,
+};
+
 /// Update the firmware for Pirate MIDI devices
 /// * Run with no commands to start the GUI *
 #[derive(Default, Parser, Debug)]
@@ -15,11 +22,56 @@ pub struct Args {
     #[clap(global = true, short, long)]
     pub debug: bool,
 
+    /// Serial port of the device to talk to (e.g. /dev/ttyACM0 or COM3)
+    /// (By default the first Pirate MIDI device found is used)
+    #[clap(global = true, short, long, verbatim_doc_comment)]
+    pub port: Option<String>,
+
+    /// Go through an install without a device or network - nothing is downloaded or installed
+    /// (Applies to the GUI, install and recover)
+    #[clap(global = true, long, verbatim_doc_comment)]
+    pub dry_run: bool,
+
+    /// Make a dry run fail: corrupt-download, device-vanishes or dfu-stall
+    #[clap(global = true, long, requires = "dry-run")]
+    pub fault: Option<Fault>,
+
+    /// Firmware policy file to hold installs to, instead of the one in settings
+    #[clap(global = true, long)]
+    pub policy: Option<PathBuf>,
+
+    /// Refuse firmware that isn't signed by a trusted key
+    /// (Keys are bundled with ahoy or listed in settings)
+    #[clap(global = true, long, verbatim_doc_comment)]
+    pub require_signature: bool,
+
+    /// Seconds to wait on the device between firmware blocks before giving up
+    #[clap(global = true, long, default_value = "5")]
+    pub transfer_timeout: u64,
+
+    /// Seconds to wait for an entire firmware install before giving up
+    #[clap(global = true, long, default_value = "300")]
+    pub install_timeout: u64,
+
     /// Source
     #[clap(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Args {
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            transfer: Duration::from_secs(self.transfer_timeout),
+            overall: Duration::from_secs(self.install_timeout),
+        }
+    }
+
+    /// the simulation to run instead of a real install, on a dry run
+    pub fn simulation(&self) -> Option<Simulation> {
+        self.dry_run.then_some(Simulation { fault: self.fault })
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct InstallArgs {
     /// Skip sending the booloader serial command
@@ -31,11 +83,167 @@ pub struct InstallArgs {
     pub file: PathBuf,
 }
 
+#[derive(Parser, Debug)]
+pub struct RecoverArgs {
+    /// Device model (bridge6 or bridge4)
+    #[clap(short, long)]
+    pub model: Model,
+
+    /// Hardware revision of the device (e.g. 1)
+    #[clap(short, long)]
+    pub revision: char,
+
+    /// Release tag to install (defaults to the latest release in the selected channels)
+    #[clap(short = 't', long, conflicts_with = "file")]
+    pub tag: Option<String>,
+
+    /// Channels to pick the latest release from: stable, beta or draft
+    /// (Comma separated, or repeated. Drafts need a GITHUB_TOKEN)
+    #[clap(
+        short = 'c',
+        long = "channel",
+        value_delimiter = ',',
+        default_value = "stable",
+        verbatim_doc_comment
+    )]
+    pub channels: Vec<Channel>,
+
+    /// Install a local binary/firmware file instead of a release
+    #[clap(short, long)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+    /// Channels to list releases from: stable, beta or draft
+    /// (Comma separated, or repeated. Drafts need a GITHUB_TOKEN)
+    #[clap(
+        short = 'c',
+        long = "channel",
+        value_delimiter = ',',
+        default_value = "stable",
+        verbatim_doc_comment
+    )]
+    pub channels: Vec<Channel>,
+
+    /// Output the releases as JSON
+    #[clap(short, long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckArgs {
+    /// Channels to look for updates in: stable, beta or draft
+    /// (Comma separated, or repeated. Drafts need a GITHUB_TOKEN)
+    #[clap(
+        short = 'c',
+        long = "channel",
+        value_delimiter = ',',
+        default_value = "stable",
+        verbatim_doc_comment
+    )]
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Parser, Debug)]
+pub struct HistoryArgs {
+    /// Only show installs for the device with this UID
+    #[clap(long)]
+    pub device: Option<String>,
+
+    /// Output the history as JSON
+    #[clap(short, long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetupArgs {
+    #[clap(subcommand)]
+    pub target: SetupTarget,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SetupTarget {
+    /// Install udev rules so non-root users can access devices [linux only]
+    /// (If not run as root, the rules are printed instead)
+    #[clap(verbatim_doc_comment)]
+    Udev,
+}
+
+#[derive(Parser, Debug)]
+pub struct DoctorArgs {
+    /// Output the report as JSON
+    #[clap(short, long)]
+    pub json: bool,
+
+    /// Also write the report to a file, so it can be shared
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Also write a zip with the report, logs, settings and install history, for bug reports
+    #[clap(short, long)]
+    pub bundle: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct StationArgs {
+    /// Firmware to flash every unit with - a local file, or a release tag
+    #[clap(short, long)]
+    pub firmware: String,
+
+    /// File to record every unit to - JSON lines for .json/.jsonl, CSV otherwise
+    #[clap(short, long, default_value = "ahoy-station.csv")]
+    pub log: PathBuf,
+
+    /// Don't ring the terminal bell after each unit
+    #[clap(short, long)]
+    pub quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on - anyone who can reach it and has the token can flash the connected device
+    #[clap(short, long, default_value = "127.0.0.1:7878")]
+    pub bind: String,
+
+    /// Directory that local firmware files may be installed from - without it, only releases can be installed
+    #[clap(long)]
+    pub firmware_dir: Option<PathBuf>,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Install a specific binary/firmware file [bypasses GUI]
     Install(InstallArgs),
 
+    /// Reinstall firmware on a device stuck in bootloader/DFU mode
+    Recover(RecoverArgs),
+
+    /// List available firmware releases
+    List(ListArgs),
+
+    /// Check whether a firmware update is available for the connected device
+    /// (Exits with code 100 when an update is available)
+    #[clap(verbatim_doc_comment)]
+    Check(CheckArgs),
+
+    /// Show every firmware install attempt made with ahoy
+    History(HistoryArgs),
+
     /// Update this application to the latest available version
     Update,
+
+    /// Configure this machine for talking to devices
+    Setup(SetupArgs),
+
+    /// Diagnose common device connection and permission issues
+    Doctor(DoctorArgs),
+
+    /// Flash every device that gets plugged in, one after another, until stopped with ctrl-c
+    /// (For production - each unit is recorded as PASS or FAIL to a log file)
+    #[clap(verbatim_doc_comment)]
+    Station(StationArgs),
+
+    /// Serve a local HTTP/JSON API for checking and updating devices from other tools
+    Serve(ServeArgs),
 }
@@ -5,7 +5,8 @@ use iced::{
 };
 use iced_aw::{modal, Card, Modal};
 
-use crate::gui::{style, Message, DEFAULT_PADDING, IMAGE_FLEXI_BRIDGE};
+use crate::gui::{keyboard::Focus, style, Message, DEFAULT_PADDING, IMAGE_FLEXI_BRIDGE};
+use ahoy_core::{command::signature::Signer, i18n::t};
 
 #[derive(Default, Clone)]
 struct ModalState {
@@ -16,12 +17,16 @@ struct ModalState {
 #[derive(Default)]
 pub struct ConfirmModal {
     temp_file: PathBuf,
+    warning: Option<String>,
+    signer: Option<Signer>,
     modal_state: modal::State<ModalState>,
 }
 
 impl ConfirmModal {
-    pub fn show(&mut self, path: PathBuf) {
+    pub fn show(&mut self, path: PathBuf, warning: Option<String>, signer: Option<Signer>) {
         self.temp_file = path;
+        self.warning = warning;
+        self.signer = signer;
         self.modal_state.show(true)
     }
 
@@ -29,60 +34,86 @@ impl ConfirmModal {
         self.modal_state.show(false)
     }
 
-    pub fn view<'a>(&'a mut self, content: Element<'a, Message>) -> Element<'a, Message> {
-        Modal::new(&mut self.modal_state, content, |state| {
-            Card::new(
-                Text::new(String::new()),
-                Column::new()
-                    .spacing(DEFAULT_PADDING)
-                    .align_items(Alignment::Center)
-                    .push(
-                        Text::new("The binary has been downloaded and is ready to install!")
-                            .horizontal_alignment(Horizontal::Center),
-                    )
-                    .push(
-                        Text::new("Next, take a TS or TRS cable and bridge Flexiports 1 and 2")
-                            .horizontal_alignment(Horizontal::Center),
-                    )
-                    .push(Svg::new(IMAGE_FLEXI_BRIDGE.clone()).width(Length::Units(300)))
-                    .push(
-                        Text::new(
-                            "PLEASE DO NOT UNPLUG YOUR DEVICE UNTIL THE INSTALLATION IS FINISHED.",
-                        )
+    pub fn is_open(&self) -> bool {
+        self.modal_state.is_shown()
+    }
+
+    pub fn view<'a>(
+        &'a mut self,
+        content: Element<'a, Message>,
+        focus: Option<Focus>,
+    ) -> Element<'a, Message> {
+        let warning = self.warning.clone();
+        let signer = self.signer.clone();
+        Modal::new(&mut self.modal_state, content, move |state| {
+            let body = Column::new()
+                .spacing(DEFAULT_PADDING)
+                .align_items(Alignment::Center)
+                .push(Text::new(t!("confirm-ready")).horizontal_alignment(Horizontal::Center))
+                .push(Text::new(t!("confirm-bridge")).horizontal_alignment(Horizontal::Center))
+                .push(Svg::new(IMAGE_FLEXI_BRIDGE.clone()).width(Length::Units(300)))
+                .push(
+                    Text::new(t!("confirm-do-not-unplug")).horizontal_alignment(Horizontal::Center),
+                );
+            let body = match &warning {
+                Some(warning) => body.push(
+                    Text::new(warning.as_str())
+                        .color(style::palette().warning)
                         .horizontal_alignment(Horizontal::Center),
-                    ),
-            )
-            .padding_body(DEFAULT_PADDING.into())
-            .foot(
-                Row::new()
-                    .spacing(DEFAULT_PADDING)
-                    .padding(DEFAULT_PADDING / 2)
-                    .width(Length::Fill)
-                    .push(
-                        Button::new(
-                            &mut state.reset_state,
-                            Text::new("Cancel").horizontal_alignment(Horizontal::Center),
-                        )
-                        .on_press(Message::Cancel)
-                        .padding(DEFAULT_PADDING)
+                ),
+                None => body,
+            };
+            // who vouches for the firmware, so an unsigned file doesn't pass unnoticed
+            let body = match &signer {
+                Some(signer) => body.push(
+                    Text::new(signer.to_string())
+                        .color(match signer {
+                            Signer::Trusted(_) => style::palette().success,
+                            Signer::Unsigned => style::palette().muted,
+                        })
+                        .horizontal_alignment(Horizontal::Center),
+                ),
+                None => body,
+            };
+
+            Card::new(Text::new(String::new()), body)
+                .padding_body(DEFAULT_PADDING.into())
+                .foot(
+                    Row::new()
+                        .spacing(DEFAULT_PADDING)
+                        .padding(DEFAULT_PADDING / 2)
                         .width(Length::Fill)
-                        .style(style::Button::CancelAction),
-                    )
-                    .push(
-                        Button::new(
-                            &mut state.ok_state,
-                            Text::new("Install").horizontal_alignment(Horizontal::Center),
+                        .push(
+                            Button::new(
+                                &mut state.reset_state,
+                                Text::new(t!("cancel")).horizontal_alignment(Horizontal::Center),
+                            )
+                            .on_press(Message::Cancel)
+                            .padding(DEFAULT_PADDING)
+                            .width(Length::Fill)
+                            .style(
+                                style::Button::CancelAction
+                                    .focused(focus == Some(Focus::ModalCancel)),
+                            ),
                         )
-                        .on_press(Message::EnterBootloader)
-                        .padding(DEFAULT_PADDING)
-                        .width(Length::Fill)
-                        .style(style::Button::SuccessAction),
-                    ),
-            )
-            .style(style::Card::Modal)
-            .width(Length::Units(400))
-            .on_close(Message::Cancel)
-            .into()
+                        .push(
+                            Button::new(
+                                &mut state.ok_state,
+                                Text::new(t!("install")).horizontal_alignment(Horizontal::Center),
+                            )
+                            .on_press(Message::EnterBootloader)
+                            .padding(DEFAULT_PADDING)
+                            .width(Length::Fill)
+                            .style(
+                                style::Button::SuccessAction
+                                    .focused(focus == Some(Focus::ModalConfirm)),
+                            ),
+                        ),
+                )
+                .style(style::Card::Modal)
+                .width(Length::Units(400))
+                .on_close(Message::Cancel)
+                .into()
         })
         .backdrop(Message::Cancel)
         .on_esc(Message::Cancel)
@@ -1,44 +1,92 @@
 use iced::{alignment::Horizontal, button, Alignment, Button, Element, Length, Row, Space, Text};
 
-use crate::gui::{style, Filter, Message, DEFAULT_PADDING};
+use crate::gui::{keyboard::Focus, style, Message, DEFAULT_PADDING};
+use ahoy_core::{
+    command::channel::{Channel, Channels},
+    i18n::t,
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct ControlsView {
     fetch_button: button::State,
+    history_button: button::State,
+    settings_button: button::State,
     stable_button: button::State,
-    prerelease_button: button::State,
+    beta_button: button::State,
+    draft_button: button::State,
+    local_button: button::State,
 }
 
 impl ControlsView {
-    pub fn view(&mut self, filter: &Filter) -> Element<Message> {
+    pub fn view(
+        &mut self,
+        channels: &Channels,
+        history_open: bool,
+        focus: Option<Focus>,
+    ) -> Element<Message> {
         let ControlsView {
             fetch_button,
+            history_button,
+            settings_button,
             stable_button,
-            prerelease_button,
+            beta_button,
+            draft_button,
+            local_button,
         } = self;
 
-        let filter_button = |state, label, filter, &current_filter| {
-            let label = Text::new(label).horizontal_alignment(Horizontal::Center);
+        // channels toggle independently, so any combination can be shown at once
+        let channel_button = |state, channel: Channel| {
+            let label = Text::new(channel.to_string()).horizontal_alignment(Horizontal::Center);
             let button = Button::new(state, label)
                 .padding(DEFAULT_PADDING)
                 .width(Length::Units(75))
-                .style(if filter == current_filter {
-                    style::Button::FilterSelected
-                } else {
-                    style::Button::FilterOption
-                });
+                .style(
+                    if channels.contains(channel) {
+                        style::Button::FilterSelected
+                    } else {
+                        style::Button::FilterOption
+                    }
+                    .focused(focus == Some(Focus::Channel(channel))),
+                );
 
-            button.on_press(Message::ReleaseFilterChanged(filter))
+            button.on_press(Message::ToggleChannel(channel))
         };
 
         let refresh_button: Element<Message> = Button::new(
             fetch_button,
-            Text::new("Refresh").horizontal_alignment(Horizontal::Center),
+            Text::new(t!("controls-refresh")).horizontal_alignment(Horizontal::Center),
         )
         .on_press(Message::FetchReleases)
         .padding(DEFAULT_PADDING)
         .width(Length::Units(100))
-        .style(style::Button::SuccessAction)
+        .style(style::Button::SuccessAction.focused(focus == Some(Focus::Refresh)))
+        .into();
+
+        let history_button: Element<Message> = Button::new(
+            history_button,
+            Text::new(t!("controls-history")).horizontal_alignment(Horizontal::Center),
+        )
+        .on_press(Message::ToggleHistory)
+        .padding(DEFAULT_PADDING)
+        .width(Length::Units(100))
+        .style(
+            if history_open {
+                style::Button::FilterSelected
+            } else {
+                style::Button::FilterOption
+            }
+            .focused(focus == Some(Focus::History)),
+        )
+        .into();
+
+        let settings_button: Element<Message> = Button::new(
+            settings_button,
+            Text::new(t!("settings")).horizontal_alignment(Horizontal::Center),
+        )
+        .on_press(Message::ShowSettings)
+        .padding(DEFAULT_PADDING)
+        .width(Length::Units(100))
+        .style(style::Button::FilterOption.focused(focus == Some(Focus::Settings)))
         .into();
 
         Row::new()
@@ -49,20 +97,14 @@ impl ControlsView {
                 Row::new()
                     .spacing(DEFAULT_PADDING)
                     .width(Length::Shrink)
-                    .push(filter_button(
-                        stable_button,
-                        "Stable",
-                        Filter::Stable,
-                        filter,
-                    ))
-                    .push(filter_button(
-                        prerelease_button,
-                        "Beta",
-                        Filter::PreRelease,
-                        filter,
-                    )),
+                    .push(channel_button(stable_button, Channel::Stable))
+                    .push(channel_button(beta_button, Channel::Beta))
+                    .push(channel_button(draft_button, Channel::Draft))
+                    .push(channel_button(local_button, Channel::Local)),
             )
             .push(Space::new(Length::Fill, Length::Shrink))
+            .push(settings_button)
+            .push(history_button)
             .push(refresh_button)
             .into()
     }
@@ -1,29 +1,40 @@
-use iced::{Alignment, Color, Column, Container, Element, Length, Row, Text};
+use iced::{
+    alignment::Horizontal, button, Alignment, Button, Column, Container, Element, Length, Row,
+    Space, Text,
+};
 use iced_native::widget::Svg;
 use pirate_midi_rs::check::CheckResponse;
 
 use crate::gui::{
-    Message, DEFAULT_PADDING, IMAGE_BRIDGE_4_DARK, IMAGE_BRIDGE_6_DARK, SECONDARY_FONT,
+    style, themed_svg, FirmwareStatus, Message, DEFAULT_PADDING, IMAGE_BRIDGE_4_DARK,
+    IMAGE_BRIDGE_4_LIGHT, IMAGE_BRIDGE_6_DARK, IMAGE_BRIDGE_6_LIGHT, SECONDARY_FONT,
     SECONDARY_FONT_SIZE,
 };
+use ahoy_core::i18n::t;
 
 #[derive(Default, Debug, Clone)]
-pub struct DeviceView {}
+pub struct DeviceView {
+    update_button: button::State,
+}
 
 impl DeviceView {
-    pub fn view<'a>(&self, conn: &'a CheckResponse) -> Element<'a, Message> {
+    pub fn view<'a>(
+        &'a mut self,
+        conn: &'a CheckResponse,
+        firmware: &'a FirmwareStatus,
+    ) -> Element<'a, Message> {
         // pull the brand for the device
         let model_brand = match conn.device_model.trim().to_lowercase().as_str() {
-            "bridge4" => Svg::new(IMAGE_BRIDGE_4_DARK.clone()),
-            _ => Svg::new(IMAGE_BRIDGE_6_DARK.clone()),
+            "bridge4" => Svg::new(themed_svg(&IMAGE_BRIDGE_4_DARK, &IMAGE_BRIDGE_4_LIGHT)),
+            _ => Svg::new(themed_svg(&IMAGE_BRIDGE_6_DARK, &IMAGE_BRIDGE_6_LIGHT)),
         }
         .width(Length::Units(100));
 
         // build the brand column
         let status_text: Row<Message> = Row::new()
             .push(
-                Text::new("CONNECTED")
-                    .color(Color::from_rgb8(100, 183, 93))
+                Text::new(t!("device-connected"))
+                    .color(style::palette().success)
                     .font(SECONDARY_FONT)
                     .size(SECONDARY_FONT_SIZE),
             )
@@ -40,7 +51,7 @@ impl DeviceView {
             .width(Length::Fill)
             .push(status_text)
             .push(
-                Text::new(format!("UID: {}", conn.uid))
+                Text::new(t!("device-uid", uid = conn.uid.as_str()))
                     .font(SECONDARY_FONT)
                     .size(SECONDARY_FONT_SIZE),
             );
@@ -55,7 +66,52 @@ impl DeviceView {
             .push(model_brand)
             .into();
 
+        // let the user know if there's newer firmware, with a shortcut to install it
+        let banner: Element<Message> = match firmware {
+            FirmwareStatus::Unknown => Space::new(Length::Shrink, Length::Shrink).into(),
+            FirmwareStatus::Current => Row::new()
+                .padding([0, DEFAULT_PADDING])
+                .push(
+                    Text::new(t!(
+                        "device-up-to-date",
+                        version = conn.firmware_version.as_str()
+                    ))
+                    .color(style::palette().success)
+                    .font(SECONDARY_FONT)
+                    .size(SECONDARY_FONT_SIZE),
+                )
+                .into(),
+            FirmwareStatus::Available(release) => Row::new()
+                .align_items(Alignment::Center)
+                .padding([0, DEFAULT_PADDING])
+                .spacing(DEFAULT_PADDING)
+                .push(
+                    Text::new(t!(
+                        "device-update-available",
+                        version = release.tag_name.as_str(),
+                        installed = conn.firmware_version.as_str()
+                    ))
+                    .color(style::palette().warning)
+                    .font(SECONDARY_FONT)
+                    .size(SECONDARY_FONT_SIZE),
+                )
+                .push(Space::with_width(Length::Fill))
+                .push(
+                    Button::new(
+                        &mut self.update_button,
+                        Text::new(t!("device-update")).horizontal_alignment(Horizontal::Center),
+                    )
+                    .on_press(Message::InstallUpdate)
+                    .padding(DEFAULT_PADDING / 2)
+                    .width(Length::Units(100))
+                    .style(style::Button::SuccessAction),
+                )
+                .into(),
+        };
+
         // wrap everything in a container.
-        Container::new(device_row).width(Length::Fill).into()
+        Container::new(Column::new().push(device_row).push(banner))
+            .width(Length::Fill)
+            .into()
     }
 }
@@ -0,0 +1,98 @@
+use iced::{
+    alignment::Horizontal, scrollable, Alignment, Column, Container, Element, Length, Row, Rule,
+    Scrollable, Text,
+};
+
+use crate::gui::{style, Message, DEFAULT_PADDING, SECONDARY_FONT, SECONDARY_FONT_SIZE};
+use ahoy_core::{
+    command::history::{format_timestamp, JournalEntry, Outcome, Source},
+    i18n::t,
+};
+
+#[derive(Debug, Default)]
+pub struct HistoryView {
+    scroll: scrollable::State,
+}
+
+impl HistoryView {
+    pub fn view<'a>(&'a mut self, entries: &'a [JournalEntry]) -> Element<'a, Message> {
+        if entries.is_empty() {
+            return Container::new(Text::new(t!("history-empty")))
+                .center_x()
+                .center_y()
+                .height(Length::Fill)
+                .width(Length::Fill)
+                .into();
+        }
+
+        let palette = style::palette();
+        let list = entries.iter().fold(
+            Column::new()
+                .padding(DEFAULT_PADDING)
+                .spacing(DEFAULT_PADDING),
+            |column, entry| {
+                let color = match entry.outcome {
+                    Outcome::Success => palette.success,
+                    Outcome::Failed => palette.danger,
+                    Outcome::Cancelled => palette.caution,
+                };
+
+                let source = match &entry.source {
+                    Source::Release { tag } => tag.clone(),
+                    Source::File { name, sha256 } => {
+                        format!("{} ({})", name, sha256.get(..12).unwrap_or(sha256))
+                    }
+                };
+
+                let mut details = Column::new()
+                    .width(Length::Fill)
+                    .push(
+                        Text::new(format!(
+                            "{} -> {}",
+                            entry
+                                .previous_version
+                                .clone()
+                                .unwrap_or_else(|| t!("history-unknown")),
+                            source
+                        ))
+                        .font(SECONDARY_FONT)
+                        .size(SECONDARY_FONT_SIZE),
+                    )
+                    .push(
+                        Text::new(t!(
+                            "history-via",
+                            time = format_timestamp(entry.timestamp),
+                            client = entry.client.as_str()
+                        ))
+                        .font(SECONDARY_FONT)
+                        .size(SECONDARY_FONT_SIZE - 4),
+                    );
+                if let Some(error) = &entry.error {
+                    details = details.push(Text::new(error).size(SECONDARY_FONT_SIZE - 4));
+                }
+
+                column
+                    .push(
+                        Row::new()
+                            .align_items(Alignment::Center)
+                            .spacing(DEFAULT_PADDING)
+                            .push(details)
+                            .push(
+                                Text::new(entry.outcome.to_string())
+                                    .color(color)
+                                    .font(SECONDARY_FONT)
+                                    .size(SECONDARY_FONT_SIZE)
+                                    .horizontal_alignment(Horizontal::Right),
+                            ),
+                    )
+                    .push(Rule::horizontal(1))
+            },
+        );
+
+        Scrollable::new(&mut self.scroll)
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .push(list)
+            .into()
+    }
+}
@@ -1,34 +1,153 @@
-use crate::gui::{Message, DEFAULT_PADDING};
+use std::time::{Duration, Instant};
 
-use iced::{Alignment, Color, Column, Container, Element, Length, ProgressBar, Row, Space, Text};
-use rusb::{Context, Device};
+use crate::gui::{style, Message, DEFAULT_PADDING, SECONDARY_FONT, SECONDARY_FONT_SIZE};
+use ahoy_core::{
+    command::{github::DownloadProgress, session::Stage, transfer::ProgressEvent},
+    i18n::t,
+};
+
+use iced::{
+    alignment::Horizontal, button, Alignment, Button, Column, Container, Element, Length,
+    ProgressBar, Row, Space, Text,
+};
+
+/// accumulates progress events from the DFU layer for display
+#[derive(Debug, Default, Clone)]
+pub struct ProgressTracker {
+    phase: Option<ProgressEvent>,
+    bytes: u64,
+    total: u64,
+    started: Option<Instant>,
+}
+
+impl ProgressTracker {
+    pub fn update(&mut self, event: ProgressEvent) {
+        if let ProgressEvent::Writing { bytes, total } = event {
+            self.started.get_or_insert_with(Instant::now);
+            self.bytes = bytes;
+            self.total = total;
+        }
+        self.phase = Some(event);
+    }
+
+    pub fn phase(&self) -> Option<ProgressEvent> {
+        self.phase
+    }
+
+    pub fn percentage(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            (self.bytes as f32 / self.total as f32) * 100.0
+        }
+    }
+
+    /// bytes per second since the first block was written
+    pub fn throughput(&self) -> Option<f64> {
+        let elapsed = self.started?.elapsed().as_secs_f64();
+        (elapsed > 0.0).then(|| self.bytes as f64 / elapsed)
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let throughput = self.throughput().filter(|rate| *rate > 0.0)?;
+        let remaining = self.total.saturating_sub(self.bytes) as f64;
+        Some(Duration::from_secs_f64(remaining / throughput))
+    }
+}
+
+/// a download's progress, shown until the firmware is ready to install
+pub fn download_view<'a>(progress: DownloadProgress) -> Element<'a, Message> {
+    let percentage = if progress.total == 0 {
+        0.0
+    } else {
+        (progress.bytes as f32 / progress.total as f32) * 100.0
+    };
+
+    Column::new()
+        .padding(DEFAULT_PADDING)
+        .spacing(DEFAULT_PADDING / 2)
+        .align_items(Alignment::Center)
+        .width(Length::Fill)
+        .push(
+            Text::new(t!(
+                "download-progress",
+                downloaded = progress.bytes / 1024,
+                total = progress.total / 1024
+            ))
+            .font(SECONDARY_FONT)
+            .size(SECONDARY_FONT_SIZE),
+        )
+        .push(ProgressBar::new(0.0..=100.0, percentage))
+        .into()
+}
 
 #[derive(Debug, Default)]
-pub struct InstallView;
+pub struct InstallView {
+    cancel_button: button::State,
+}
 
 impl InstallView {
-    pub fn view<'a>(&self, progress: f32, dfu: &Option<Device<Context>>) -> Element<'a, Message> {
-        let status_text: Row<Message> = if dfu.is_some() {
-            Row::new().push(Text::new("CONNECTED").color(Color::from_rgb8(100, 183, 93)))
+    pub fn view<'a>(
+        &'a mut self,
+        progress: &ProgressTracker,
+        stage: &Stage,
+        cancelling: bool,
+    ) -> Element<'a, Message> {
+        let waiting = matches!(stage, Stage::AwaitingBootloader { .. });
+        let status_text: Row<Message> = if waiting {
+            Row::new().push(Text::new(t!("install-waiting-device")).color(style::palette().caution))
         } else {
-            Row::new().push(Text::new("WAITING FOR DEVICE").color(Color::from_rgb8(142, 110, 34)))
+            Row::new().push(Text::new(t!("device-connected")).color(style::palette().success))
         };
 
-        let message_text: Row<Message> = if dfu.is_some() {
-            if progress > 0.1 {
-                Row::new().push(Text::new("Installing firmware..."))
-            } else {
-                Row::new().push(Text::new("Preparing device for new firmware..."))
-            }
-        } else {
-            Row::new().push(Text::new("Waiting for device to enter bootloader mode..."))
+        let message = match progress.phase() {
+            _ if waiting => t!("install-waiting-bootloader"),
+            _ if matches!(stage, Stage::Verifying) => t!("install-verifying"),
+            _ if cancelling => t!("install-cancelling"),
+            None => t!("install-preparing"),
+            Some(ProgressEvent::Writing { .. }) => t!("install-writing"),
+            Some(ProgressEvent::Manifesting) => t!("install-finalizing"),
+            Some(ProgressEvent::Done) => t!("install-done"),
         };
+        let message_text: Row<Message> = Row::new().push(Text::new(message));
 
         // progress bar
-        let progress_bar: Element<Message> = if progress < 0.1 {
+        let progress_bar: Element<Message> = if progress.percentage() < 0.1 {
             Space::new(Length::Units(0), Length::Units(0)).into()
         } else {
-            ProgressBar::new(0.0..=100.0, progress).into()
+            ProgressBar::new(0.0..=100.0, progress.percentage()).into()
+        };
+
+        // throughput and time remaining
+        let stats_text: Element<Message> = match (progress.throughput(), progress.eta()) {
+            (Some(rate), Some(eta)) => Text::new(t!(
+                "install-stats",
+                rate = format!("{:.1}", rate / 1024.0),
+                seconds = eta.as_secs()
+            ))
+            .font(SECONDARY_FONT)
+            .size(SECONDARY_FONT_SIZE)
+            .into(),
+            _ => Space::new(Length::Units(0), Length::Units(0)).into(),
+        };
+
+        // the transfer can only be stopped once it's running
+        let cancel_button: Element<Message> = if matches!(stage, Stage::Flashing) {
+            let button = Button::new(
+                &mut self.cancel_button,
+                Text::new(t!("cancel")).horizontal_alignment(Horizontal::Center),
+            )
+            .padding(DEFAULT_PADDING)
+            .width(Length::Units(130))
+            .style(style::Button::CancelAction);
+
+            if cancelling {
+                button.into()
+            } else {
+                button.on_press(Message::CancelInstall).into()
+            }
+        } else {
+            Space::new(Length::Units(0), Length::Units(0)).into()
         };
 
         let primary_column: Column<Message> = Column::new()
@@ -37,6 +156,8 @@ impl InstallView {
             .push(status_text)
             .push(message_text)
             .push(progress_bar)
+            .push(stats_text)
+            .push(cancel_button)
             .align_items(Alignment::Center)
             .width(Length::Fill);
 
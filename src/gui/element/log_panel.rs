@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use iced::{
+    alignment::Horizontal, button, scrollable, Alignment, Button, Column, Container, Element,
+    Length, Row, Rule, Scrollable, Space, Text,
+};
+use log::Level;
+
+use crate::gui::{
+    keyboard::Focus, style, Message, DEFAULT_PADDING, SECONDARY_FONT, SECONDARY_FONT_SIZE,
+};
+use ahoy_core::{command::logging::LogLine, i18n::t};
+
+/// how many of the captured lines the panel shows
+const VISIBLE_LINES: usize = 200;
+
+/// where the last diagnostics export got to
+#[derive(Debug, Default, Clone)]
+pub enum ExportStatus {
+    #[default]
+    Idle,
+    Exporting,
+    Saved(PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug, Default)]
+pub struct LogPanel {
+    toggle_button: button::State,
+    export_button: button::State,
+    scroll: scrollable::State,
+}
+
+impl LogPanel {
+    pub fn view<'a>(
+        &'a mut self,
+        open: bool,
+        lines: &'a [LogLine],
+        export: &ExportStatus,
+        focus: Option<Focus>,
+    ) -> Element<'a, Message> {
+        let LogPanel {
+            toggle_button,
+            export_button,
+            scroll,
+        } = self;
+        let palette = style::palette();
+
+        let toggle = Button::new(
+            toggle_button,
+            Text::new(if open { t!("log-hide") } else { t!("log-show") })
+                .size(SECONDARY_FONT_SIZE)
+                .horizontal_alignment(Horizontal::Center),
+        )
+        .on_press(Message::ToggleLog)
+        .padding(DEFAULT_PADDING / 2)
+        .style(
+            if open {
+                style::Button::FilterSelected
+            } else {
+                style::Button::FilterOption
+            }
+            .focused(focus == Some(Focus::Log)),
+        );
+
+        let status: Element<Message> = match export {
+            ExportStatus::Idle => Space::with_width(Length::Fill).into(),
+            ExportStatus::Exporting => Text::new(t!("log-exporting"))
+                .size(SECONDARY_FONT_SIZE)
+                .color(palette.muted)
+                .width(Length::Fill)
+                .into(),
+            ExportStatus::Saved(path) => {
+                Text::new(t!("log-exported", path = path.display().to_string()))
+                    .size(SECONDARY_FONT_SIZE)
+                    .color(palette.success)
+                    .width(Length::Fill)
+                    .into()
+            }
+            ExportStatus::Failed(reason) => {
+                Text::new(t!("log-export-failed", reason = reason.as_str()))
+                    .size(SECONDARY_FONT_SIZE)
+                    .color(palette.danger)
+                    .width(Length::Fill)
+                    .into()
+            }
+        };
+
+        let mut export_button = Button::new(
+            export_button,
+            Text::new(t!("log-export"))
+                .size(SECONDARY_FONT_SIZE)
+                .horizontal_alignment(Horizontal::Center),
+        )
+        .padding(DEFAULT_PADDING / 2)
+        .style(style::Button::SuccessAction.focused(focus == Some(Focus::Export)));
+        if !matches!(export, ExportStatus::Exporting) {
+            export_button = export_button.on_press(Message::ExportDiagnostics);
+        }
+
+        let header = Row::new()
+            .padding([0, DEFAULT_PADDING, DEFAULT_PADDING / 2, DEFAULT_PADDING])
+            .spacing(DEFAULT_PADDING)
+            .align_items(Alignment::Center)
+            .push(toggle)
+            .push(status)
+            .push(export_button);
+
+        let mut panel = Column::new().push(Rule::horizontal(1)).push(header);
+        if !open {
+            return panel.into();
+        }
+
+        // newest first, so nothing needs scrolling to see what just happened
+        let list = if lines.is_empty() {
+            Column::new().push(Text::new(t!("log-empty")).size(SECONDARY_FONT_SIZE))
+        } else {
+            lines
+                .iter()
+                .rev()
+                .take(VISIBLE_LINES)
+                .fold(Column::new(), |column, line| {
+                    let color = match line.level {
+                        Level::Error => palette.danger,
+                        Level::Warn => palette.caution,
+                        Level::Info => palette.text,
+                        Level::Debug | Level::Trace => palette.muted,
+                    };
+                    column.push(
+                        Text::new(line.to_string())
+                            .font(SECONDARY_FONT)
+                            .size(SECONDARY_FONT_SIZE - 6)
+                            .color(color),
+                    )
+                })
+        };
+
+        panel = panel.push(
+            Container::new(
+                Scrollable::new(scroll)
+                    .width(Length::Fill)
+                    .padding([0, DEFAULT_PADDING])
+                    .push(list),
+            )
+            .height(Length::Units(200))
+            .width(Length::Fill),
+        );
+        panel.into()
+    }
+}
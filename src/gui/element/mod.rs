@@ -1,6 +1,11 @@
 pub mod confirm_modal;
 pub mod controls;
 pub mod device;
+pub mod history;
 pub mod install;
+pub mod log_panel;
+pub mod notes;
+pub mod recover;
+pub mod settings_modal;
 pub mod update_modal;
 pub mod version;
@@ -0,0 +1,125 @@
+use std::slice::IterMut;
+
+use iced::{button, Button, Column, Container, Element, Length, Row, Rule, Space, Text};
+use iced_aw::Wrap;
+
+use crate::gui::{
+    markdown::{Block, Span},
+    style, Message, DEFAULT_FONT_SIZE, DEFAULT_HEADING_FONT_SIZE, DEFAULT_PADDING, SECONDARY_FONT,
+    SECONDARY_FONT_SIZE,
+};
+
+/// renders parsed release notes
+#[derive(Debug, Default, Clone)]
+pub struct ReleaseNotes {
+    links: Vec<button::State>,
+}
+
+impl ReleaseNotes {
+    pub fn view<'a>(&'a mut self, blocks: &'a [Block]) -> Element<'a, Message> {
+        // every link needs its own button state
+        let link_count = blocks
+            .iter()
+            .flat_map(|block| match block {
+                Block::Heading(_, spans)
+                | Block::Paragraph(spans)
+                | Block::ListItem { spans, .. } => spans.as_slice(),
+                _ => [].as_slice(),
+            })
+            .filter(|span| span.link.is_some())
+            .count();
+        self.links.resize_with(link_count, button::State::default);
+        let mut links = self.links.iter_mut();
+
+        blocks
+            .iter()
+            .fold(
+                Column::new().spacing(DEFAULT_PADDING).width(Length::Fill),
+                |column, block| {
+                    column.push(match block {
+                        Block::Heading(level, spans) => {
+                            let size = match level {
+                                1 => DEFAULT_HEADING_FONT_SIZE + 4,
+                                2 => DEFAULT_HEADING_FONT_SIZE,
+                                _ => DEFAULT_FONT_SIZE + 2,
+                            };
+                            spans_view(spans, size, &mut links)
+                        }
+                        Block::Paragraph(spans) => {
+                            spans_view(spans, SECONDARY_FONT_SIZE, &mut links)
+                        }
+                        Block::ListItem {
+                            depth,
+                            marker,
+                            spans,
+                        } => Row::new()
+                            .spacing(DEFAULT_PADDING / 2)
+                            .push(Space::with_width(Length::Units(
+                                DEFAULT_PADDING * 2 * *depth as u16,
+                            )))
+                            .push(
+                                Text::new(marker.as_str())
+                                    .size(SECONDARY_FONT_SIZE)
+                                    .width(Length::Units(DEFAULT_PADDING * 2)),
+                            )
+                            .push(spans_view(spans, SECONDARY_FONT_SIZE, &mut links))
+                            .into(),
+                        Block::Code(code) => Container::new(
+                            Text::new(code.as_str())
+                                .font(SECONDARY_FONT)
+                                .size(SECONDARY_FONT_SIZE - 2),
+                        )
+                        .padding(DEFAULT_PADDING)
+                        .width(Length::Fill)
+                        .style(style::Container::Code)
+                        .into(),
+                        Block::Rule => Rule::horizontal(1).into(),
+                    })
+                },
+            )
+            .into()
+    }
+}
+
+/// lay out a run of styled text, wrapping on word boundaries
+fn spans_view<'a>(
+    spans: &'a [Span],
+    size: u16,
+    links: &mut IterMut<'a, button::State>,
+) -> Element<'a, Message> {
+    spans
+        .iter()
+        .fold(Wrap::new().spacing(5).line_spacing(2), |wrap, span| {
+            let palette = style::palette();
+            match (&span.link, span.code) {
+                (Some(url), _) => {
+                    let state = links.next().expect("link button state went missing!");
+                    wrap.push(
+                        Button::new(state, Text::new(span.text.trim()).size(size))
+                            .on_press(Message::OpenUrl(url.clone()))
+                            .padding(0)
+                            .style(style::Button::Link),
+                    )
+                }
+                // inline code stays together, in a monospaced font
+                (None, true) => wrap.push(
+                    Text::new(span.text.as_str())
+                        .font(SECONDARY_FONT)
+                        .size(size - 2)
+                        .color(style::palette().prerelease),
+                ),
+                (None, false) => span.text.split_whitespace().fold(wrap, |wrap, word| {
+                    let text = Text::new(word).size(size);
+                    wrap.push(if span.strong {
+                        text.color(palette.release)
+                    } else if span.emphasis {
+                        // muted, since we only ship a regular weight font
+                        text.color(palette.muted)
+                    } else {
+                        text
+                    })
+                }),
+            }
+        })
+        .into()
+}
@@ -0,0 +1,159 @@
+use iced::{
+    alignment::Horizontal, button, pick_list, text_input, Alignment, Button, Column, Container,
+    Element, Length, PickList, Row, Space, Text, TextInput,
+};
+
+use crate::gui::{
+    style, Error, Message, RecoveryOptions, DEFAULT_HEADING_FONT_SIZE, DEFAULT_PADDING,
+};
+use ahoy_core::{
+    command::{device::Model, github::Release},
+    i18n::t,
+};
+
+#[derive(Debug, Default)]
+pub struct RecoverView {
+    model_list: pick_list::State<Model>,
+    revision_list: pick_list::State<char>,
+    release_list: pick_list::State<String>,
+    file_input: text_input::State,
+    recover_button: button::State,
+}
+
+impl RecoverView {
+    pub fn view<'a>(
+        &'a mut self,
+        error: &'a Option<Error>,
+        options: &'a RecoveryOptions,
+        releases: &'a Option<Vec<Release>>,
+    ) -> Element<'a, Message> {
+        let model = options.model.id();
+
+        // every hardware revision we have firmware for
+        let mut revisions: Vec<char> = releases
+            .iter()
+            .flatten()
+            .flat_map(|release| release.revisions(model))
+            .collect();
+        revisions.sort_unstable();
+        revisions.dedup();
+
+        // only offer releases that have an asset for the selected model and revision
+        let tags: Vec<String> = match options.revision {
+            Some(revision) => releases
+                .iter()
+                .flatten()
+                .filter(|release| !release.draft && release.find_asset(model, revision).is_some())
+                .map(|release| release.tag_name.clone())
+                .collect(),
+            None => vec![],
+        };
+
+        let error_message: Element<Message> = match error {
+            Some(error) => Container::new(
+                Text::new(error.to_string())
+                    .horizontal_alignment(Horizontal::Center)
+                    .width(Length::Fill),
+            )
+            .padding(DEFAULT_PADDING)
+            .width(Length::Fill)
+            .style(style::Container::Error)
+            .into(),
+            None => Space::new(Length::Shrink, Length::Shrink).into(),
+        };
+
+        let labeled = |label: &str, control: Element<'a, Message>| -> Element<'a, Message> {
+            Row::new()
+                .align_items(Alignment::Center)
+                .spacing(DEFAULT_PADDING)
+                .push(Text::new(label).width(Length::Units(100)))
+                .push(control)
+                .into()
+        };
+
+        let release_control: Element<Message> = if releases.is_none() {
+            Text::new(t!("loading")).into()
+        } else {
+            PickList::new(
+                &mut self.release_list,
+                tags,
+                options.tag.clone(),
+                Message::RecoverReleaseChanged,
+            )
+            .placeholder(t!("recover-select-release"))
+            .width(Length::Units(250))
+            .into()
+        };
+
+        let can_recover = !options.file.trim().is_empty()
+            || (options.revision.is_some() && options.tag.is_some());
+        let recover_button = Button::new(
+            &mut self.recover_button,
+            Text::new(t!("recover-button")).horizontal_alignment(Horizontal::Center),
+        )
+        .padding(DEFAULT_PADDING)
+        .width(Length::Units(250))
+        .style(style::Button::SuccessAction);
+        let recover_button = if can_recover {
+            recover_button.on_press(Message::Recover)
+        } else {
+            recover_button
+        };
+
+        let primary_column: Column<Message> = Column::new()
+            .padding(DEFAULT_PADDING)
+            .spacing(DEFAULT_PADDING)
+            .align_items(Alignment::Center)
+            .width(Length::Units(500))
+            .push(Text::new(t!("recover-title")).color(style::palette().caution))
+            .push(Text::new(t!("recover-detected")).size(DEFAULT_HEADING_FONT_SIZE))
+            .push(Text::new(t!("recover-explanation")).horizontal_alignment(Horizontal::Center))
+            .push(error_message)
+            .push(Space::with_height(Length::Units(DEFAULT_PADDING)))
+            .push(labeled(
+                &t!("recover-model"),
+                PickList::new(
+                    &mut self.model_list,
+                    &Model::ALL[..],
+                    Some(options.model),
+                    Message::RecoverModelChanged,
+                )
+                .width(Length::Units(250))
+                .into(),
+            ))
+            .push(labeled(
+                &t!("recover-revision"),
+                PickList::new(
+                    &mut self.revision_list,
+                    revisions,
+                    options.revision,
+                    Message::RecoverRevisionChanged,
+                )
+                .placeholder(t!("recover-select-revision"))
+                .width(Length::Units(250))
+                .into(),
+            ))
+            .push(labeled(&t!("recover-release"), release_control))
+            .push(Text::new(t!("or")))
+            .push(labeled(
+                &t!("local-file"),
+                TextInput::new(
+                    &mut self.file_input,
+                    "/path/to/firmware.bin",
+                    &options.file,
+                    Message::RecoverFileChanged,
+                )
+                .padding(DEFAULT_PADDING / 2)
+                .width(Length::Units(250))
+                .into(),
+            ))
+            .push(Space::with_height(Length::Units(DEFAULT_PADDING)))
+            .push(recover_button);
+
+        // wrap everything in a container.
+        Container::new(primary_column)
+            .width(Length::Fill)
+            .center_x()
+            .into()
+    }
+}
@@ -0,0 +1,146 @@
+use iced::{
+    alignment::Horizontal, button, pick_list, Alignment, Button, Column, Element, Length, PickList,
+    Row, Text,
+};
+use iced_aw::{modal, Card, Modal};
+
+use std::fmt;
+
+use crate::gui::{keyboard::Focus, style, Message, DEFAULT_PADDING};
+use ahoy_core::{
+    command::settings::{Settings, ThemePreference},
+    i18n::{t, Locale, LOCALES},
+};
+
+/// a language offered in settings - following the system, or one we ship a catalog for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageChoice {
+    System,
+    Locale(&'static Locale),
+}
+
+impl LanguageChoice {
+    pub fn of(settings: &Settings) -> LanguageChoice {
+        settings
+            .language
+            .as_deref()
+            .and_then(|code| LOCALES.iter().find(|locale| locale.code == code))
+            .map_or(LanguageChoice::System, LanguageChoice::Locale)
+    }
+
+    pub fn code(&self) -> Option<String> {
+        match self {
+            LanguageChoice::System => None,
+            LanguageChoice::Locale(locale) => Some(locale.code.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LanguageChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanguageChoice::System => write!(f, "{}", t!("settings-system-language")),
+            LanguageChoice::Locale(locale) => write!(f, "{}", locale),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ModalState {
+    theme_list: pick_list::State<ThemePreference>,
+    language_list: pick_list::State<LanguageChoice>,
+    close_state: button::State,
+}
+
+#[derive(Default)]
+pub struct SettingsModal {
+    modal_state: modal::State<ModalState>,
+}
+
+impl SettingsModal {
+    pub fn show(&mut self) {
+        self.modal_state.show(true)
+    }
+
+    pub fn hide(&mut self) {
+        self.modal_state.show(false)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.modal_state.is_shown()
+    }
+
+    pub fn view<'a>(
+        &'a mut self,
+        content: Element<'a, Message>,
+        settings: &'a Settings,
+        focus: Option<Focus>,
+    ) -> Element<'a, Message> {
+        Modal::new(&mut self.modal_state, content, move |state| {
+            let languages: Vec<LanguageChoice> = std::iter::once(LanguageChoice::System)
+                .chain(LOCALES.iter().map(LanguageChoice::Locale))
+                .collect();
+
+            Card::new(
+                Text::new(t!("settings")),
+                Column::new()
+                    .spacing(DEFAULT_PADDING)
+                    .push(
+                        Row::new()
+                            .align_items(Alignment::Center)
+                            .spacing(DEFAULT_PADDING)
+                            .push(Text::new(t!("settings-theme")).width(Length::Units(100)))
+                            .push(
+                                PickList::new(
+                                    &mut state.theme_list,
+                                    &ThemePreference::ALL[..],
+                                    Some(settings.theme),
+                                    Message::ThemeChanged,
+                                )
+                                .width(Length::Fill),
+                            ),
+                    )
+                    .push(
+                        Row::new()
+                            .align_items(Alignment::Center)
+                            .spacing(DEFAULT_PADDING)
+                            .push(Text::new(t!("settings-language")).width(Length::Units(100)))
+                            .push(
+                                PickList::new(
+                                    &mut state.language_list,
+                                    languages,
+                                    Some(LanguageChoice::of(settings)),
+                                    Message::LanguageChanged,
+                                )
+                                .width(Length::Fill),
+                            ),
+                    ),
+            )
+            .padding_body(DEFAULT_PADDING.into())
+            .foot(
+                Row::new()
+                    .padding(DEFAULT_PADDING / 2)
+                    .width(Length::Fill)
+                    .push(
+                        Button::new(
+                            &mut state.close_state,
+                            Text::new(t!("close")).horizontal_alignment(Horizontal::Center),
+                        )
+                        .on_press(Message::HideSettings)
+                        .padding(DEFAULT_PADDING)
+                        .width(Length::Fill)
+                        .style(
+                            style::Button::CancelAction.focused(focus == Some(Focus::ModalCancel)),
+                        ),
+                    ),
+            )
+            .style(style::Card::Modal)
+            .width(Length::Units(400))
+            .on_close(Message::HideSettings)
+            .into()
+        })
+        .on_esc(Message::HideSettings)
+        .style(style::Modal::Default)
+        .into()
+    }
+}
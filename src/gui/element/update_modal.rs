@@ -1,7 +1,8 @@
 use iced::{alignment::Horizontal, button, Alignment, Button, Column, Element, Length, Row, Text};
 use iced_aw::{modal, Card, Modal};
 
-use crate::gui::{style, Message, DEFAULT_PADDING};
+use crate::gui::{keyboard::Focus, style, Message, DEFAULT_PADDING};
+use ahoy_core::i18n::t;
 
 #[derive(Default, Clone)]
 struct ModalState {
@@ -25,20 +26,32 @@ impl UpdateModal {
         self.modal_state.show(false)
     }
 
-    pub fn view<'a>(&'a mut self, content: Element<'a, Message>) -> Element<'a, Message> {
-        Modal::new(&mut self.modal_state, content, |state| {
+    pub fn is_open(&self) -> bool {
+        self.modal_state.is_shown()
+    }
+
+    pub fn view<'a>(
+        &'a mut self,
+        content: Element<'a, Message>,
+        focus: Option<Focus>,
+    ) -> Element<'a, Message> {
+        let new_version = self.new_version.clone();
+        Modal::new(&mut self.modal_state, content, move |state| {
             Card::new(
                 Text::new(String::new()),
                 Column::new()
                     .spacing(DEFAULT_PADDING)
                     .align_items(Alignment::Center)
                     .push(
-                        Text::new("There is an update available for this application!")
+                        Text::new(t!("update-available-title"))
                             .horizontal_alignment(Horizontal::Center),
                     )
                     .push(
-                        Text::new(format!("Version: {} is now available!", self.new_version))
-                            .horizontal_alignment(Horizontal::Center),
+                        Text::new(t!(
+                            "update-available-version",
+                            version = new_version.as_str()
+                        ))
+                        .horizontal_alignment(Horizontal::Center),
                     ),
             )
             .padding_body(DEFAULT_PADDING.into())
@@ -50,22 +63,29 @@ impl UpdateModal {
                     .push(
                         Button::new(
                             &mut state.reset_state,
-                            Text::new("Dismiss").horizontal_alignment(Horizontal::Center),
+                            Text::new(t!("update-dismiss"))
+                                .horizontal_alignment(Horizontal::Center),
                         )
                         .on_press(Message::IgnoreUpdate)
                         .padding(DEFAULT_PADDING)
                         .width(Length::Fill)
-                        .style(style::Button::CancelAction),
+                        .style(
+                            style::Button::CancelAction.focused(focus == Some(Focus::ModalCancel)),
+                        ),
                     )
                     .push(
                         Button::new(
                             &mut state.ok_state,
-                            Text::new("Update and Quit").horizontal_alignment(Horizontal::Center),
+                            Text::new(t!("update-and-quit"))
+                                .horizontal_alignment(Horizontal::Center),
                         )
                         .on_press(Message::UpdateApplication)
                         .padding(DEFAULT_PADDING)
                         .width(Length::Fill)
-                        .style(style::Button::SuccessAction),
+                        .style(
+                            style::Button::SuccessAction
+                                .focused(focus == Some(Focus::ModalConfirm)),
+                        ),
                     ),
             )
             .style(style::Card::Modal)
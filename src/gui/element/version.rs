@@ -1,17 +1,25 @@
+use std::collections::HashMap;
+
 use iced::{
-    alignment::Horizontal, button, scrollable, Alignment, Button, Column, Container, Element,
-    Length, Row, Rule, Scrollable, Space, Text,
+    alignment::Horizontal, button, scrollable, text_input, Alignment, Button, Column, Container,
+    Element, Length, Row, Rule, Scrollable, Space, Text, TextInput,
 };
 use log::debug;
-use regex::Regex;
 use pirate_midi_rs::check::CheckResponse;
 
-use crate::{
-    command::github::Release,
-    gui::{
-        style::{self},
-        Error, Filter, Message, DEFAULT_PADDING, SECONDARY_FONT, SECONDARY_FONT_SIZE,
+use super::notes::ReleaseNotes;
+use ahoy_core::{
+    command::{
+        channel::{Channel, Channels},
+        github::Release,
     },
+    i18n::t,
+};
+use crate::gui::{
+    keyboard::Focus,
+    markdown::Block,
+    style::{self},
+    Error, Message, DEFAULT_PADDING, SECONDARY_FONT, SECONDARY_FONT_SIZE,
 };
 
 #[derive(Default, Debug, Clone)]
@@ -26,16 +34,23 @@ pub struct VersionList {
     detail_scroll: scrollable::State,
     version_scroll: scrollable::State,
     install_button: button::State,
+    local_button: button::State,
+    local_input: text_input::State,
+    notes: ReleaseNotes,
 }
 
 impl VersionList {
     pub fn view<'a>(
         &'a mut self,
         error: &'a Option<Error>,
-        filter: &'a Filter,
+        filter: &'a Channels,
+        local_file: &'a Option<String>,
         releases: &'a Option<Vec<Release>>,
         device_details: &'a CheckResponse,
         selected_release: &'a Option<Release>,
+        refusals: &'a HashMap<u64, String>,
+        release_notes: &'a [Block],
+        focus: Option<Focus>,
     ) -> Element<'a, Message> {
 
         let error_message: Element<Message> = if let Some(error) = error {
@@ -70,11 +85,33 @@ impl VersionList {
                     selector
                 });
 
+                // local files are offered ahead of every release
+                let release_selection_column = Column::new()
+                    .padding(DEFAULT_PADDING)
+                    .spacing(DEFAULT_PADDING);
+                let release_selection_column = if filter.contains(Channel::Local) {
+                    release_selection_column.push(
+                        Button::new(
+                            &mut self.local_button,
+                            Text::new(t!("local-file")).horizontal_alignment(Horizontal::Center),
+                        )
+                        .on_press(Message::SelectedLocalFile)
+                        .padding(DEFAULT_PADDING)
+                        .width(Length::Units(130))
+                        .style(if local_file.is_some() {
+                            style::Button::FilterSelected
+                        } else {
+                            style::Button::FilterOption
+                        }),
+                    )
+                } else {
+                    release_selection_column
+                };
+
                 // build our selectable version column
+                let mut last_group = None;
                 let release_selection_column = self.button_states.iter_mut().fold(
-                    Column::new()
-                        .padding(DEFAULT_PADDING)
-                        .spacing(DEFAULT_PADDING),
+                    release_selection_column,
                     |column, version| {
                         let release = version
                             .release
@@ -82,7 +119,21 @@ impl VersionList {
                             .expect("something went terribly wrong!");
 
                         if filter.matches(release) {
-                            column.push(
+                            // group releases by their major and minor version
+                            let group = release.version().map(|version| (version.parts[0], version.parts[1]));
+                            let column = match group {
+                                Some((major, minor)) if group != last_group => {
+                                    last_group = group;
+                                    column.push(
+                                        Text::new(format!("{}.{}.x", major, minor))
+                                            .font(SECONDARY_FONT)
+                                            .size(SECONDARY_FONT_SIZE - 4),
+                                    )
+                                }
+                                _ => column,
+                            };
+
+                            let column = column.push(
                                 Button::new(
                                     &mut version.state,
                                     Text::new(release.tag_name.clone())
@@ -91,48 +142,105 @@ impl VersionList {
                                 .on_press(Message::SelectedRelease(Box::new(release.clone())))
                                 .padding(DEFAULT_PADDING)
                                 .width(Length::Units(130))
-                                .style(
+                                .style({
                                     // TODO: clean up this abomination
-                                    if let Some(selected) = selected_release {
+                                    let button_style = if let Some(selected) = selected_release {
                                         if release.id == selected.id {
-                                            if release.prerelease {
+                                            if release.is_prerelease() {
                                                 style::Button::PreReleaseSelected
                                             } else {
                                                 style::Button::ReleaseSelected
                                             }
-                                        } else if release.prerelease {
+                                        } else if release.is_prerelease() {
                                             style::Button::PreRelease
                                         } else {
                                             style::Button::Release
                                         }
-                                    } else if release.prerelease {
+                                    } else if release.is_prerelease() {
                                         style::Button::PreRelease
                                     } else {
                                         style::Button::Release
-                                    },
-                                ),
-                            )
+                                    };
+                                    let is_selected = matches!(selected_release, Some(selected) if selected.id == release.id);
+                                    button_style.focused(is_selected && focus == Some(Focus::Releases))
+                                }),
+                            );
+
+                            // mark the firmware the device is running, releases the policy refuses, and unpublished releases
+                            let label = if release.is_version(&device_details.firmware_version) {
+                                Some(t!("versions-installed"))
+                            } else if refusals.contains_key(&release.id) {
+                                Some(t!("versions-not-allowed"))
+                            } else if release.draft {
+                                Some(t!("versions-draft"))
+                            } else {
+                                None
+                            };
+                            if let Some(label) = label {
+                                column.push(
+                                    Text::new(label)
+                                        .font(SECONDARY_FONT)
+                                        .size(SECONDARY_FONT_SIZE - 4)
+                                        .color(style::palette().release),
+                                )
+                            } else {
+                                column
+                            }
                         } else {
                             column
                         }
                     },
                 );
 
-                let release_selected_detail: Element<Message> = match selected_release {
-                    Some(selected) => {
-                        let selected_asset = selected.assets.iter().find(|asset| {
-                            // this is kind of brittle... :-/
-                            // assume format "bridgeX_vX.X.X.X.bin" or "bridgeX_vX.X.X.X-beta.X.bin"
-                            // check both the device type, and the hardware revision!
-                            let model = device_details.device_model.trim().to_lowercase();
-                            let revision = device_details.hardware_version.chars().last().expect("unable to retrieve hardware version!");
-                            // ^bridge6_v\d\.\d\.\d\.1.+$
-                            // ^{model}_v\d\.\d\.\d\.{revision}.+$
-                            let regex = Regex::new(format!(r"^{model}_v\d\.\d\.\d\.{revision}.+$").as_str()).expect("unable to parse regex pattern");
-
-                            // determine if we have a match
-                            regex.is_match(&asset.name)
-                        });
+                let release_selected_detail: Element<Message> = match (local_file, selected_release) {
+                    (Some(path), _) => {
+                        let install_button = Button::new(
+                            &mut self.install_button,
+                            Text::new(t!("install")).horizontal_alignment(Horizontal::Center),
+                        )
+                        .padding(DEFAULT_PADDING)
+                        .width(Length::Units(250))
+                        .style(style::Button::SuccessAction.focused(focus == Some(Focus::Install)));
+
+                        Column::new()
+                            .padding(DEFAULT_PADDING)
+                            .spacing(DEFAULT_PADDING)
+                            .height(Length::Fill)
+                            .width(Length::Fill)
+                            .push(Text::new(t!("versions-local-title")))
+                            .push(Rule::horizontal(1))
+                            .push(
+                                TextInput::new(
+                                    &mut self.local_input,
+                                    &t!("versions-local-placeholder"),
+                                    path,
+                                    Message::LocalFileChanged,
+                                )
+                                .on_submit(Message::InstallLocalFile)
+                                .padding(DEFAULT_PADDING),
+                            )
+                            .push(Space::with_height(Length::Fill))
+                            .push(Rule::horizontal(1))
+                            .push(
+                                Row::new()
+                                    .padding([DEFAULT_PADDING, 0, 0, 0])
+                                    .push(Space::with_width(Length::Fill))
+                                    .push(if path.trim().is_empty() {
+                                        install_button
+                                    } else {
+                                        install_button.on_press(Message::InstallLocalFile)
+                                    }),
+                            )
+                            .into()
+                    }
+                    (None, Some(selected)) => {
+                        // check both the device type, and the hardware revision!
+                        // a device that doesn't report a revision has no firmware to offer
+                        let selected_asset = device_details
+                            .hardware_version
+                            .chars()
+                            .last()
+                            .and_then(|revision| selected.find_asset(&device_details.device_model, revision));
 
                         debug!("selected asset: {:?}", selected_asset);
 
@@ -142,23 +250,27 @@ impl VersionList {
                             .height(Length::Shrink)
                             .width(Length::Fill);
 
-                        let install_bar = match selected_asset {
-                            Some(asset) => install_bar
+                        let install_bar = match (selected_asset, refusals.get(&selected.id)) {
+                            // the notes can still be read, but the policy says why it can't be installed
+                            (Some(_), Some(refusal)) => install_bar.push(
+                                Text::new(refusal.clone()).color(style::palette().danger),
+                            ),
+                            (Some(asset), None) => install_bar
                                 // .push(Text::new(format!("{}", asset.name)))
                                 .push(Space::with_width(Length::Fill))
                                 .push(
                                     Button::new(
                                         &mut self.install_button,
-                                        Text::new("Download and Install")
+                                        Text::new(t!("versions-download-install"))
                                             .horizontal_alignment(Horizontal::Center),
                                     )
                                     .on_press(Message::Download(Box::new(asset.clone())))
                                     .padding(DEFAULT_PADDING)
                                     .width(Length::Units(250))
-                                    .style(style::Button::SuccessAction),
+                                    .style(style::Button::SuccessAction.focused(focus == Some(Focus::Install))),
                                 ),
-                            None => install_bar.push(Text::new(
-                                "No assets are available for download for this device",
+                            (None, _) => install_bar.push(Text::new(
+                                t!("versions-no-assets"),
                             )),
                         };
 
@@ -172,13 +284,13 @@ impl VersionList {
                             .push(
                                 Scrollable::new(&mut self.detail_scroll)
                                     .height(Length::Fill)
-                                    .push(Text::new(selected.body.clone().unwrap_or_default())),
+                                    .push(self.notes.view(release_notes)),
                             )
                             .push(Rule::horizontal(1))
                             .push(install_bar)
                             .into()
                     }
-                    None => Container::new(Text::new("Please select a release"))
+                    (None, None) => Container::new(Text::new(t!("versions-select")))
                         .center_x()
                         .center_y()
                         .height(Length::Fill)
@@ -210,7 +322,7 @@ impl VersionList {
                     .height(Length::Fill)
                     .width(Length::Fill)
                     .push(Space::new(Length::Fill, Length::Shrink))
-                    .push(Text::new("Loading..."))
+                    .push(Text::new(t!("loading")))
                     .push(Space::new(Length::Fill, Length::Shrink))
                     .into()
         };
@@ -0,0 +1,214 @@
+use iced::keyboard::{KeyCode, Modifiers};
+
+use ahoy_core::command::{channel::Channel, github::Release, session::Stage};
+
+use super::{element::log_panel::ExportStatus, Ahoy, DeviceState, Message};
+
+/// the control that has keyboard focus.
+/// the modal buttons are shared, since only one modal is open at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Focus {
+    Channel(Channel),
+    Settings,
+    History,
+    Refresh,
+    Releases,
+    Install,
+    ModalCancel,
+    ModalConfirm,
+    Close,
+    Log,
+    Export,
+}
+
+/// the controls that can take focus right now, in tab order
+fn focus_order(ahoy: &Ahoy) -> Vec<Focus> {
+    if ahoy.update_modal.is_open() {
+        return vec![Focus::ModalCancel, Focus::ModalConfirm];
+    }
+    if ahoy.settings_modal.is_open() {
+        return vec![Focus::ModalCancel];
+    }
+
+    if browsing(ahoy) && ahoy.confirm_modal.is_open() {
+        return vec![Focus::ModalCancel, Focus::ModalConfirm];
+    }
+
+    let mut order = match (ahoy.session.stage(), &ahoy.device) {
+        (Stage::Done, _) => vec![Focus::Close],
+        _ if ahoy.installing() => vec![],
+        (_, DeviceState::Disconnected) => vec![Focus::Settings],
+        (_, DeviceState::Connected(_)) => {
+            let mut order: Vec<Focus> = Channel::ALL.into_iter().map(Focus::Channel).collect();
+            order.extend([Focus::Settings, Focus::History, Focus::Refresh]);
+            if ahoy.history.is_none() {
+                order.extend([Focus::Releases, Focus::Install]);
+            }
+            order
+        }
+        (_, DeviceState::DFU(_)) => vec![],
+    };
+
+    // the log panel is on every screen
+    order.push(Focus::Log);
+    if ahoy.log_open {
+        order.push(Focus::Export);
+    }
+    order
+}
+
+/// the release list is on screen
+fn browsing(ahoy: &Ahoy) -> bool {
+    matches!(ahoy.device, DeviceState::Connected(_)) && !ahoy.installing()
+}
+
+/// move focus forwards or backwards, wrapping around at either end
+fn cycle(order: &[Focus], current: Option<Focus>, backwards: bool) -> Option<Focus> {
+    let position = current.and_then(|focus| order.iter().position(|f| *f == focus));
+    let next = match (position, backwards) {
+        (None, false) => 0,
+        (None, true) => order.len().checked_sub(1)?,
+        (Some(index), false) => (index + 1) % order.len(),
+        (Some(index), true) => (index + order.len() - 1) % order.len(),
+    };
+    order.get(next).copied()
+}
+
+/// the release next to the selected one, among those the filter shows
+fn step<'a>(visible: &[&'a Release], selected: Option<&Release>, up: bool) -> Option<&'a Release> {
+    let position = selected.and_then(|selected| visible.iter().position(|r| r.id == selected.id));
+    match (position, up) {
+        (None, _) => visible.first().copied(),
+        (Some(index), true) => visible.get(index.checked_sub(1)?).copied(),
+        (Some(index), false) => visible.get(index + 1).copied(),
+    }
+}
+
+/// the focus only counts if that control is on screen
+pub(crate) fn focused(ahoy: &Ahoy) -> Option<Focus> {
+    ahoy.focus.filter(|focus| focus_order(ahoy).contains(focus))
+}
+
+/// what pressing a control does
+fn activate(ahoy: &Ahoy, focus: Focus) -> Option<Message> {
+    match focus {
+        Focus::Channel(channel) => Some(Message::ToggleChannel(channel)),
+        Focus::Settings => Some(Message::ShowSettings),
+        Focus::History => Some(Message::ToggleHistory),
+        Focus::Refresh => Some(Message::FetchReleases),
+        Focus::Releases | Focus::Install => install_selected(ahoy),
+        Focus::ModalCancel if ahoy.update_modal.is_open() => Some(Message::IgnoreUpdate),
+        Focus::ModalCancel if ahoy.settings_modal.is_open() => Some(Message::HideSettings),
+        Focus::ModalCancel => Some(Message::Cancel),
+        Focus::ModalConfirm if ahoy.update_modal.is_open() => Some(Message::UpdateApplication),
+        Focus::ModalConfirm => Some(Message::EnterBootloader),
+        Focus::Close => Some(Message::AttemptReset),
+        Focus::Log => Some(Message::ToggleLog),
+        Focus::Export if matches!(ahoy.export_status, ExportStatus::Exporting) => None,
+        Focus::Export => Some(Message::ExportDiagnostics),
+    }
+}
+
+/// the same as clicking "Download and Install", or "Install" for a local file
+fn install_selected(ahoy: &Ahoy) -> Option<Message> {
+    if let Some(path) = &ahoy.local_file {
+        return (!path.trim().is_empty()).then_some(Message::InstallLocalFile);
+    }
+
+    match (&ahoy.device, &ahoy.selected_version) {
+        (DeviceState::Connected(details), Some(selected)) => {
+            let revision = details.hardware_version.chars().last()?;
+            selected
+                .find_asset(&details.device_model, revision)
+                .map(|asset| Message::Download(Box::new(asset.clone())))
+        }
+        _ => None,
+    }
+}
+
+/// turn a key press nobody else handled into a message
+pub(crate) fn handle_key(
+    ahoy: &mut Ahoy,
+    key_code: KeyCode,
+    modifiers: Modifiers,
+) -> Option<Message> {
+    let order = focus_order(ahoy);
+    let browsing = browsing(ahoy)
+        && !ahoy.confirm_modal.is_open()
+        && !ahoy.settings_modal.is_open()
+        && !ahoy.update_modal.is_open();
+    // the simulation can only be changed between installs
+    let debugging =
+        (ahoy.debug || ahoy.simulation.is_some()) && matches!(ahoy.session.stage(), Stage::Idle);
+
+    match key_code {
+        KeyCode::Tab => {
+            ahoy.focus = cycle(&order, focused(ahoy), modifiers.shift());
+            None
+        }
+        // enter also works without focus - it picks the obvious action for the screen
+        KeyCode::Enter | KeyCode::NumpadEnter => match focused(ahoy) {
+            Some(focus) => activate(ahoy, focus),
+            None if order.contains(&Focus::ModalConfirm) => activate(ahoy, Focus::ModalConfirm),
+            None if order.contains(&Focus::Install) => activate(ahoy, Focus::Install),
+            None if order.contains(&Focus::Close) => activate(ahoy, Focus::Close),
+            None => None,
+        },
+        KeyCode::Space => focused(ahoy).and_then(|focus| activate(ahoy, focus)),
+        KeyCode::Up | KeyCode::Down if browsing && ahoy.history.is_none() => {
+            let visible: Vec<&Release> = ahoy
+                .releases
+                .iter()
+                .flatten()
+                .filter(|release| ahoy.filter.matches(release))
+                .collect();
+            let release = step(
+                &visible,
+                ahoy.selected_version.as_ref(),
+                key_code == KeyCode::Up,
+            )?;
+            ahoy.focus = Some(Focus::Releases);
+            Some(Message::SelectedRelease(Box::new(release.clone())))
+        }
+        // number keys switch channels, in the order the buttons are shown
+        KeyCode::Key1 | KeyCode::Key2 | KeyCode::Key3 | KeyCode::Key4 if browsing => {
+            let index = match key_code {
+                KeyCode::Key1 => 0,
+                KeyCode::Key2 => 1,
+                KeyCode::Key3 => 2,
+                _ => 3,
+            };
+            Some(Message::ToggleChannel(Channel::ALL[index]))
+        }
+        // hidden debugging keys - swap the hardware for a simulation, and pick what goes wrong
+        KeyCode::F9 if debugging => Some(Message::ToggleSimulation),
+        KeyCode::F10 if debugging && ahoy.simulation.is_some() => Some(Message::CycleFault),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_wraps_around() {
+        let order = [Focus::ModalCancel, Focus::ModalConfirm];
+        assert_eq!(cycle(&order, None, false), Some(Focus::ModalCancel));
+        assert_eq!(cycle(&order, None, true), Some(Focus::ModalConfirm));
+        assert_eq!(
+            cycle(&order, Some(Focus::ModalConfirm), false),
+            Some(Focus::ModalCancel)
+        );
+        assert_eq!(
+            cycle(&order, Some(Focus::ModalCancel), true),
+            Some(Focus::ModalConfirm)
+        );
+        // focus left over from another screen starts again from the top
+        assert_eq!(
+            cycle(&order, Some(Focus::Close), false),
+            Some(Focus::ModalCancel)
+        );
+        assert_eq!(cycle(&[], Some(Focus::Close), false), None);
+    }
+}
@@ -0,0 +1,232 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+/// a run of text that shares the same styling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub strong: bool,
+    pub emphasis: bool,
+    pub code: bool,
+    pub link: Option<String>,
+}
+
+/// the subset of markdown we render in release notes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading(u32, Vec<Span>),
+    Paragraph(Vec<Span>),
+    ListItem {
+        depth: usize,
+        marker: String,
+        spans: Vec<Span>,
+    },
+    Code(String),
+    Rule,
+}
+
+#[derive(Debug)]
+enum Pending {
+    Heading(u32),
+    Paragraph,
+    ListItem { depth: usize, marker: String },
+}
+
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<Block>,
+    pending: Option<Pending>,
+    spans: Vec<Span>,
+    strong: usize,
+    emphasis: usize,
+    link: Option<String>,
+    // the next number of each open list, or none for bullet lists
+    lists: Vec<Option<u64>>,
+    code: Option<String>,
+}
+
+impl Builder {
+    fn push_text(&mut self, text: &str, code: bool) {
+        // fold into the previous span if nothing about the styling changed
+        if let Some(last) = self.spans.last_mut() {
+            if last.strong == (self.strong > 0)
+                && last.emphasis == (self.emphasis > 0)
+                && last.code == code
+                && last.link == self.link
+            {
+                last.text.push_str(text);
+                return;
+            }
+        }
+        self.spans.push(Span {
+            text: text.to_string(),
+            strong: self.strong > 0,
+            emphasis: self.emphasis > 0,
+            code,
+            link: self.link.clone(),
+        });
+    }
+
+    fn flush(&mut self) {
+        let spans = std::mem::take(&mut self.spans);
+        let block = match self.pending.take() {
+            _ if spans.iter().all(|span| span.text.trim().is_empty()) => return,
+            Some(Pending::Heading(level)) => Block::Heading(level, spans),
+            Some(Pending::ListItem { depth, marker }) => Block::ListItem {
+                depth,
+                marker,
+                spans,
+            },
+            Some(Pending::Paragraph) | None => Block::Paragraph(spans),
+        };
+        self.blocks.push(block);
+    }
+
+    fn in_list_item(&self) -> bool {
+        matches!(self.pending, Some(Pending::ListItem { .. }))
+    }
+}
+
+/// break release notes down into blocks the gui knows how to draw
+pub fn parse(markdown: &str) -> Vec<Block> {
+    let mut builder = Builder::default();
+
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Start(Tag::Heading(level, _, _)) => {
+                builder.flush();
+                builder.pending = Some(Pending::Heading(level as u32));
+            }
+            // loose list items wrap their text in paragraphs - keep it on the item
+            Event::Start(Tag::Paragraph) if builder.in_list_item() => (),
+            Event::Start(Tag::Paragraph) => {
+                builder.flush();
+                builder.pending = Some(Pending::Paragraph);
+            }
+            Event::Start(Tag::List(start)) => {
+                // a nested list ends the text of its parent item
+                builder.flush();
+                builder.lists.push(start);
+            }
+            Event::Start(Tag::Item) => {
+                builder.flush();
+                let depth = builder.lists.len().saturating_sub(1);
+                let marker = match builder.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_string(),
+                };
+                builder.pending = Some(Pending::ListItem { depth, marker });
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                builder.flush();
+                builder.code = Some(String::new());
+            }
+            Event::Start(Tag::Emphasis) => builder.emphasis += 1,
+            Event::Start(Tag::Strong) => builder.strong += 1,
+            Event::Start(Tag::Link(_, url, _)) => builder.link = Some(url.to_string()),
+            Event::End(Tag::Heading(..)) | Event::End(Tag::Item) => builder.flush(),
+            Event::End(Tag::Paragraph) if !builder.in_list_item() => builder.flush(),
+            Event::End(Tag::List(_)) => {
+                builder.flush();
+                builder.lists.pop();
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(code) = builder.code.take() {
+                    builder
+                        .blocks
+                        .push(Block::Code(code.trim_end().to_string()));
+                }
+            }
+            Event::End(Tag::Emphasis) => builder.emphasis = builder.emphasis.saturating_sub(1),
+            Event::End(Tag::Strong) => builder.strong = builder.strong.saturating_sub(1),
+            Event::End(Tag::Link(..)) => builder.link = None,
+            Event::Text(text) => match builder.code.as_mut() {
+                Some(code) => code.push_str(&text),
+                None => builder.push_text(&text, false),
+            },
+            Event::Code(text) => builder.push_text(&text, true),
+            Event::SoftBreak | Event::HardBreak => builder.push_text(" ", false),
+            Event::Rule => {
+                builder.flush();
+                builder.blocks.push(Block::Rule);
+            }
+            Event::TaskListMarker(done) => {
+                builder.push_text(if done { "[x] " } else { "[ ] " }, false)
+            }
+            _ => (), // html, footnotes, tables - not used in release notes
+        }
+    }
+    builder.flush();
+
+    builder.blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> Span {
+        Span {
+            text: text.to_string(),
+            strong: false,
+            emphasis: false,
+            code: false,
+            link: None,
+        }
+    }
+
+    #[test]
+    fn parses_release_notes() {
+        let blocks = parse(
+            "## What's Changed\n\n* fixed **midi clock** in `bank.rs`\n* see [the docs](https://example.com)\n  1. nested\n\n---\n",
+        );
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading(2, vec![plain("What's Changed")]),
+                Block::ListItem {
+                    depth: 0,
+                    marker: "•".to_string(),
+                    spans: vec![
+                        plain("fixed "),
+                        Span {
+                            strong: true,
+                            ..plain("midi clock")
+                        },
+                        plain(" in "),
+                        Span {
+                            code: true,
+                            ..plain("bank.rs")
+                        },
+                    ],
+                },
+                Block::ListItem {
+                    depth: 0,
+                    marker: "•".to_string(),
+                    spans: vec![
+                        plain("see "),
+                        Span {
+                            link: Some("https://example.com".to_string()),
+                            ..plain("the docs")
+                        },
+                    ],
+                },
+                Block::ListItem {
+                    depth: 1,
+                    marker: "1.".to_string(),
+                    spans: vec![plain("nested")],
+                },
+                Block::Rule,
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_code_blocks_verbatim() {
+        let blocks = parse("```\nahoy install *.bin\n```");
+        assert_eq!(blocks, vec![Block::Code("ahoy install *.bin".to_string())]);
+    }
+}
@@ -1,40 +1,63 @@
 mod element;
+mod keyboard;
+mod markdown;
 mod style;
 mod update;
 mod usb;
 mod view;
 
 use async_std::sync::Mutex;
-use futures::{
-    channel::mpsc::{Receiver, Sender},
-    StreamExt,
-};
+use futures::{channel::mpsc::Receiver, StreamExt};
 use iced::{
-    button, image, svg, window, Application, Color, Command, Element, Font, Settings, Subscription,
+    button, image,
+    keyboard::{KeyCode, Modifiers},
+    svg, window, Application, Color, Command, Element, Font, Settings, Subscription,
 };
-use iced_native::subscription;
+use iced_native::{event, subscription};
 use lazy_static::lazy_static;
-use log::*;
+use log::error;
 use pirate_midi_rs::check::CheckResponse;
 use rusb::Device;
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, fmt, path::PathBuf, sync::Arc, time::Duration};
 
 use crate::{
     cli::{self, Args},
+    update::update_available,
+};
+use ahoy_core::{
     command::{
-        github::{Asset, Release},
-        update::update_available,
+        channel::{Channel, Channels},
+        device::Model,
+        github::{Asset, DownloadProgress, Release},
+        history::JournalEntry,
+        logging::LogLine,
+        policy::{self, Policy},
+        session::{InstallSession, Stage},
+        settings::{self as user_settings, ThemePreference},
+        signature,
+        simulate::Simulation,
+        transfer::{CancelToken, ProgressEvent, Timeouts},
         CommandError,
     },
+    i18n::t,
 };
 
 use self::{
     element::controls::ControlsView,
     element::{
-        confirm_modal::ConfirmModal, device::DeviceView, install::InstallView,
-        update_modal::UpdateModal, version::VersionList,
+        confirm_modal::ConfirmModal,
+        device::DeviceView,
+        history::HistoryView,
+        install::{InstallView, ProgressTracker},
+        log_panel::{ExportStatus, LogPanel},
+        recover::RecoverView,
+        settings_modal::{LanguageChoice, SettingsModal},
+        update_modal::UpdateModal,
+        version::VersionList,
     },
-    update::handle_message,
+    keyboard::Focus,
+    markdown::Block,
+    update::{handle_message, set_simulation},
     view::handle_view,
 };
 
@@ -47,19 +70,32 @@ lazy_static! {
         svg::Handle::from_memory(include_bytes!("../../resources/bridge6-dark.svg").to_vec());
     pub static ref IMAGE_BRIDGE_4_DARK: svg::Handle =
         svg::Handle::from_memory(include_bytes!("../../resources/bridge4-dark.svg").to_vec());
+    pub static ref IMAGE_USB_CABLE_LIGHT: svg::Handle =
+        svg::Handle::from_memory(include_bytes!("../../resources/usb-light.svg").to_vec());
+    pub static ref IMAGE_BRIDGE_6_LIGHT: svg::Handle =
+        svg::Handle::from_memory(include_bytes!("../../resources/bridge6-light.svg").to_vec());
+    pub static ref IMAGE_BRIDGE_4_LIGHT: svg::Handle =
+        svg::Handle::from_memory(include_bytes!("../../resources/bridge4-light.svg").to_vec());
     pub static ref IMAGE_FLEXI_BRIDGE: svg::Handle =
         svg::Handle::from_memory(include_bytes!("../../resources/wire-bridge.svg").to_vec());
     pub static ref IMAGE_PIRATE_MIDI_LOGO: image::Handle =
         image::Handle::from_memory(include_bytes!("../../resources/pirate-midi-pink.png").to_vec());
 }
 
+/// the "dark" artwork is drawn in light strokes for a dark background, and vice versa
+pub fn themed_svg(dark: &svg::Handle, light: &svg::Handle) -> svg::Handle {
+    match style::theme() {
+        style::Theme::Dark => dark.clone(),
+        style::Theme::Light => light.clone(),
+    }
+}
+
 // DEFAULTS
 pub static DEFAULT_PADDING: u16 = 10;
 pub static DEFAULT_FONT_SIZE: u16 = 20;
 pub static SECONDARY_FONT_SIZE: u16 = 18;
 pub static DEFAULT_BORDER_RADIUS: f32 = 6.0;
 pub static DEFAULT_HEADING_FONT_SIZE: u16 = 24;
-pub static DEFAULT_FONT_COLOR: Color = Color::WHITE;
 pub static DEFAULT_FONT: &[u8] = include_bytes!("../../resources/OpenSans-Regular.ttf");
 pub static SECONDARY_FONT: Font = Font::External {
     name: "RobotoMono",
@@ -100,52 +136,153 @@ pub enum Message {
     FetchReleases,
     SelectedRelease(Box<Release>),
     RetrievedReleases(Result<Vec<Release>, CommandError>),
-    ReleaseFilterChanged(Filter),
+    ToggleChannel(Channel),
+    SelectedLocalFile,
+    LocalFileChanged(String),
+    InstallLocalFile,
 
     // prompt
     Cancel,
     EnterBootloader,
     WaitForBootloader(Result<(), CommandError>),
-    Install,
-    InstallProgress(f32),
+    BootloaderReady(Result<(), CommandError>),
+    InstallProgress(ProgressEvent),
+    CancelInstall,
     AttemptReset,
     PostInstallResult(Result<(), CommandError>),
+    Verified(Result<(), CommandError>),
 
     // install specific
     Download(Box<Asset>),
+    DownloadProgress(DownloadProgress),
     Downloaded(Result<PathBuf, CommandError>),
+
+    // recovery specific
+    RecoverModelChanged(Model),
+    RecoverRevisionChanged(char),
+    RecoverReleaseChanged(String),
+    RecoverFileChanged(String),
+    Recover,
+
+    // release notes
+    OpenUrl(String),
+
+    // firmware update banner
+    InstallUpdate,
+
+    // install history
+    ToggleHistory,
+    HistoryLoaded(Result<Vec<JournalEntry>, CommandError>),
+
+    // log panel
+    ToggleLog,
+    RefreshLog,
+    ExportDiagnostics,
+    DiagnosticsExported(Result<PathBuf, CommandError>),
+
+    // keyboard navigation
+    KeyPressed(KeyCode, Modifiers),
+
+    // debugging
+    ToggleSimulation,
+    CycleFault,
+
+    // settings
+    ShowSettings,
+    HideSettings,
+    ThemeChanged(ThemePreference),
+    LanguageChanged(LanguageChoice),
 }
 
 #[derive(Default)]
 pub(crate) struct Ahoy {
     debug: bool,
+    simulation: Option<Simulation>,
+    port_override: Option<String>,
+    port: Option<String>,
+    timeouts: Timeouts,
+    install_token: Option<CancelToken>,
+    install_receiver: Option<Arc<Mutex<Receiver<ProgressEvent>>>>,
     error: Option<Error>,
-    filter: Filter,
+    filter: Channels,
+    local_file: Option<String>,
     device: DeviceState,
     status: DeviceView,
     controls: ControlsView,
     releases: Option<Vec<Release>>,
     versions: VersionList,
     installer: InstallView,
+    recover_view: RecoverView,
+    recovery: RecoveryOptions,
     confirm_modal: ConfirmModal,
     update_modal: UpdateModal,
-    install_progress: f32,
+    settings: user_settings::Settings,
+    settings_modal: SettingsModal,
+    settings_button: button::State,
+    install_progress: ProgressTracker,
+    /// how far the firmware download has got, while there is one
+    download_progress: Option<DownloadProgress>,
+    download_receiver: Option<Arc<Mutex<Receiver<DownloadProgress>>>>,
     selected_version: Option<Release>,
-    installable_asset: Option<PathBuf>,
+    policy: Policy,
+    /// a policy that's configured but unreadable - nothing may be installed until it's fixed
+    policy_error: Option<CommandError>,
+    /// why the policy refuses each release it does, by release id
+    refusals: HashMap<u64, String>,
+    firmware_status: FirmwareStatus,
+    release_notes: Vec<Block>,
+    session: InstallSession,
+    pending_install: Option<JournalEntry>,
+    history: Option<Vec<JournalEntry>>,
+    history_view: HistoryView,
     reset_button: button::State,
+    focus: Option<Focus>,
+    log_open: bool,
+    log_lines: Vec<LogLine>,
+    log_panel: LogPanel,
+    export_status: ExportStatus,
 }
 
+/// what's plugged in - an install in progress is the session's business
 #[derive(Default)]
 pub(crate) enum DeviceState {
     #[default]
     Disconnected,
     Connected(CheckResponse),
-    DFU(
-        Option<Device<rusb::Context>>,
-        Option<Sender<f32>>,
-        Option<Arc<Mutex<Receiver<f32>>>>,
-    ),
-    PostInstall,
+    /// a device in its bootloader - the one an install is waiting for, or one that needs recovering
+    DFU(Device<rusb::Context>),
+}
+
+/// what to reinstall on a device that's stuck in DFU mode
+#[derive(Default, Debug, Clone)]
+pub(crate) struct RecoveryOptions {
+    pub model: Model,
+    pub revision: Option<char>,
+    pub tag: Option<String>,
+    pub file: String,
+}
+
+/// how the connected device's firmware compares to the available releases
+#[derive(Default, Debug, Clone)]
+pub(crate) enum FirmwareStatus {
+    #[default]
+    Unknown,
+    Current,
+    Available(Box<Release>),
+}
+
+impl Ahoy {
+    /// an install takes the window over from whatever is connected, until it's closed
+    pub(crate) fn installing(&self) -> bool {
+        matches!(
+            self.session.stage(),
+            Stage::AwaitingBootloader { requested: true }
+                | Stage::Flashing
+                | Stage::Rebooting
+                | Stage::Verifying
+                | Stage::Done
+        )
+    }
 }
 
 impl Application for Ahoy {
@@ -154,38 +291,86 @@ impl Application for Ahoy {
     type Flags = cli::Args;
 
     fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+        let settings = user_settings::load();
+        style::set_theme(settings.theme.into());
+
+        let (policy, policy_error) = match policy::load(flags.policy.as_deref()) {
+            Ok(policy) => (policy, None),
+            Err(err) => {
+                error!("unable to load firmware policy: {}", err.report());
+                (Policy::default(), Some(err))
+            }
+        };
+
+        let mut ahoy = Ahoy {
+            debug: flags.debug,
+            timeouts: flags.timeouts(),
+            port_override: flags.port.clone(),
+            settings,
+            policy,
+            policy_error,
+            session: InstallSession::new().verifying(signature::load(flags.require_signature)),
+            ..Default::default()
+        };
+        let simulating = set_simulation(&mut ahoy, flags.simulation());
+
         (
-            Ahoy {
-                debug: flags.debug,
-                ..Default::default()
-            },
-            Command::perform(update_available(), Self::Message::UpdateAvailable),
+            ahoy,
+            Command::batch([
+                Command::perform(update_available(), Self::Message::UpdateAvailable),
+                simulating,
+            ]),
         )
     }
 
     fn title(&self) -> String {
-        String::from("AHOY! - Pirate MIDI Firmware Updater")
+        t!("app-title")
+    }
+
+    fn background_color(&self) -> Color {
+        style::palette().background
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        let progress_subscription: Subscription<f32> = match &self.device {
-            DeviceState::DFU(_, _, channel_recv) => match channel_recv.clone() {
+        let progress_subscription: Subscription<ProgressEvent> = match self.install_receiver.clone()
+        {
+            Some(receiver) => subscription::unfold(
+                std::any::TypeId::of::<Self>(),
+                receiver,
+                |recv| async move {
+                    let value = recv.lock().await.next().await;
+                    (value, recv)
+                },
+            ),
+            None => Subscription::none(),
+        };
+
+        let download_subscription: Subscription<DownloadProgress> =
+            match self.download_receiver.clone() {
                 Some(receiver) => subscription::unfold(
-                    std::any::TypeId::of::<Self>(),
+                    std::any::TypeId::of::<DownloadProgress>(),
                     receiver,
                     |recv| async move {
                         let value = recv.lock().await.next().await;
-                        (value.clone(), recv)
+                        (value, recv)
                     },
                 ),
                 None => Subscription::none(),
-            },
-            _ => Subscription::none(),
+            };
+
+        // only poll for new log lines while someone is looking at them
+        let log_subscription = if self.log_open {
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::RefreshLog)
+        } else {
+            Subscription::none()
         };
 
         Subscription::batch([
             usb::listener().map(Message::DeviceChangedAction),
             progress_subscription.map(Message::InstallProgress),
+            download_subscription.map(Message::DownloadProgress),
+            subscription::events_with(key_pressed),
+            log_subscription,
         ])
     }
 
@@ -198,33 +383,51 @@ impl Application for Ahoy {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Filter {
-    #[default]
-    Stable,
-    PreRelease,
-}
-
-impl Filter {
-    fn matches(&self, release: &Release) -> bool {
-        match self {
-            Filter::Stable => !release.prerelease,
-            Filter::PreRelease => release.prerelease,
-        }
+// only keys that no widget used, so typing in a text input doesn't navigate
+fn key_pressed(event: iced_native::Event, status: event::Status) -> Option<Message> {
+    match (event, status) {
+        (
+            iced_native::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            }),
+            event::Status::Ignored,
+        ) => Some(Message::KeyPressed(key_code, modifiers)),
+        _ => None,
     }
 }
 
-#[derive(thiserror::Error, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub enum Error {
-    #[error("Error reaching Github!\nRun with `-v` flag to see more details. Reason: {0}")]
     RemoteApi(String),
-    #[error("Unable to install update! Reason: {0}")]
     Install(String),
 }
 
-impl From<surf::Error> for Error {
-    fn from(error: surf::Error) -> Error {
-        debug!("ERROR: {}", error);
-        Error::RemoteApi(error.to_string())
+// written by hand rather than with thiserror, so the messages can be translated
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RemoteApi(reason) => {
+                write!(f, "{}", t!("error-remote-api", reason = reason.as_str()))
+            }
+            Error::Install(reason) => {
+                write!(f, "{}", t!("error-install", reason = reason.as_str()))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// failures talking to github get their own banner, and every banner says what to try next
+impl From<CommandError> for Error {
+    fn from(err: CommandError) -> Error {
+        let reason = format!("{}\n{}", err.report(), err.hint());
+        match err {
+            CommandError::Retrieval(_) | CommandError::Download(_) | CommandError::Checksum(_) => {
+                Error::RemoteApi(reason)
+            }
+            _ => Error::Install(reason),
+        }
     }
 }
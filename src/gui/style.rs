@@ -2,8 +2,11 @@ use iced::{button, container, Background, Color};
 use iced_aw::card;
 use iced_aw::modal;
 
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use ahoy_core::command::settings::ThemePreference;
+
 use super::DEFAULT_BORDER_RADIUS;
-use super::DEFAULT_FONT_COLOR;
 
 // COLORS
 // Colors are between 0.0 and 1.0, but most color codes are in u8 with a max value of 255.
@@ -40,18 +43,6 @@ pub static SECONDARY: Color = Color {
     b: 0.7921,
     a: 1.0,
 };
-pub static SECONDARY_HOVER: Color = Color {
-    r: 0.3568,
-    g: 0.5529,
-    b: 0.7921,
-    a: 0.8,
-};
-pub static SECONDARY_CLICK: Color = Color {
-    r: 0.3568,
-    g: 0.5529,
-    b: 0.7921,
-    a: 0.6,
-};
 
 // #85D1D4 - R133/G209/B212
 pub static SECONDARY_END: Color = Color {
@@ -60,18 +51,6 @@ pub static SECONDARY_END: Color = Color {
     b: 0.8313,
     a: 1.0,
 };
-pub static SECONDARY_END_HOVER: Color = Color {
-    r: 0.4784,
-    g: 0.8196,
-    b: 0.8313,
-    a: 0.8,
-};
-pub static SECONDARY_END_CLICK: Color = Color {
-    r: 0.4784,
-    g: 0.8196,
-    b: 0.8313,
-    a: 0.6,
-};
 
 // R248/G254/B167
 pub static PRERELEASE: Color = Color {
@@ -80,19 +59,130 @@ pub static PRERELEASE: Color = Color {
     b: 0.6549,
     a: 1.0,
 };
-pub static PRERELEASE_HOVER: Color = Color {
-    r: 0.9725,
-    g: 0.9960,
-    b: 0.6549,
-    a: 0.7,
-};
-pub static PRERELEASE_CLICK: Color = Color {
-    r: 0.9725,
-    g: 0.9960,
-    b: 0.6549,
-    a: 0.5,
+
+// #F2A541 - R242/G165/B65
+pub static WARNING: Color = Color {
+    r: 0.9490,
+    g: 0.6470,
+    b: 0.2549,
+    a: 1.0,
 };
 
+// THEMES
+// the stylesheets below can't see the application state, so the active theme is kept here
+static THEME: AtomicU8 = AtomicU8::new(Theme::Dark as u8);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+pub fn set_theme(theme: Theme) {
+    THEME.store(theme as u8, Ordering::Relaxed);
+}
+
+pub fn theme() -> Theme {
+    match THEME.load(Ordering::Relaxed) {
+        x if x == Theme::Light as u8 => Theme::Light,
+        _ => Theme::Dark,
+    }
+}
+
+impl From<ThemePreference> for Theme {
+    fn from(preference: ThemePreference) -> Theme {
+        match preference {
+            ThemePreference::Light => Theme::Light,
+            ThemePreference::Dark => Theme::Dark,
+            ThemePreference::System => match dark_light::detect() {
+                dark_light::Mode::Light => Theme::Light,
+                _ => Theme::Dark,
+            },
+        }
+    }
+}
+
+/// the colors of the active theme
+pub fn palette() -> Palette {
+    match theme() {
+        Theme::Dark => Palette::dark(),
+        Theme::Light => Palette::light(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub background: Color,
+    pub surface: Color,
+    pub text: Color,
+    pub muted: Color,
+    pub border: Color,
+    pub backdrop: Color,
+    pub primary: Color,
+    pub secondary: Color,
+    pub release: Color,
+    pub prerelease: Color,
+    // text drawn on top of a selected release
+    pub selected_text: Color,
+    pub warning: Color,
+    pub success: Color,
+    pub danger: Color,
+    pub caution: Color,
+    // the ring around whatever has keyboard focus
+    pub focus: Color,
+}
+
+impl Palette {
+    fn dark() -> Palette {
+        Palette {
+            background: BLACK,
+            surface: Color::from_rgb8(44, 52, 56),
+            text: Color::WHITE,
+            muted: Color::from_rgb(0.7, 0.7, 0.7),
+            border: Color::from_rgb8(74, 74, 74),
+            backdrop: Color::from_rgba8(10, 10, 10, 0.86),
+            primary: PRIMARY,
+            secondary: SECONDARY,
+            release: SECONDARY_END,
+            prerelease: PRERELEASE,
+            selected_text: BLACK,
+            warning: WARNING,
+            success: Color::from_rgb8(100, 183, 93),
+            danger: Color::from_rgb8(223, 84, 107),
+            caution: Color::from_rgb8(142, 110, 34),
+            focus: Color::from_rgb8(255, 209, 102),
+        }
+    }
+
+    // the brand colors are too pale on white, so the light theme uses darker shades of them
+    fn light() -> Palette {
+        Palette {
+            background: Color::from_rgb8(247, 247, 245),
+            surface: Color::from_rgb8(230, 233, 235),
+            text: BLACK,
+            muted: Color::from_rgb(0.4, 0.4, 0.4),
+            border: Color::from_rgb8(200, 200, 200),
+            backdrop: Color::from_rgba8(240, 240, 240, 0.86),
+            primary: Color::from_rgb8(214, 52, 100),
+            secondary: Color::from_rgb8(52, 102, 166),
+            release: Color::from_rgb8(34, 134, 140),
+            prerelease: Color::from_rgb8(150, 128, 0),
+            selected_text: Color::WHITE,
+            warning: Color::from_rgb8(196, 118, 16),
+            success: Color::from_rgb8(62, 146, 56),
+            danger: Color::from_rgb8(196, 52, 78),
+            caution: Color::from_rgb8(142, 110, 34),
+            focus: Color::from_rgb8(31, 111, 235),
+        }
+    }
+}
+
+// hover and click states are the same color, faded
+fn fade(color: Color, alpha: f32) -> Color {
+    Color { a: alpha, ..color }
+}
+
 pub enum Button {
     SuccessAction,
     CancelAction,
@@ -102,71 +192,81 @@ pub enum Button {
     ReleaseSelected,
     PreRelease,
     PreReleaseSelected,
+    Link,
 }
 
 impl button::StyleSheet for Button {
     fn active(&self) -> button::Style {
+        let palette = palette();
         let basic = button::Style {
-            border_color: Color::from_rgb8(210, 210, 210),
+            border_color: palette.border,
             border_radius: DEFAULT_BORDER_RADIUS,
+            text_color: palette.text,
             ..button::Style::default()
         };
 
         match self {
             Button::SuccessAction => button::Style {
                 background: Some(Background::Color(Color::TRANSPARENT)),
-                text_color: PRIMARY,
-                border_color: PRIMARY,
+                text_color: palette.primary,
+                border_color: palette.primary,
                 border_width: 1.0,
                 ..basic
             },
             Button::CancelAction => button::Style {
                 background: Some(Background::Color(Color::TRANSPARENT)),
-                text_color: SECONDARY,
-                border_color: SECONDARY,
+                text_color: palette.secondary,
+                border_color: palette.secondary,
                 border_width: 1.0,
                 ..basic
             },
             Button::FilterOption => button::Style {
                 background: Some(Background::Color(Color::TRANSPARENT)),
-                text_color: SECONDARY,
-                border_color: SECONDARY,
+                text_color: palette.secondary,
+                border_color: palette.secondary,
                 border_width: 1.0,
                 ..basic
             },
             Button::FilterSelected => button::Style {
-                background: Some(Background::Color(SECONDARY)),
+                background: Some(Background::Color(palette.secondary)),
                 text_color: Color::WHITE,
                 ..basic
             },
             Button::Release => button::Style {
                 background: Some(Background::Color(Color::TRANSPARENT)),
-                text_color: SECONDARY_END,
-                border_color: SECONDARY_END,
+                text_color: palette.release,
+                border_color: palette.release,
                 border_width: 1.0,
                 ..basic
             },
             Button::PreRelease => button::Style {
                 background: Some(Background::Color(Color::TRANSPARENT)),
-                text_color: PRERELEASE,
-                border_color: PRERELEASE,
+                text_color: palette.prerelease,
+                border_color: palette.prerelease,
                 border_width: 1.0,
                 ..basic
             },
             Button::ReleaseSelected => button::Style {
-                background: Some(Background::Color(SECONDARY_END)),
-                text_color: BLACK,
+                background: Some(Background::Color(palette.release)),
+                text_color: palette.selected_text,
                 ..basic
             },
             Button::PreReleaseSelected => button::Style {
-                background: Some(Background::Color(PRERELEASE)),
-                text_color: BLACK,
+                background: Some(Background::Color(palette.prerelease)),
+                text_color: palette.selected_text,
+                ..basic
+            },
+            Button::Link => button::Style {
+                background: Some(Background::Color(Color::TRANSPARENT)),
+                text_color: palette.secondary,
+                border_width: 0.0,
                 ..basic
             },
         }
     }
 
     fn hovered(&self) -> button::Style {
+        let palette = palette();
         let active = self.active();
 
         button::Style {
@@ -175,20 +275,21 @@ impl button::StyleSheet for Button {
                 | Button::FilterOption
                 | Button::SuccessAction
                 | Button::CancelAction => Color::WHITE,
-                _ => BLACK,
+                Button::Link => palette.release,
+                _ => palette.selected_text,
             },
             border_color: match self {
-                Button::Release => SECONDARY_END_HOVER,
-                Button::PreRelease => PRERELEASE_HOVER,
-                Button::FilterOption => SECONDARY_HOVER,
+                Button::Release => fade(palette.release, 0.8),
+                Button::PreRelease => fade(palette.prerelease, 0.7),
+                Button::FilterOption => fade(palette.secondary, 0.8),
                 _ => active.border_color,
             },
             background: match self {
-                Button::Release => Some(Background::Color(SECONDARY_END_HOVER)),
-                Button::PreRelease => Some(Background::Color(PRERELEASE_HOVER)),
-                Button::CancelAction => Some(Background::Color(SECONDARY_HOVER)),
-                Button::FilterOption => Some(Background::Color(SECONDARY_HOVER)),
-                Button::SuccessAction => Some(Background::Color(PRIMARY)),
+                Button::Release => Some(Background::Color(fade(palette.release, 0.8))),
+                Button::PreRelease => Some(Background::Color(fade(palette.prerelease, 0.7))),
+                Button::CancelAction => Some(Background::Color(fade(palette.secondary, 0.8))),
+                Button::FilterOption => Some(Background::Color(fade(palette.secondary, 0.8))),
+                Button::SuccessAction => Some(Background::Color(palette.primary)),
 
                 _ => active.background,
             },
@@ -197,24 +298,25 @@ impl button::StyleSheet for Button {
     }
 
     fn pressed(&self) -> button::Style {
+        let palette = palette();
         let hovered = self.hovered();
 
         button::Style {
             border_color: match self {
-                Button::Release | Button::ReleaseSelected => SECONDARY_END_CLICK,
-                Button::PreRelease | Button::PreReleaseSelected => PRERELEASE_CLICK,
-                Button::FilterOption | Button::FilterSelected => SECONDARY_CLICK,
+                Button::Release | Button::ReleaseSelected => fade(palette.release, 0.6),
+                Button::PreRelease | Button::PreReleaseSelected => fade(palette.prerelease, 0.5),
+                Button::FilterOption | Button::FilterSelected => fade(palette.secondary, 0.6),
                 _ => hovered.border_color,
             },
             background: match self {
                 Button::Release | Button::ReleaseSelected => {
-                    Some(Background::Color(SECONDARY_END_CLICK))
+                    Some(Background::Color(fade(palette.release, 0.6)))
                 }
                 Button::PreRelease | Button::PreReleaseSelected => {
-                    Some(Background::Color(PRERELEASE_CLICK))
+                    Some(Background::Color(fade(palette.prerelease, 0.5)))
                 }
                 Button::FilterOption | Button::FilterSelected => {
-                    Some(Background::Color(SECONDARY_CLICK))
+                    Some(Background::Color(fade(palette.secondary, 0.6)))
                 }
                 _ => hovered.background,
             },
@@ -223,27 +325,76 @@ impl button::StyleSheet for Button {
     }
 }
 
+/// a button style, with a focus ring drawn around it while it has keyboard focus
+pub struct Focusable {
+    style: Button,
+    focused: bool,
+}
+
+impl Button {
+    pub fn focused(self, focused: bool) -> Focusable {
+        Focusable {
+            style: self,
+            focused,
+        }
+    }
+}
+
+impl Focusable {
+    fn ring(&self, style: button::Style) -> button::Style {
+        if self.focused {
+            button::Style {
+                border_color: palette().focus,
+                border_width: 2.0,
+                ..style
+            }
+        } else {
+            style
+        }
+    }
+}
+
+impl button::StyleSheet for Focusable {
+    fn active(&self) -> button::Style {
+        self.ring(self.style.active())
+    }
+
+    fn hovered(&self) -> button::Style {
+        self.ring(self.style.hovered())
+    }
+
+    fn pressed(&self) -> button::Style {
+        self.ring(self.style.pressed())
+    }
+}
+
 pub enum Container {
     Error,
     Default,
+    Code,
 }
 
 impl container::StyleSheet for Container {
     fn style(&self) -> container::Style {
+        let palette = palette();
         let basic = container::Style {
-            text_color: Some(DEFAULT_FONT_COLOR),
+            text_color: Some(palette.text),
             border_radius: DEFAULT_BORDER_RADIUS,
-            background: Some(Background::Color(BLACK)),
+            background: Some(Background::Color(palette.background)),
             ..container::Style::default()
         };
 
         match self {
             Container::Error => container::Style {
                 text_color: Some(Color::WHITE),
-                background: Some(Background::Color(Color::from_rgb8(223, 84, 107))),
+                background: Some(Background::Color(palette.danger)),
                 ..basic
             },
             Container::Default => container::Style { ..basic },
+            Container::Code => container::Style {
+                background: Some(Background::Color(palette.surface)),
+                ..basic
+            },
         }
     }
 }
@@ -256,7 +407,7 @@ impl modal::StyleSheet for Modal {
     fn active(&self) -> modal::Style {
         match self {
             Modal::Default => modal::Style {
-                background: Background::Color(Color::from_rgba8(10, 10, 10, 0.86)),
+                background: Background::Color(palette().backdrop),
             },
         }
     }
@@ -268,13 +419,14 @@ pub enum Card {
 
 impl card::StyleSheet for Card {
     fn active(&self) -> card::Style {
+        let palette = palette();
         let basic = card::Style {
-            close_color: Color::WHITE,
-            border_color: Color::from_rgb8(74, 74, 74),
-            head_text_color: Color::WHITE,
-            body_text_color: Color::WHITE,
-            foot_text_color: Color::WHITE,
-            background: Background::Color(BLACK),
+            close_color: palette.text,
+            border_color: palette.border,
+            head_text_color: palette.text,
+            body_text_color: palette.text,
+            foot_text_color: palette.text,
+            background: Background::Color(palette.background),
             head_background: Color::TRANSPARENT.into(),
             border_radius: DEFAULT_BORDER_RADIUS,
             ..card::Style::default()
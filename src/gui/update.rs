@@ -1,18 +1,44 @@
-use std::{fs::remove_file, sync::Arc};
+use std::{collections::HashMap, convert::identity, fs::remove_file, path::Path, sync::Arc};
 
 use async_std::{sync::Mutex, task};
 use futures::{channel::mpsc, SinkExt};
 use iced::Command;
 use log::*;
-use pirate_midi_rs::*;
+use url::Url;
 
-use crate::command::{
-    device::{enter_bootloader, install_binary},
-    github::{fetch_asset, fetch_releases},
-    update::update_self,
+use ahoy_core::{
+    command::{
+        channel::Channel,
+        device::{check_device, enter_bootloader, install_binary, stage_firmware},
+        diagnostics,
+        doctor::report,
+        github::{
+            changelog, fetch_asset, fetch_releases, is_downgrade, update_for, Asset,
+            DownloadProgress, Release,
+        },
+        history::{read as read_history, try_record, JournalEntry, Source},
+        logging,
+        policy::{Policy, Target},
+        session::{
+            check_installed, restart_deadline, DeviceBackend, Event, Firmware, ReleaseBackend,
+            Stage, Step,
+        },
+        settings::save as save_settings,
+        signature::signature_path,
+        simulate::{self, Fault, Simulation},
+        transfer::{CancelToken, ProgressEvent},
+        version::Version,
+        CommandError,
+    },
+    i18n::{self, t},
 };
 
-use super::{usb, Ahoy, Message};
+use crate::update::{latest_version, update_self};
+
+use super::{
+    element::{install::ProgressTracker, log_panel::ExportStatus},
+    keyboard, markdown, style, usb, Ahoy, Message,
+};
 
 pub(crate) fn handle_message(ahoy: &mut Ahoy, message: Message) -> Command<Message> {
     match message {
@@ -24,14 +50,14 @@ pub(crate) fn handle_message(ahoy: &mut Ahoy, message: Message) -> Command<Messa
             }
         }
         Message::UpdateAvailable(Err(err)) => {
-            error!("issue with updates: {err}");
+            error!("issue with updates: {}", err.report());
         }
         Message::UpdateApplication => return Command::perform(update_self(false), Message::Exit),
         Message::Exit(result) => {
             let exit_code = match result {
                 Ok(_) => 0,
                 Err(err) => {
-                    error!("unable to install: {}", err);
+                    error!("unable to install: {}", err.report());
                     1
                 }
             };
@@ -42,91 +68,661 @@ pub(crate) fn handle_message(ahoy: &mut Ahoy, message: Message) -> Command<Messa
         Message::FetchReleases => {
             info!("fetching releases");
             ahoy.releases = None;
+            ahoy.firmware_status = super::FirmwareStatus::Unknown;
             ahoy.selected_version = None;
             info!("refresh requested - attempt to fetch releases...");
-            return Command::perform(fetch_releases(), Message::RetrievedReleases);
+            return retrieve_releases(ahoy);
         }
         Message::RetrievedReleases(Ok(releases)) => {
             info!("retrieved releases");
-            // grab first version that matches the filter
-            ahoy.selected_version = releases
-                .iter()
-                .cloned()
-                .find(|rel| ahoy.filter.matches(rel));
+            ahoy.refusals = refusals(ahoy, &releases);
+
+            // grab first version that matches the filter - one the policy allows, if there is one
+            let matching = || releases.iter().filter(|rel| ahoy.filter.matches(rel));
+            let selected = matching()
+                .find(|rel| !ahoy.refusals.contains_key(&rel.id))
+                .or_else(|| matching().next())
+                .cloned();
 
             // set our releases
             ahoy.releases = Some(releases);
+            select_release(ahoy, selected);
+            refresh_firmware_status(ahoy);
+        }
+        Message::RetrievedReleases(Err(err)) => ahoy.error = Some(err.into()),
+        Message::ToggleChannel(channel) => {
+            ahoy.filter.toggle(channel);
+            refresh_firmware_status(ahoy);
+            if !ahoy.filter.contains(Channel::Local) {
+                ahoy.local_file = None;
+            }
+
+            // move the selection if its channel was just hidden
+            let hidden = match &ahoy.selected_version {
+                Some(selected) => !ahoy.filter.matches(selected),
+                None => ahoy.local_file.is_none(),
+            };
+            if hidden {
+                let selected = ahoy
+                    .releases
+                    .iter()
+                    .flatten()
+                    .find(|rel| ahoy.filter.matches(rel))
+                    .cloned();
+                select_release(ahoy, selected);
+            }
         }
-        Message::RetrievedReleases(Err(err)) => {
-            ahoy.error = Some(super::Error::RemoteApi(err.to_string()))
-        }
-        Message::ReleaseFilterChanged(filter) => ahoy.filter = filter,
-        Message::SelectedRelease(release) => ahoy.selected_version = Some(*release),
-        Message::Download(asset) => {
-            info!("downloading asset");
-            return Command::perform(fetch_asset(*asset), Message::Downloaded);
-        }
-        Message::Downloaded(Ok(path)) => {
-            info!("downloaded release to: {}", path.display());
-            ahoy.installable_asset = Some(path.clone());
-            ahoy.confirm_modal.show(path);
-        }
-        Message::Downloaded(Err(err)) => {
-            ahoy.error = Some(super::Error::RemoteApi(err.to_string()))
-        }
-        Message::DeviceChangedAction(event) => match event {
-            usb::Event::Connect(device) => {
-                info!("DEVICE CONNECTED: {:?}", device);
-                // if a DFU device connects, and we have an asset, install it!
-                if ahoy.installable_asset.is_some() && device.is_dfu_device() {
-                    // create our channel for sharing install progress
-                    let (tx, rx) = mpsc::channel::<f32>(10);
-                    ahoy.device = super::DeviceState::DFU(
-                        Some(device.raw_device.unwrap()),
-                        Some(tx),
-                        Some(Arc::new(Mutex::new(rx))),
-                    );
-                    return self::handle_message(ahoy, Message::Install);
+        Message::SelectedRelease(release) => {
+            ahoy.local_file = None;
+            select_release(ahoy, Some(*release));
+        }
+        Message::SelectedLocalFile => {
+            select_release(ahoy, None);
+            ahoy.local_file = Some(String::new());
+        }
+        Message::LocalFileChanged(path) => ahoy.local_file = Some(path),
+        Message::InstallLocalFile => {
+            if let Some(path) = &ahoy.local_file {
+                let path = Path::new(path.trim());
+                ahoy.error = None;
+
+                let target = match &ahoy.device {
+                    super::DeviceState::Connected(details) => Target::device(details),
+                    _ => Target::default(),
+                };
+                if let Err(err) = permit(ahoy, |policy| policy.check_file(path, &target)) {
+                    ahoy.error = Some(err.into());
+                    return Command::none();
+                }
+
+                ahoy.pending_install = match (Source::from_file(path), &ahoy.device) {
+                    (Ok(source), super::DeviceState::Connected(details)) => {
+                        Some(JournalEntry::new("gui", source).with_device(details))
+                    }
+                    (Ok(source), _) => Some(JournalEntry::new("gui", source)),
+                    (Err(err), _) => {
+                        ahoy.error = Some(err.into());
+                        return Command::none();
+                    }
+                };
+                return match stage_firmware(path) {
+                    Ok(staged) => {
+                        let version = ahoy
+                            .pending_install
+                            .as_ref()
+                            .and_then(|entry| entry.source.version());
+                        begin(ahoy, Firmware::File(staged), version)
+                    }
+                    Err(err) => {
+                        ahoy.error = Some(err.into());
+                        Command::none()
+                    }
+                };
+            }
+        }
+        // links come from release notes, so only web pages are opened - never files or other apps
+        Message::OpenUrl(url) => match Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {
+                if let Err(err) = webbrowser::open(parsed.as_str()) {
+                    error!("unable to open {}: {}", url, err);
+                }
+            }
+            _ => warn!("refusing to open {}", url),
+        },
+        Message::InstallUpdate => {
+            if let (
+                super::FirmwareStatus::Available(release),
+                super::DeviceState::Connected(details),
+            ) = (&ahoy.firmware_status, &ahoy.device)
+            {
+                let release = *release.clone();
+                let asset = details
+                    .hardware_version
+                    .chars()
+                    .last()
+                    .and_then(|revision| release.find_asset(&details.device_model, revision))
+                    .cloned();
+
+                ahoy.local_file = None;
+                ahoy.history = None;
+                select_release(ahoy, Some(release));
+                if let Some(asset) = asset {
+                    return download(ahoy, Box::new(asset));
                 }
+            }
+        }
+        Message::Download(asset) => return download(ahoy, asset),
+        // updates can trail behind the download finishing
+        Message::DownloadProgress(progress) if ahoy.download_receiver.is_some() => {
+            ahoy.download_progress = Some(progress);
+            return advance(ahoy, Event::DownloadProgress(progress));
+        }
+        Message::DownloadProgress(_) => (),
+        Message::Downloaded(result) => {
+            ahoy.download_progress = None;
+            ahoy.download_receiver = None;
+            return advance(ahoy, Event::Downloaded(result));
+        }
+        // the simulated device is the only one there is, until the simulation stops
+        Message::DeviceChangedAction(event) if ahoy.simulation.is_some() => {
+            debug!("ignoring usb event during a simulation: {:?}", event);
+        }
+        Message::DeviceChangedAction(event) => {
+            match event {
+                usb::Event::Connect(device) => {
+                    info!("DEVICE CONNECTED: {:?}", device);
+                    if let (true, Some(raw_device)) =
+                        (device.is_dfu_device(), device.raw_device.clone())
+                    {
+                        ahoy.device = super::DeviceState::DFU(raw_device);
+
+                        // if a DFU device connects while we're waiting for one, install to it!
+                        if matches!(ahoy.session.stage(), Stage::AwaitingBootloader { .. }) {
+                            return advance(ahoy, Event::BootloaderReady(Ok(())));
+                        }
+
+                        // otherwise it's likely stuck after an interrupted install - offer to recover it
+                        info!("DFU device connected without a pending install - entering recovery");
+                        ahoy.recovery = super::RecoveryOptions::default();
+                        ahoy.releases = None;
+                        return Command::perform(fetch_releases(), Message::RetrievedReleases);
+                    }
 
-                // if we detect a device, attempt to get the details
-                if device.is_stm_device() {
-                    info!("device is STM!");
-                    // attempt to get the device details
-                    match PirateMIDIDevice::new().send(pirate_midi_rs::Command::Check) {
-                        Ok(response) => {
-                            if let Response::Check(details) = response {
+                    // if we detect a device, attempt to get the details
+                    if device.is_stm_device() {
+                        info!("device is STM!");
+                        // make sure we talk to the serial port of *this* device - never whichever answers first
+                        ahoy.port = ahoy.port_override.clone().or_else(|| device.serial_port());
+                        let details = match ahoy.port.as_deref() {
+                            Some(port) => check_device(Some(port)),
+                            None => Err(CommandError::Device(
+                                "cannot identify the device's serial port - pick it with --port"
+                                    .into(),
+                            )),
+                        };
+                        // a device coming back from an install is how we find out whether it took
+                        let verifying = matches!(ahoy.session.stage(), Stage::Verifying);
+                        match details {
+                            Ok(details) => {
                                 info!("DEVICE DETAILS: {:?}", details);
+                                let verified = check_installed(
+                                    ahoy.session.version(),
+                                    &details.firmware_version,
+                                );
                                 ahoy.device = super::DeviceState::Connected(details);
 
                                 // retrieve releases if we have a valid device
-                                return Command::perform(
-                                    fetch_releases(),
-                                    Message::RetrievedReleases,
-                                );
+                                let releases =
+                                    Command::perform(fetch_releases(), Message::RetrievedReleases);
+                                if verifying {
+                                    let verified = advance(ahoy, Event::Verified(verified));
+                                    return Command::batch([verified, releases]);
+                                }
+                                return releases;
+                            }
+                            Err(err) if verifying => {
+                                return advance(ahoy, Event::Verified(Err(err)));
+                            }
+                            Err(err) => {
+                                error!("error connecting to device: {}", err.report());
+                                cancel(ahoy);
                             }
-                        }
-                        Err(err) => {
-                            error!("error connecting to device: {:?}", err);
-                            return self::handle_message(ahoy, Message::Cancel);
                         }
                     }
                 }
+                usb::Event::Disconnect(device) => {
+                    info!("DEVICE DISCONNECTED: {:?}", device);
+                    ahoy.device = super::DeviceState::Disconnected;
+                    ahoy.port = None;
+                    return Command::none();
+                }
             }
-            usb::Event::Disconnect(device) => {
-                info!("DEVICE DISCONNECTED: {:?}", device);
-                match ahoy.device {
-                    crate::gui::DeviceState::PostInstall => (), // do nothing
-                    _ => {
-                        ahoy.device = super::DeviceState::Disconnected;
+        }
+        Message::EnterBootloader => return advance(ahoy, Event::Confirmed),
+        Message::WaitForBootloader(result) => {
+            return advance(ahoy, Event::BootloaderRequested(result))
+        }
+        Message::BootloaderReady(result) => return advance(ahoy, Event::BootloaderReady(result)),
+        Message::InstallProgress(event) => {
+            ahoy.install_progress.update(event);
+            return advance(ahoy, Event::Progress(event));
+        }
+        Message::CancelInstall => {
+            if let Some(token) = &ahoy.install_token {
+                token.cancel();
+            }
+        }
+        Message::PostInstallResult(result) => return advance(ahoy, Event::Flashed(result)),
+        // the restart deadline outlives a device that came back in time
+        Message::Verified(result) if matches!(ahoy.session.stage(), Stage::Verifying) => {
+            return advance(ahoy, Event::Verified(result))
+        }
+        Message::Verified(_) => (),
+        Message::RecoverModelChanged(model) => {
+            ahoy.recovery.model = model;
+            ahoy.recovery.revision = None;
+            ahoy.recovery.tag = None;
+        }
+        Message::RecoverRevisionChanged(revision) => {
+            ahoy.recovery.revision = Some(revision);
+            ahoy.recovery.tag = None;
+        }
+        Message::RecoverReleaseChanged(tag) => ahoy.recovery.tag = Some(tag),
+        Message::RecoverFileChanged(file) => ahoy.recovery.file = file,
+        Message::Recover => {
+            ahoy.error = None;
+            let options = ahoy.recovery.clone();
+
+            // a local file takes precedence over a release
+            if !options.file.trim().is_empty() {
+                let path = Path::new(options.file.trim());
+                let target = recovery_target(&options);
+                if let Err(err) = permit(ahoy, |policy| policy.check_file(path, &target)) {
+                    ahoy.error = Some(err.into());
+                    return Command::none();
+                }
+                ahoy.pending_install = Source::from_file(path)
+                    .ok()
+                    .map(|source| recovery_entry(source, &options));
+                return match stage_firmware(path) {
+                    Ok(staged) => {
+                        let version = ahoy
+                            .pending_install
+                            .as_ref()
+                            .and_then(|entry| entry.source.version());
+                        begin(ahoy, Firmware::File(staged), version)
+                    }
+                    Err(err) => {
+                        ahoy.error = Some(err.into());
+                        Command::none()
                     }
+                };
+            }
+
+            let asset = match (&ahoy.releases, options.revision, &options.tag) {
+                (Some(releases), Some(revision), Some(tag)) => releases
+                    .iter()
+                    .find(|release| &release.tag_name == tag)
+                    .and_then(|release| release.find_asset(options.model.id(), revision))
+                    .cloned(),
+                _ => None,
+            };
+
+            match asset {
+                Some(asset) => {
+                    if let Some(tag) = &options.tag {
+                        let source = Source::Release { tag: tag.clone() };
+                        ahoy.pending_install = Some(recovery_entry(source, &options));
+                    }
+                    return download(ahoy, Box::new(asset));
+                }
+                None => ahoy.error = Some(super::Error::Install(t!("recover-no-firmware"))),
+            }
+        }
+        Message::ToggleHistory => {
+            if ahoy.history.take().is_none() {
+                let uid = match &ahoy.device {
+                    super::DeviceState::Connected(details) => Some(details.uid.clone()),
+                    _ => None,
+                };
+                return Command::perform(
+                    async move { read_history(uid.as_deref()) },
+                    Message::HistoryLoaded,
+                );
+            }
+        }
+        Message::HistoryLoaded(Ok(entries)) => ahoy.history = Some(entries),
+        Message::HistoryLoaded(Err(err)) => {
+            error!("unable to read install history: {}", err.report())
+        }
+        Message::ToggleLog => {
+            ahoy.log_open = !ahoy.log_open;
+            ahoy.log_lines = logging::recent();
+        }
+        Message::RefreshLog => ahoy.log_lines = logging::recent(),
+        Message::ExportDiagnostics => {
+            ahoy.export_status = ExportStatus::Exporting;
+            let port = ahoy.port_override.clone();
+            return Command::perform(
+                async move {
+                    let report =
+                        report(port, env!("CARGO_PKG_VERSION"), latest_version().await).await;
+                    let path = diagnostics::default_path();
+                    diagnostics::export(&report, &path).map(|_| path)
+                },
+                Message::DiagnosticsExported,
+            );
+        }
+        Message::DiagnosticsExported(Ok(path)) => ahoy.export_status = ExportStatus::Saved(path),
+        Message::DiagnosticsExported(Err(err)) => {
+            error!("unable to export diagnostics: {}", err.report());
+            ahoy.export_status = ExportStatus::Failed(err.report().to_string());
+        }
+        Message::KeyPressed(key_code, modifiers) => {
+            // the key's message comes back around through the runtime, like any other
+            if let Some(message) = keyboard::handle_key(ahoy, key_code, modifiers) {
+                return Command::perform(async { message }, identity);
+            }
+        }
+        Message::ToggleSimulation => {
+            let simulation = match ahoy.simulation {
+                Some(_) => None,
+                None => Some(Simulation::default()),
+            };
+            return set_simulation(ahoy, simulation);
+        }
+        Message::CycleFault => {
+            if let Some(simulation) = &mut ahoy.simulation {
+                simulation.fault = Fault::cycle(simulation.fault);
+                info!("simulated failure: {:?}", simulation.fault);
+            }
+        }
+        Message::ShowSettings => ahoy.settings_modal.show(),
+        Message::HideSettings => ahoy.settings_modal.hide(),
+        Message::ThemeChanged(theme) => {
+            info!("switching to the {:?} theme", theme);
+            ahoy.settings.theme = theme;
+            style::set_theme(theme.into());
+            if let Err(err) = save_settings(&ahoy.settings) {
+                error!("unable to save settings: {}", err.report());
+            }
+        }
+        Message::LanguageChanged(language) => {
+            ahoy.settings.language = language.code();
+            i18n::init(ahoy.settings.language.as_deref());
+            info!("switching to {}", i18n::current().name);
+            if let Err(err) = save_settings(&ahoy.settings) {
+                error!("unable to save settings: {}", err.report());
+            }
+        }
+        // the finished install makes way for whatever is connected now
+        Message::AttemptReset => {
+            ahoy.session.reset();
+            // a simulated device is plugged straight back in
+            if let Some(simulation) = ahoy.simulation {
+                return set_simulation(ahoy, Some(simulation));
+            }
+        }
+        Message::Cancel => cancel(ahoy),
+    }
+    Command::none()
+}
+
+/// abandon the install, if there is one, and clean up after it
+fn cancel(ahoy: &mut Ahoy) {
+    info!("cancelling or cleaning up");
+    clean_up(ahoy);
+    ahoy.session.reset();
+}
+
+/// clear away what's left of an install - its progress, and its firmware file and signature
+fn clean_up(ahoy: &mut Ahoy) {
+    ahoy.install_progress = ProgressTracker::default();
+    ahoy.install_receiver = None;
+    ahoy.download_progress = None;
+    ahoy.download_receiver = None;
+    if let Some(asset_path) = ahoy.session.take_firmware() {
+        for path in [signature_path(&asset_path), asset_path] {
+            if path.exists() {
+                info!("deleting file: {}", path.display());
+                if let Err(err) = remove_file(&path) {
+                    error!("unable to delete file: {}", err.to_string());
+                }
+            }
+        }
+    }
+    ahoy.error = None;
+    // hide the modal - if open
+    ahoy.confirm_modal.hide();
+}
+
+/// select a release, and parse its notes once rather than on every redraw.
+/// notes cover every release between the installed firmware and the selection.
+fn select_release(ahoy: &mut Ahoy, release: Option<Release>) {
+    ahoy.release_notes = match &release {
+        Some(selected) => {
+            let mut releases = match (&ahoy.releases, &ahoy.device) {
+                (Some(releases), super::DeviceState::Connected(details)) => {
+                    changelog(releases, &details.firmware_version, selected)
+                }
+                _ => vec![],
+            };
+            if releases.is_empty() {
+                releases.push(selected);
+            }
+
+            let notes = match releases.as_slice() {
+                [single] => single.body.clone().unwrap_or_default(),
+                releases => releases
+                    .iter()
+                    .map(|release| {
+                        format!(
+                            "# {}\n\n{}",
+                            release.name.as_deref().unwrap_or(&release.tag_name),
+                            release.body.as_deref().unwrap_or_default()
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n\n---\n\n"),
+            };
+            markdown::parse(&notes)
+        }
+        None => vec![],
+    };
+    ahoy.selected_version = release;
+}
+
+/// swap the device and github for a simulation, or go back to the real ones
+pub(crate) fn set_simulation(ahoy: &mut Ahoy, simulation: Option<Simulation>) -> Command<Message> {
+    ahoy.simulation = simulation;
+    ahoy.error = None;
+    ahoy.history = None;
+    ahoy.port = None;
+    ahoy.releases = None;
+    ahoy.local_file = None;
+    ahoy.firmware_status = super::FirmwareStatus::Unknown;
+    select_release(ahoy, None);
+
+    match simulation {
+        Some(_) => {
+            info!("simulating a connected device");
+            ahoy.device = super::DeviceState::Connected(simulate::device_details());
+            retrieve_releases(ahoy)
+        }
+        // a real device shows up again once it's reconnected
+        None => {
+            info!("simulation stopped");
+            ahoy.device = super::DeviceState::Disconnected;
+            Command::none()
+        }
+    }
+}
+
+/// fetch the release list - or make one up, on a dry run
+fn retrieve_releases(ahoy: &Ahoy) -> Command<Message> {
+    match ahoy.simulation {
+        Some(_) => Command::perform(
+            async { Ok(simulate::releases()) },
+            Message::RetrievedReleases,
+        ),
+        None => Command::perform(fetch_releases(), Message::RetrievedReleases),
+    }
+}
+
+/// check something against the firmware policy - nothing is allowed while it can't be read
+fn permit(
+    ahoy: &Ahoy,
+    check: impl FnOnce(&Policy) -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    match &ahoy.policy_error {
+        Some(err) => Err(err.clone()),
+        None => check(&ahoy.policy),
+    }
+}
+
+/// the release about to be downloaded - the selected one, or the one picked for recovery
+fn pending_release(ahoy: &Ahoy) -> Option<(&Release, Target)> {
+    match &ahoy.device {
+        super::DeviceState::Connected(details) => {
+            Some((ahoy.selected_version.as_ref()?, Target::device(details)))
+        }
+        super::DeviceState::DFU(_) => {
+            let release = ahoy
+                .releases
+                .iter()
+                .flatten()
+                .find(|release| Some(&release.tag_name) == ahoy.recovery.tag.as_ref())?;
+            Some((release, recovery_target(&ahoy.recovery)))
+        }
+        super::DeviceState::Disconnected => None,
+    }
+}
+
+fn permit_release(ahoy: &Ahoy) -> Result<(), CommandError> {
+    match pending_release(ahoy) {
+        Some((release, target)) => permit(ahoy, |policy| policy.check_release(release, &target)),
+        None => Ok(()),
+    }
+}
+
+/// why the policy refuses each release on the connected device
+fn refusals(ahoy: &Ahoy, releases: &[Release]) -> HashMap<u64, String> {
+    let target = match &ahoy.device {
+        super::DeviceState::Connected(details) => Target::device(details),
+        _ => return HashMap::new(),
+    };
+    releases
+        .iter()
+        .filter_map(|release| {
+            permit(ahoy, |policy| policy.check_release(release, &target))
+                .err()
+                .map(|err| (release.id, err.report().to_string()))
+        })
+        .collect()
+}
+
+/// compare the connected device's firmware with the newest release it could run
+fn refresh_firmware_status(ahoy: &mut Ahoy) {
+    ahoy.firmware_status = match (&ahoy.releases, &ahoy.device) {
+        (Some(releases), super::DeviceState::Connected(details)) => {
+            // only releases the policy allows are offered as updates
+            let releases: Vec<Release> = releases
+                .iter()
+                .filter(|release| !ahoy.refusals.contains_key(&release.id))
+                .cloned()
+                .collect();
+            let update = match details.hardware_version.chars().last() {
+                Some(revision) => update_for(
+                    &releases,
+                    &ahoy.filter,
+                    &details.device_model,
+                    revision,
+                    &details.firmware_version,
+                ),
+                None => Err(CommandError::Device(
+                    "device did not report a hardware version".into(),
+                )),
+            };
+            // a device that can't be compared with releases is neither current nor behind
+            match update {
+                Ok(Some(release)) => super::FirmwareStatus::Available(Box::new(release.clone())),
+                Ok(None) => super::FirmwareStatus::Current,
+                Err(err) => {
+                    warn!("unable to check for firmware updates: {}", err.report());
+                    super::FirmwareStatus::Unknown
                 }
-                return Command::none();
             }
+        }
+        _ => super::FirmwareStatus::Unknown,
+    };
+}
+
+/// fetch a release asset and install it, if the policy allows the release
+fn download(ahoy: &mut Ahoy, asset: Box<Asset>) -> Command<Message> {
+    if let Err(err) = permit_release(ahoy) {
+        ahoy.error = Some(err.into());
+        return Command::none();
+    }
+    info!("downloading asset");
+    let version = pending_release(ahoy).and_then(|(release, _)| release.version());
+    begin(ahoy, Firmware::Asset(asset), version)
+}
+
+/// start an install session - straight to flashing if the device is already in DFU mode.
+/// `version` is what the device should report once it restarts, when it's known.
+fn begin(ahoy: &mut Ahoy, firmware: Firmware, version: Option<Version>) -> Command<Message> {
+    let in_bootloader = matches!(ahoy.device, super::DeviceState::DFU(_));
+    advance(
+        ahoy,
+        Event::Start {
+            firmware,
+            in_bootloader,
+            version,
         },
-        Message::EnterBootloader => {
-            // change the device state for quicker ui update
-            ahoy.device = super::DeviceState::DFU(None, None, None);
+    )
+}
+
+/// hand an event to the install session, and carry out whatever it asks for next
+fn advance(ahoy: &mut Ahoy, event: Event) -> Command<Message> {
+    match ahoy.session.handle(event) {
+        Step::Download(asset) => {
+            // progress is shared over a channel, like an install's
+            let (mut tx, rx) = mpsc::channel::<DownloadProgress>(10);
+            ahoy.download_progress = Some(DownloadProgress {
+                bytes: 0,
+                total: asset.size,
+            });
+            ahoy.download_receiver = Some(Arc::new(Mutex::new(rx)));
+
+            // the download runs on the executor, so updates are dropped rather than waited on
+            let progress_fn = move |progress| {
+                if let Err(err) = tx.try_send(progress) {
+                    debug!("skipping download progress: {err}");
+                }
+            };
+
+            match ahoy.simulation {
+                Some(simulation) => Command::perform(
+                    simulation
+                        .releases()
+                        .download(*asset, Box::new(progress_fn)),
+                    Message::Downloaded,
+                ),
+                None => Command::perform(fetch_asset(*asset, progress_fn), Message::Downloaded),
+            }
+        }
+        Step::Confirm(path) => {
+            // warn before going back to an older firmware
+            let warning = match (&ahoy.device, &ahoy.selected_version) {
+                (super::DeviceState::Connected(details), Some(selected))
+                    if is_downgrade(&details.firmware_version, selected) =>
+                {
+                    Some(t!(
+                        "downgrade-warning",
+                        installed = details.firmware_version.as_str(),
+                        version = selected.tag_name.as_str()
+                    ))
+                }
+                _ => None,
+            };
+            let signer = ahoy.session.signer().cloned();
+            ahoy.confirm_modal.show(path, warning, signer);
+            Command::none()
+        }
+        Step::EnterBootloader => {
+            // remember what we're installing over, for the install journal
+            if let Some(release) = &ahoy.selected_version {
+                let entry = JournalEntry::new(
+                    "gui",
+                    Source::Release {
+                        tag: release.tag_name.clone(),
+                    },
+                );
+                ahoy.pending_install = Some(match &ahoy.device {
+                    super::DeviceState::Connected(details) => entry.with_device(details),
+                    _ => entry,
+                });
+            }
 
             // hide the modal
             info!("hiding modal");
@@ -134,92 +730,148 @@ pub(crate) fn handle_message(ahoy: &mut Ahoy, message: Message) -> Command<Messa
 
             // send the command to enter bootloader mode
             info!("sending bootloader command...");
-            return Command::perform(enter_bootloader(), Message::WaitForBootloader);
-        }
-        Message::WaitForBootloader(Ok(())) => {
-            // wait for the DeviceChangedAction::Connect event!
-            return Command::none();
-        }
-        Message::WaitForBootloader(Err(err)) => {
-            ahoy.error = Some(super::Error::Install(err.to_string()));
-            return self::handle_message(ahoy, Message::Cancel);
+            match ahoy.simulation {
+                Some(simulation) => Command::perform(
+                    simulation.device(CancelToken::new()).enter_bootloader(),
+                    Message::WaitForBootloader,
+                ),
+                None => Command::perform(
+                    enter_bootloader(ahoy.port.clone()),
+                    Message::WaitForBootloader,
+                ),
+            }
         }
-        Message::InstallProgress(progress) => ahoy.install_progress += progress,
-        Message::Install => {
+        // a simulated device never shows up on the bus, so there's no connect event to wait for
+        Step::WaitForBootloader => match ahoy.simulation {
+            Some(simulation) => Command::perform(
+                simulation.device(CancelToken::new()).wait_for_bootloader(),
+                Message::BootloaderReady,
+            ),
+            None => Command::none(),
+        },
+        // wait for the DeviceChangedAction::Connect event!
+        Step::Wait => Command::none(),
+        Step::Flash(binary_path) => {
             info!("installing!");
+            let device = match &ahoy.device {
+                super::DeviceState::DFU(device) => Some(device.clone()),
+                _ => None,
+            };
 
-            match &ahoy.device {
-                crate::gui::DeviceState::DFU(device, sender, _) => {
-                    // get our firmware path
-                    let binary_path = ahoy
-                        .installable_asset
-                        .as_ref()
-                        .expect("downloaded asset went missing!");
-
-                    let progress_fn = {
-                        // get total file size to determine percentage
-                        let total = binary_path.metadata().unwrap().len();
-                        let mut tx = sender.as_ref().unwrap().clone();
-
-                        move |uploaded| {
-                            let percentage = (uploaded as f32 / total as f32) * 100.0;
-                            match task::block_on(async { tx.send(percentage).await }) {
-                                Ok(_) => (),
-                                Err(err) => error!("error sending install percentage: {err}"),
-                            };
-                        }
-                    };
+            // create our channel for sharing install progress
+            let (tx, rx) = mpsc::channel::<ProgressEvent>(10);
+            ahoy.install_receiver = Some(Arc::new(Mutex::new(rx)));
 
-                    return Command::perform(
-                        install_binary(
-                            binary_path.to_path_buf(),
-                            Some(progress_fn),
-                            device.clone(),
-                        ),
-                        Message::PostInstallResult,
-                    );
-                }
-                _ => return self::handle_message(ahoy, Message::Cancel),
-            }
-        }
-        Message::PostInstallResult(result) => {
-            match result {
-                Ok(_) => info!("post-install result: DONE"),
-                Err(err) => {
-                    error!("post-install result: {:?}", err);
-                    ahoy.error = Some(super::Error::Install(err.to_string()));
+            let progress_fn = {
+                let mut tx = tx;
+                move |event| {
+                    match task::block_on(async { tx.send(event).await }) {
+                        Ok(_) => (),
+                        Err(err) => error!("error sending install progress: {err}"),
+                    };
                 }
             };
 
-            ahoy.device = super::DeviceState::PostInstall;
-            return self::handle_message(ahoy, Message::Cancel); //send cancel to cleanup
+            // keep a handle on the transfer, so it can be cancelled from the ui
+            let token = CancelToken::new();
+            ahoy.install_token = Some(token.clone());
+
+            match ahoy.simulation {
+                Some(simulation) => Command::perform(
+                    simulation
+                        .device(token)
+                        .flash(binary_path, Box::new(progress_fn)),
+                    Message::PostInstallResult,
+                ),
+                None => Command::perform(
+                    install_binary(binary_path, Some(progress_fn), device, token, ahoy.timeouts),
+                    Message::PostInstallResult,
+                ),
+            }
         }
-        Message::AttemptReset => {
-            ahoy.device = super::DeviceState::Disconnected;
+        // a real device is checked once it connects again - unless it never does
+        Step::Verify(version) => match ahoy.simulation {
+            Some(simulation) => Command::perform(
+                simulation.device(CancelToken::new()).verify(version),
+                Message::Verified,
+            ),
+            None => Command::perform(restart_deadline(), Message::Verified),
+        },
+        Step::Finished(result) => finish(ahoy, result),
+    }
+}
+
+/// wrap up a finished session - only a transfer that was started leaves the device elsewhere
+fn finish(ahoy: &mut Ahoy, result: Result<(), CommandError>) -> Command<Message> {
+    let flashed = ahoy.install_token.take().is_some();
+    record_attempt(ahoy, &result);
+
+    // the session keeps a successful install on screen until it's closed
+    clean_up(ahoy);
+    if result.is_err() {
+        ahoy.session.reset();
+    }
+
+    match result {
+        Ok(_) => {
+            info!("post-install result: DONE");
+            Command::none()
         }
-        Message::Cancel => {
-            info!("cancelling or cleaning up");
-            // reset install progress
-            ahoy.install_progress = 0.0;
-            // delete the downloaded file if it exists
-            match &ahoy.installable_asset {
-                Some(asset_path) => {
-                    if asset_path.exists() {
-                        info!("deleting file: {}", asset_path.display());
-                        match remove_file(asset_path) {
-                            Ok(_) => ahoy.installable_asset = None,
-                            Err(err) => error!("unable to delete file: {}", err.to_string()),
-                        }
-                    } else {
-                        ahoy.installable_asset = None;
-                    }
+        // the device was put back in dfu idle - hand it over to the recovery screen
+        Err(err @ (CommandError::Cancelled | CommandError::Timeout(_))) if flashed => {
+            warn!("install stopped: {}", err.report());
+            ahoy.error = Some(err.into());
+
+            match ahoy.device {
+                super::DeviceState::DFU(_) => {
+                    ahoy.recovery = super::RecoveryOptions::default();
+                    ahoy.releases = None;
+                    Command::perform(fetch_releases(), Message::RetrievedReleases)
                 }
-                None => (), // do nothing
+                _ => match ahoy.simulation {
+                    // a simulated device is never really stuck, so it's plugged straight back in
+                    Some(simulation) => {
+                        let error = ahoy.error.take();
+                        let command = set_simulation(ahoy, Some(simulation));
+                        ahoy.error = error;
+                        command
+                    }
+                    None => Command::none(),
+                },
             }
-            ahoy.error = None;
-            // hide the modal - if open
-            ahoy.confirm_modal.hide();
+        }
+        Err(err) => {
+            error!("install failed: {:?}", err);
+            ahoy.error = Some(err.into());
+            Command::none()
         }
     }
-    Command::none()
+}
+
+/// finish the pending journal entry with the result of the attempt
+fn record_attempt(ahoy: &mut Ahoy, result: &Result<(), CommandError>) {
+    if let Some(entry) = ahoy.pending_install.take() {
+        // dry runs are left out of the journal
+        if ahoy.simulation.is_none() {
+            try_record(&entry.finish(result));
+        }
+    }
+}
+
+/// a device stuck in DFU mode can't be asked what it is, so the policy goes by what the user picked
+fn recovery_target(options: &super::RecoveryOptions) -> Target {
+    Target {
+        model: Some(options.model.id().to_string()),
+        revision: options.revision,
+        installed: None,
+    }
+}
+
+/// recovery has no device details to go on, so record what the user picked
+fn recovery_entry(source: Source, options: &super::RecoveryOptions) -> JournalEntry {
+    let entry = JournalEntry::new("gui", source);
+    match options.revision {
+        Some(revision) => entry.with_model(options.model.id(), revision),
+        None => entry,
+    }
 }
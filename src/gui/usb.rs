@@ -1,4 +1,4 @@
-use crate::usb::{
+use ahoy_core::usb::{
     observer::{self, UsbDevice},
     watcher,
 };
@@ -1,101 +1,198 @@
 use iced::{
-    alignment::Horizontal, Alignment, Button, Color, Column, Container, Element, Length, Row, Rule,
-    Space, Svg, Text,
+    alignment::Horizontal, Alignment, Button, Column, Container, Element, Length, Row, Rule, Space,
+    Svg, Text,
 };
 
+use ahoy_core::{command::session::Stage, i18n::t};
+
 use super::{
-    style, Ahoy, Message, DEFAULT_HEADING_FONT_SIZE, DEFAULT_PADDING, IMAGE_BRIDGE_4_DARK,
-    IMAGE_BRIDGE_6_DARK, IMAGE_PIRATE_MIDI_LOGO, IMAGE_USB_CABLE_DARK,
+    element::install::download_view,
+    keyboard::{self, Focus},
+    style, themed_svg, Ahoy, Message, DEFAULT_HEADING_FONT_SIZE, DEFAULT_PADDING,
+    IMAGE_BRIDGE_4_DARK, IMAGE_BRIDGE_4_LIGHT, IMAGE_BRIDGE_6_DARK, IMAGE_BRIDGE_6_LIGHT,
+    IMAGE_PIRATE_MIDI_LOGO, IMAGE_USB_CABLE_DARK, IMAGE_USB_CABLE_LIGHT, SECONDARY_FONT_SIZE,
 };
 
 pub(crate) fn handle_view(ahoy: &mut Ahoy) -> Element<Message> {
+    let focus = keyboard::focused(ahoy);
+
     /* WHEN A DEVICE IS NOT CONNECTED */
-    let usb_cable_image = Svg::new(IMAGE_USB_CABLE_DARK.clone()).height(Length::Units(400));
-    let bridge6 = Svg::new(IMAGE_BRIDGE_6_DARK.clone()).width(Length::Units(100));
-    let bridge4 = Svg::new(IMAGE_BRIDGE_4_DARK.clone()).width(Length::Units(100));
+    let usb_cable_image = Svg::new(themed_svg(&IMAGE_USB_CABLE_DARK, &IMAGE_USB_CABLE_LIGHT))
+        .height(Length::Units(400));
+    let bridge6 =
+        Svg::new(themed_svg(&IMAGE_BRIDGE_6_DARK, &IMAGE_BRIDGE_6_LIGHT)).width(Length::Units(100));
+    let bridge4 =
+        Svg::new(themed_svg(&IMAGE_BRIDGE_4_DARK, &IMAGE_BRIDGE_4_LIGHT)).width(Length::Units(100));
     let pm_logo =
         iced_native::widget::Image::new(IMAGE_PIRATE_MIDI_LOGO.clone()).width(Length::Units(200));
 
     // BUILD PRIMARY VIEW
-    let content: Element<Message> = match &ahoy.device {
-        super::DeviceState::Disconnected => Column::new()
+    let installing = ahoy.installing();
+    let content: Element<Message> = match (ahoy.session.stage(), &ahoy.device) {
+        // the install finished, and stays on screen until it's closed
+        (Stage::Done, _) => Column::new()
+            .align_items(Alignment::Center)
+            .spacing(DEFAULT_PADDING)
+            .width(Length::Fill)
+            .push(Space::with_height(Length::Fill))
+            .push(Text::new(t!("install-complete")).size(DEFAULT_HEADING_FONT_SIZE))
+            .push(Text::new(t!("install-complete-detail")))
+            .push(Space::with_height(Length::Units(DEFAULT_PADDING * 2)))
+            .push(
+                Button::new(
+                    &mut ahoy.reset_button,
+                    Text::new(t!("close")).horizontal_alignment(Horizontal::Center),
+                )
+                .on_press(Message::AttemptReset)
+                .padding(DEFAULT_PADDING)
+                .width(Length::Units(130))
+                .style(style::Button::SuccessAction.focused(focus == Some(Focus::Close))),
+            )
+            .push(Space::with_height(Length::Fill))
+            .push(pm_logo)
+            .into(),
+        // an install in progress, whatever is connected at the moment
+        (stage, _) if installing => Column::new()
+            .padding(DEFAULT_PADDING)
+            .align_items(Alignment::Center)
+            // .push(ahoy.status.view(&details))
+            // .push(Rule::horizontal(1))
+            .push(Space::with_height(Length::Fill))
+            .push(
+                ahoy.installer.view(
+                    &ahoy.install_progress,
+                    stage,
+                    ahoy.install_token
+                        .as_ref()
+                        .map(|token| token.is_cancelled())
+                        .unwrap_or(false),
+                ),
+            )
+            .push(Space::with_height(Length::Fill))
+            .push(pm_logo)
+            .into(),
+        (_, super::DeviceState::Disconnected) => Column::new()
             .align_items(Alignment::Center)
             .spacing(DEFAULT_PADDING)
             .width(Length::Fill)
+            .push(
+                Row::new()
+                    .padding(DEFAULT_PADDING)
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        Button::new(
+                            &mut ahoy.settings_button,
+                            Text::new(t!("settings")).horizontal_alignment(Horizontal::Center),
+                        )
+                        .on_press(Message::ShowSettings)
+                        .padding(DEFAULT_PADDING)
+                        .width(Length::Units(100))
+                        .style(style::Button::FilterOption.focused(focus == Some(Focus::Settings))),
+                    ),
+            )
             .push(usb_cable_image)
             .push(Space::with_height(Length::Units(DEFAULT_PADDING * 2)))
-            .push(Text::new("Please connect your").size(DEFAULT_HEADING_FONT_SIZE))
+            .push(Text::new(t!("connect-prompt")).size(DEFAULT_HEADING_FONT_SIZE))
             .push(
                 Row::new()
                     .align_items(Alignment::Center)
                     .spacing(DEFAULT_PADDING * 2)
                     .push(bridge6)
-                    .push(Text::new("or").size(DEFAULT_HEADING_FONT_SIZE))
+                    .push(Text::new(t!("or")).size(DEFAULT_HEADING_FONT_SIZE))
                     .push(bridge4),
             )
             .push(Space::with_height(Length::Fill))
             .push(pm_logo)
             .into(),
-        super::DeviceState::Connected(details) => {
+        (_, super::DeviceState::Connected(details)) => {
             // selecting a release
             let inner_content = Column::new()
                 .padding(DEFAULT_PADDING)
-                .push(ahoy.status.view(&details))
+                .push(ahoy.status.view(&details, &ahoy.firmware_status))
                 .push(Rule::horizontal(1))
-                .push(ahoy.controls.view(&ahoy.filter))
+                .push(
+                    ahoy.controls
+                        .view(&ahoy.filter, ahoy.history.is_some(), focus),
+                )
                 .push(Rule::horizontal(1))
-                .push(ahoy.versions.view(
-                    &ahoy.error,
-                    &ahoy.filter,
-                    &ahoy.releases,
-                    &details,
-                    &ahoy.selected_version,
-                ))
-                .into();
+                .push(match &ahoy.history {
+                    Some(entries) => ahoy.history_view.view(entries),
+                    None => ahoy.versions.view(
+                        &ahoy.error,
+                        &ahoy.filter,
+                        &ahoy.local_file,
+                        &ahoy.releases,
+                        &details,
+                        &ahoy.selected_version,
+                        &ahoy.refusals,
+                        &ahoy.release_notes,
+                        focus,
+                    ),
+                });
+            let inner_content = match ahoy.download_progress {
+                Some(progress) => inner_content
+                    .push(Rule::horizontal(1))
+                    .push(download_view(progress))
+                    .into(),
+                None => inner_content.into(),
+            };
 
             // wrap modal around the inner content
-            ahoy.confirm_modal.view(inner_content)
+            ahoy.confirm_modal.view(inner_content, focus)
         }
-        // device is connected in DFU mode
-        super::DeviceState::DFU(device, _, _) => Column::new()
+        // device is stuck in DFU mode, without anything to install
+        (_, super::DeviceState::DFU(_)) => Column::new()
             .padding(DEFAULT_PADDING)
             .align_items(Alignment::Center)
-            // .push(ahoy.status.view(&details))
-            // .push(Rule::horizontal(1))
-            .push(Space::with_height(Length::Fill))
-            .push(ahoy.installer.view(ahoy.install_progress, device))
-            .push(Space::with_height(Length::Fill))
-            .push(pm_logo)
-            .into(),
-        super::DeviceState::PostInstall => Column::new()
-            .align_items(Alignment::Center)
-            .spacing(DEFAULT_PADDING)
-            .width(Length::Fill)
             .push(Space::with_height(Length::Fill))
-            .push(Text::new("Installation Complete!").size(DEFAULT_HEADING_FONT_SIZE))
-            .push(Text::new("Unplug your device and go forth brave explorer!"))
-            .push(Space::with_height(Length::Units(DEFAULT_PADDING * 2)))
             .push(
-                Button::new(
-                    &mut ahoy.reset_button,
-                    Text::new("Close").horizontal_alignment(Horizontal::Center),
-                )
-                .on_press(Message::AttemptReset)
-                .padding(DEFAULT_PADDING)
-                .width(Length::Units(130))
-                .style(style::Button::SuccessAction),
+                ahoy.recover_view
+                    .view(&ahoy.error, &ahoy.recovery, &ahoy.releases),
             )
+            .push(match ahoy.download_progress {
+                Some(progress) => download_view(progress),
+                None => Space::new(Length::Units(0), Length::Units(0)).into(),
+            })
             .push(Space::with_height(Length::Fill))
             .push(pm_logo)
             .into(),
     };
 
-    // make update modal available in all states
-    let content = ahoy.update_modal.view(content);
+    // a dry run says so on every screen, so it's never mistaken for the real thing
+    let mut column = Column::new().height(Length::Fill);
+    if let Some(simulation) = &ahoy.simulation {
+        let fault = match simulation.fault {
+            Some(fault) => fault.to_string(),
+            None => t!("simulation-no-fault"),
+        };
+        column = column.push(
+            Container::new(
+                Text::new(t!("simulation-banner", fault = fault.as_str()))
+                    .size(SECONDARY_FONT_SIZE),
+            )
+            .width(Length::Fill)
+            .padding(DEFAULT_PADDING / 2)
+            .center_x()
+            .style(style::Container::Code),
+        );
+    }
+
+    // the log panel sits under every screen, and under the modals
+    let content: Element<Message> = column
+        .push(content)
+        .push(
+            ahoy.log_panel
+                .view(ahoy.log_open, &ahoy.log_lines, &ahoy.export_status, focus),
+        )
+        .into();
+
+    // make settings and update modals available in all states
+    let content = ahoy.settings_modal.view(content, &ahoy.settings, focus);
+    let content = ahoy.update_modal.view(content, focus);
 
     // setup graphical debugging
     let output = if ahoy.debug {
-        content.explain(Color::BLACK)
+        content.explain(style::palette().text)
     } else {
         content
     };
@@ -6,32 +6,61 @@
     windows_subsystem = "windows"
 )]
 
-use std::{process::exit, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::exit,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    cli::{Args, Commands},
+    cli::{Args, Commands, RecoverArgs, SetupTarget, StationArgs},
+    update::{latest_version, update_self},
+};
+use ahoy_core::{
     command::{
-        device::{enter_bootloader, install_binary},
-        update::update_self,
+        channel::{Channel, Channels},
+        device::check_device,
+        diagnostics,
+        doctor::{libusb_version, report},
+        github::{fetch_asset, fetch_releases, latest_in, update_for, Asset, Release},
+        history::{format_timestamp, read as read_history, try_record, JournalEntry, Source},
+        logging,
+        policy::{self, Policy, Target},
+        session::{
+            self, DeviceBackend, Event, Firmware, Github, InstallSession, PirateDevice,
+            ReleaseBackend, Stage,
+        },
+        settings,
+        setup::{install_udev_rules, SetupOutcome, UDEV_RULES_PATH},
+        signature::{self, Verifier},
+        simulate::{self, Simulation},
+        station::{StationLog, StationRecord, Verdict},
+        transfer::{CancelToken, ProgressEvent, Timeouts},
+        version::Version,
+        CommandError, Context,
     },
+    i18n::{self, t},
+    usb::observer::{Event as UsbEvent, Observer, UsbDevice},
+    USB_TIMEOUT,
 };
 use async_std::task;
 use clap::Parser;
-use log::{error, info};
+use crossbeam_channel::RecvTimeoutError;
+use log::{error, info, warn};
+use pirate_midi_rs::check::CheckResponse;
 
 mod cli;
-mod command;
 mod gui;
-mod usb;
+mod server;
+mod update;
 
 // GLOBALS
-const USB_VENDOR_ID: u16 = 0x0483;
-const USB_PRODUCT_ID: u16 = 0x5740;
-const USB_PRODUCT_DFU_ID: u16 = 0xDF11;
-const USB_TIMEOUT: Duration = Duration::from_secs(1);
-const GITHUB_API_URL: &str = "https://api.github.com";
-const GITHUB_ORG: &str = "Pirate-MIDI";
-const GITHUB_REPO: &str = "Pirate-MIDI-BridgeOS";
+// kept below 256, so scripts see the same code on every platform
+const EXIT_UPDATE_AVAILABLE: i32 = 100;
+const EXIT_DOCTOR_FAILED: i32 = 101;
+// how long a freshly plugged in unit takes to bring up its serial port
+const STATION_SETTLE_DELAY: Duration = Duration::from_secs(2);
 
 fn main() {
     // parse the arguments
@@ -46,107 +75,329 @@ fn main() {
         _ => rusb::set_log_level(rusb::LogLevel::Debug),
     }
 
-    // configure std logging
-    stderrlog::new()
-        .module(module_path!())
-        .module("pirate_midi_rs")
-        .module("dfu_libusb")
-        .module("dfu_core")
-        .module("rusb")
-        .module("surf")
-        .verbosity(args.verbose)
-        .timestamp(stderrlog::Timestamp::Second)
-        .init()
-        .unwrap();
+    // configure std logging, keeping a copy for the log panel and diagnostics
+    logging::init(env!("CARGO_CRATE_NAME"), args.verbose);
 
     info!("ahoy matey - starting up...");
 
-    let version = rusb::version();
+    info!("libusb {}", libusb_version());
 
-    info!(
-        "libusb v{}.{}.{}.{}{}",
-        version.major(),
-        version.minor(),
-        version.micro(),
-        version.nano(),
-        version.rc().unwrap_or("")
-    );
+    // pick a language before anything is printed
+    i18n::init(settings::load().language.as_deref());
+
+    // an explicitly selected serial port and timeouts apply to every device command
+    let port = args.port.clone();
+    let timeouts = args.timeouts();
+    let simulation = args.simulation();
+    let policy_file = args.policy.clone();
+    let verifier = signature::load(args.require_signature);
 
     // execute!
     match args.command {
         Some(cmd) => match cmd {
-            // Commands::List => todo!(),
             Commands::Install(args) => task::block_on(async {
-                // get file size
-                let file_size = match args.file.metadata() {
-                    Ok(meta) => meta.len(),
-                    Err(err) => {
-                        error!("unable to retrieve file size: {}", err);
-                        std::process::exit(0x0200);
-                    }
+                // identify the firmware for the install journal
+                let source = match Source::from_file(&args.file) {
+                    Ok(source) => source,
+                    Err(err) => fail(err),
                 };
-                info!("binary size: {}", file_size);
-
-                // send or skip booloader command
-                if !args.skip_bootloader {
-                    // enter bootloader
-                    println!("entering bootloader mode...");
-                    match enter_bootloader().await {
-                        Ok(_) => (), // continue
-                        Err(err) => {
-                            error!("device unable to enter bootloader mode: {}", err);
-                            std::process::exit(0x0300);
-                        }
-                    };
+                let version = source.version();
+                let mut entry = JournalEntry::new("cli", source);
 
-                    // sleep to wait for bootloader mode
-                    println!("pausing thread for 3 seconds to wait for bootloader mode...");
-                    std::thread::sleep(Duration::from_secs(3));
+                // remember what the device was running before we replace it
+                let details = match (args.skip_bootloader, simulation) {
+                    (true, _) => None,
+                    (false, Some(_)) => Some(simulate::device_details()),
+                    (false, None) => check_device(port.as_deref())
+                        .map_err(|err| warn!("{}", err))
+                        .ok(),
+                };
+                if let Some(details) = &details {
+                    entry = entry.with_device(details);
                 }
 
-                // attempt install
-                println!("installing...");
+                let target = details.as_ref().map(Target::device).unwrap_or_default();
+                if let Err(err) =
+                    enforced_policy(policy_file.as_deref()).check_file(&args.file, &target)
+                {
+                    fail(err);
+                }
 
-                // create progress bar
-                let bar = indicatif::ProgressBar::new(file_size as u64);
-                bar.set_style(
-                    indicatif::ProgressStyle::default_bar()
-                        .template(
-                            "{spinner:.green} [{elapsed_precise}] [{bar:27.cyan/blue}] \
-                            {bytes}/{total_bytes} ({bytes_per_sec}) ({eta}) {msg:10}",
-                        )
-                        .unwrap()
-                        .progress_chars("#>-"),
-                );
-
-                let install_result = install_binary(
-                    args.file,
-                    Some({
-                        let bar = bar.clone();
-                        move |count| {
-                            bar.inc(count as u64);
+                // attempt install - skipping the booloader command if asked to
+                let result = install(
+                    Firmware::File(args.file),
+                    args.skip_bootloader,
+                    version,
+                    port.clone(),
+                    timeouts,
+                    simulation,
+                    verifier,
+                )
+                .await;
+                // dry runs are left out of the journal
+                if simulation.is_none() {
+                    try_record(&entry.finish(&result));
+                }
+                exit_on_failure(result);
+            }),
+            Commands::Recover(args) => task::block_on(async {
+                let policy = enforced_policy(policy_file.as_deref());
+                let target = Target::model(args.model.id(), args.revision);
+                let (firmware, source) = match &args.file {
+                    Some(file) => match policy
+                        .check_file(file, &target)
+                        .and_then(|_| Source::from_file(file))
+                    {
+                        Ok(source) => (Firmware::File(file.clone()), source),
+                        Err(err) => fail(err),
+                    },
+                    None => {
+                        match &args.tag {
+                            Some(tag) => {
+                                println!("{}", t!("cli-fetching-release", tag = tag.as_str()))
+                            }
+                            None => println!(
+                                "{}",
+                                t!(
+                                    "cli-fetching-latest",
+                                    channels = Channels::new(args.channels.clone()).to_string()
+                                )
+                            ),
                         }
-                    }),
-                    None,
+                        let (tag, asset) =
+                            match fetch_recovery_asset(&args, &policy, &target, simulation).await {
+                                Ok(found) => found,
+                                Err(err) => fail(err),
+                            };
+                        (Firmware::Asset(Box::new(asset)), Source::Release { tag })
+                    }
+                };
+                let version = source.version();
+                let entry =
+                    JournalEntry::new("cli", source).with_model(args.model.id(), args.revision);
+
+                // the device is already in DFU mode, so there's no bootloader command to send
+                let result = install(
+                    firmware,
+                    true,
+                    version,
+                    port.clone(),
+                    timeouts,
+                    simulation,
+                    verifier,
                 )
                 .await;
+                if simulation.is_none() {
+                    try_record(&entry.finish(&result));
+                }
+                exit_on_failure(result);
+            }),
+            Commands::List(args) => task::block_on(async {
+                let channels = Channels::new(args.channels);
+                let releases: Vec<Release> = match fetch_releases().await {
+                    Ok(releases) => releases
+                        .into_iter()
+                        .filter(|release| channels.matches(release))
+                        .collect(),
+                    Err(err) => fail(err),
+                };
 
-                // handle results
-                match install_result {
-                    Ok(_) => (),
-                    Err(err) => {
-                        error!("unable to install: {:?}", err);
-                        std::process::exit(0x0400);
+                if args.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&releases)
+                            .expect("unable to serialize releases")
+                    );
+                } else if releases.is_empty() {
+                    println!("{}", t!("cli-no-releases", channels = channels.to_string()));
+                } else {
+                    for release in releases {
+                        println!(
+                            "{:<20} {:<8} {:<12} {}",
+                            release.tag_name,
+                            Channel::of(&release),
+                            release
+                                .published_at
+                                .as_deref()
+                                .unwrap_or(&release.created_at)
+                                .split('T')
+                                .next()
+                                .unwrap_or_default(),
+                            release.name.as_deref().unwrap_or_default()
+                        );
                     }
                 }
+            }),
+            Commands::Check(args) => task::block_on(async {
+                let details = match check_device(port.as_deref()) {
+                    Ok(details) => details,
+                    Err(err) => fail(err),
+                };
+                // only updates the policy allows are offered
+                let policy = enforced_policy(policy_file.as_deref());
+                let target = Target::device(&details);
+                let releases: Vec<Release> = match fetch_releases().await {
+                    Ok(releases) => releases
+                        .into_iter()
+                        .filter(|release| policy.check_release(release, &target).is_ok())
+                        .collect(),
+                    Err(err) => fail(err),
+                };
+                let revision = match details.hardware_version.chars().last() {
+                    Some(revision) => revision,
+                    None => fail(CommandError::Device(
+                        "device did not report a hardware version".into(),
+                    )),
+                };
 
-                // finish progress bar
-                bar.finish();
+                match update_for(
+                    &releases,
+                    &Channels::new(args.channels),
+                    &details.device_model,
+                    revision,
+                    &details.firmware_version,
+                ) {
+                    Ok(Some(release)) => {
+                        println!(
+                            "{}",
+                            t!(
+                                "cli-update-available",
+                                device = details.device_name.as_str(),
+                                installed = details.firmware_version.as_str(),
+                                version = release.tag_name.as_str()
+                            )
+                        );
+                        std::process::exit(EXIT_UPDATE_AVAILABLE);
+                    }
+                    Ok(None) => println!(
+                        "{}",
+                        t!(
+                            "cli-up-to-date",
+                            device = details.device_name.as_str(),
+                            installed = details.firmware_version.as_str()
+                        )
+                    ),
+                    Err(err) => fail(err),
+                }
             }),
+            Commands::History(args) => match read_history(args.device.as_deref()) {
+                Ok(entries) if args.json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&entries).expect("unable to serialize history")
+                ),
+                Ok(entries) if entries.is_empty() => println!("{}", t!("cli-no-history")),
+                Ok(entries) => {
+                    for entry in entries {
+                        println!(
+                            "{}  {:<8} {:<10} {:<26} {} -> {}  [{}]{}",
+                            format_timestamp(entry.timestamp),
+                            entry.model.as_deref().unwrap_or("unknown"),
+                            entry.hardware_version.as_deref().unwrap_or("-"),
+                            entry.device_uid.as_deref().unwrap_or("-"),
+                            entry.previous_version.as_deref().unwrap_or("?"),
+                            match &entry.source {
+                                Source::Release { tag } => tag.clone(),
+                                Source::File { name, sha256 } => format!(
+                                    "{} (sha256 {})",
+                                    name,
+                                    sha256.get(..12).unwrap_or(sha256)
+                                ),
+                            },
+                            entry.outcome,
+                            entry
+                                .error
+                                .map(|err| format!(" - {}", err))
+                                .unwrap_or_default(),
+                        );
+                    }
+                }
+                Err(err) => fail(err),
+            },
             Commands::Update => task::block_on(async {
                 match update_self(true).await {
-                    Ok(_) => println!("update complete"),
-                    Err(err) => error!("unable to perform update: {}", err),
+                    Ok(_) => println!("{}", t!("cli-update-complete")),
+                    Err(err) => fail(err),
+                }
+            }),
+            Commands::Setup(args) => match args.target {
+                SetupTarget::Udev => match install_udev_rules() {
+                    Ok(SetupOutcome::Installed) => {
+                        println!("{}", t!("cli-udev-installed", path = UDEV_RULES_PATH));
+                        println!("{}", t!("cli-udev-reconnect"));
+                    }
+                    Ok(SetupOutcome::Printed(rules)) => {
+                        println!("# {}", t!("cli-udev-save", path = UDEV_RULES_PATH));
+                        println!(
+                            "# {}: sudo udevadm control --reload-rules && sudo udevadm trigger",
+                            t!("cli-udev-reload")
+                        );
+                        println!("# {}\n", t!("cli-udev-sudo"));
+                        print!("{}", rules);
+                    }
+                    Err(err) => fail(err),
+                },
+            },
+            Commands::Doctor(args) => task::block_on(async {
+                let report = report(
+                    port.clone(),
+                    env!("CARGO_PKG_VERSION"),
+                    latest_version().await,
+                )
+                .await;
+                let output = if args.json {
+                    serde_json::to_string_pretty(&report).expect("unable to serialize report")
+                } else {
+                    report.to_string()
+                };
+
+                println!("{}", output);
+
+                if let Some(path) = args.output {
+                    match std::fs::write(&path, &output) {
+                        Ok(_) => println!(
+                            "{}",
+                            t!("cli-report-written", path = path.display().to_string())
+                        ),
+                        Err(err) => error!("unable to write report: {}", err),
+                    }
+                }
+
+                if let Some(path) = args.bundle {
+                    match diagnostics::export(&report, &path) {
+                        Ok(_) => println!(
+                            "{}",
+                            t!("cli-diagnostics-written", path = path.display().to_string())
+                        ),
+                        Err(err) => error!("unable to export diagnostics: {}", err.report()),
+                    }
+                }
+
+                if report.has_failures() {
+                    std::process::exit(EXIT_DOCTOR_FAILED);
+                }
+            }),
+            Commands::Station(args) => task::block_on(async {
+                let policy = enforced_policy(policy_file.as_deref());
+                if let Err(err) = station(args, &policy, verifier, timeouts).await {
+                    fail(err);
+                }
+            }),
+            Commands::Serve(args) => task::block_on(async {
+                let token = server::new_token().unwrap_or_else(|err| fail(err));
+                println!("{}", t!("cli-serving", address = args.bind.as_str()));
+                println!("{}", t!("cli-serving-token", token = token.as_str()));
+                let policy = enforced_policy(policy_file.as_deref());
+                if let Err(err) = server::serve(
+                    &args.bind,
+                    token,
+                    args.firmware_dir,
+                    policy,
+                    verifier,
+                    timeouts,
+                )
+                .await
+                {
+                    fail(err);
                 }
             }),
         },
@@ -159,3 +410,487 @@ fn main() {
         }
     }
 }
+
+/// run an install session to the end - on the device, or against a simulated one on a dry run
+async fn install(
+    firmware: Firmware,
+    in_bootloader: bool,
+    version: Option<Version>,
+    port: Option<String>,
+    timeouts: Timeouts,
+    simulation: Option<Simulation>,
+    verifier: Verifier,
+) -> Result<(), CommandError> {
+    // ctrl-c stops the transfer between blocks, so the device is left in a clean state
+    let token = CancelToken::new();
+    if let Err(err) = ctrlc::set_handler({
+        let token = token.clone();
+        move || token.cancel()
+    }) {
+        error!("unable to register ctrl-c handler: {}", err);
+    }
+
+    match simulation {
+        Some(simulation) => {
+            println!("{}", t!("cli-dry-run"));
+            if let Some(fault) = simulation.fault {
+                println!("{}", t!("cli-dry-run-fault", fault = fault.to_string()));
+            }
+            show_install(
+                firmware,
+                in_bootloader,
+                version,
+                &simulation.releases(),
+                &simulation.device(token),
+                verifier,
+            )
+            .await
+        }
+        None => {
+            let device = PirateDevice {
+                port,
+                raw_device: None,
+                token,
+                timeouts,
+            };
+            show_install(firmware, in_bootloader, version, &Github, &device, verifier).await
+        }
+    }
+}
+
+/// drive an install session with a progress bar for the transfer
+async fn show_install(
+    firmware: Firmware,
+    in_bootloader: bool,
+    version: Option<Version>,
+    releases: &impl ReleaseBackend,
+    device: &impl DeviceBackend,
+    verifier: Verifier,
+) -> Result<(), CommandError> {
+    // a progress bar is only drawn while there's something to download or transfer
+    let mut bar: Option<indicatif::ProgressBar> = None;
+    let mut session = InstallSession::new().verifying(verifier);
+    let install_result = session::run(
+        &mut session,
+        firmware,
+        in_bootloader,
+        version,
+        releases,
+        device,
+        |event, session| {
+            if let Event::Downloaded(result) = event {
+                match (bar.take(), result) {
+                    (Some(bar), Ok(_)) => bar.finish(),
+                    (Some(bar), Err(_)) => bar.abandon(),
+                    (None, _) => (),
+                }
+            }
+            match (event, session.stage()) {
+                (
+                    Event::Start {
+                        firmware: Firmware::Asset(asset),
+                        ..
+                    },
+                    _,
+                ) => {
+                    println!("{}", t!("cli-downloading", name = asset.name.as_str()));
+                    bar = Some(progress_bar(asset.size));
+                }
+                (Event::DownloadProgress(progress), _) => {
+                    if let Some(bar) = &bar {
+                        bar.set_position(progress.bytes);
+                    }
+                }
+                // the firmware is ready - say who signed it before going any further
+                (
+                    Event::Start { .. } | Event::Downloaded(Ok(_)),
+                    Stage::AwaitingBootloader { requested: false },
+                ) => {
+                    if let Some(signer) = session.signer() {
+                        println!("{}", signer);
+                    }
+                }
+                (Event::Confirmed, _) => println!("{}", t!("cli-entering-bootloader")),
+                (Event::BootloaderRequested(Ok(())), _) => {
+                    println!("{}", t!("cli-waiting-for-bootloader"))
+                }
+                (Event::Progress(progress), _) => {
+                    if let Some(bar) = &bar {
+                        match progress {
+                            ProgressEvent::Writing { bytes, .. } => {
+                                bar.set_position(*bytes);
+                                bar.set_message(t!("cli-progress-writing"));
+                            }
+                            ProgressEvent::Manifesting => {
+                                bar.set_message(t!("cli-progress-manifesting"))
+                            }
+                            ProgressEvent::Done => bar.set_message(t!("cli-progress-done")),
+                        }
+                    }
+                }
+                (Event::Flashed(Ok(())), Stage::Verifying) => {
+                    if let Some(bar) = bar.take() {
+                        bar.finish();
+                    }
+                    println!("{}", t!("cli-verifying"));
+                }
+                // anything else that leads to flashing starts the transfer
+                (_, Stage::Flashing) => {
+                    // recoveries go straight from the firmware being ready to installing it
+                    if let (Event::Start { .. } | Event::Downloaded(Ok(_)), Some(signer)) =
+                        (event, session.signer())
+                    {
+                        println!("{}", signer);
+                    }
+                    let file_size = session
+                        .firmware()
+                        .and_then(|file| file.metadata().ok())
+                        .map(|metadata| metadata.len())
+                        .unwrap_or_default();
+                    info!("binary size: {}", file_size);
+
+                    println!("{}", t!("cli-installing"));
+                    bar = Some(progress_bar(file_size));
+                }
+                _ => (),
+            }
+        },
+    )
+    .await;
+
+    // finish progress bar
+    match (&bar, &install_result) {
+        (Some(bar), Ok(_)) => bar.finish(),
+        (Some(bar), Err(_)) => bar.abandon(),
+        (None, _) => (),
+    }
+
+    install_result
+}
+
+/// a bar for downloading or transferring `total` bytes
+fn progress_bar(total: u64) -> indicatif::ProgressBar {
+    let bar = indicatif::ProgressBar::new(total);
+    bar.set_style(
+        indicatif::ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:27.cyan/blue}] \
+                {bytes}/{total_bytes} ({bytes_per_sec}) ({eta}) {msg:16}",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    bar
+}
+
+/// what a station flashes - a release is downloaded once for each model and revision
+enum StationFirmware {
+    File(PathBuf, Source),
+    Release(Box<Release>),
+}
+
+/// release assets a station has downloaded, by model and revision - each is verified once
+struct StationDownloads {
+    verifier: Verifier,
+    files: HashMap<(String, char), PathBuf>,
+}
+
+/// flash every unit that gets plugged in, until ctrl-c
+async fn station(
+    args: StationArgs,
+    policy: &Policy,
+    verifier: Verifier,
+    timeouts: Timeouts,
+) -> Result<(), CommandError> {
+    let path = PathBuf::from(&args.firmware);
+    let firmware = if path.is_file() {
+        let source = Source::from_file(&path)?;
+        println!("{}", verifier.verify(&path)?);
+        StationFirmware::File(path, source)
+    } else {
+        println!(
+            "{}",
+            t!("cli-fetching-release", tag = args.firmware.as_str())
+        );
+        fetch_releases()
+            .await?
+            .into_iter()
+            .find(|release| release.tag_name == args.firmware)
+            .map(|release| StationFirmware::Release(Box::new(release)))
+            .ok_or_else(|| {
+                CommandError::Retrieval(
+                    format!("no file or release named {}", args.firmware).into(),
+                )
+            })?
+    };
+
+    // ctrl-c stops the unit being flashed, then the station
+    let token = CancelToken::new();
+    if let Err(err) = ctrlc::set_handler({
+        let token = token.clone();
+        move || token.cancel()
+    }) {
+        error!("unable to register ctrl-c handler: {}", err);
+    }
+
+    let observer =
+        Observer::new().context(CommandError::Device, "unable to watch for usb devices")?;
+    let subscription = observer.subscribe();
+    let log = StationLog::new(args.log);
+    let mut downloads = StationDownloads {
+        verifier,
+        files: HashMap::new(),
+    };
+    let mut flashed = HashSet::new();
+
+    let waiting = t!("cli-station-waiting", log = log.path.display().to_string());
+    println!("{}", waiting);
+    while !token.is_cancelled() {
+        let devices = match subscription.rx_event.recv_timeout(USB_TIMEOUT) {
+            Ok(UsbEvent::Initial(devices)) => devices,
+            Ok(UsbEvent::Connected(device)) => vec![device],
+            Ok(UsbEvent::Disconnected(_)) | Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        // devices in bootloader mode are either ours mid-install, or need `ahoy recover`
+        for device in devices.iter().filter(|device| device.is_stm_device()) {
+            let started = Instant::now();
+            let entry = match flash_unit(
+                device,
+                &firmware,
+                policy,
+                &mut downloads,
+                &mut flashed,
+                &token,
+                timeouts,
+            )
+            .await
+            {
+                Some(entry) => entry,
+                None => continue,
+            };
+            try_record(&entry);
+
+            let record = StationRecord::new(&entry, started.elapsed());
+            if let Err(err) = log.append(&record) {
+                error!("{}", err.report());
+            }
+
+            let uid = record.device_uid.as_deref().unwrap_or("-");
+            match (record.result, &record.error) {
+                (Verdict::Pass, _) => println!(
+                    "{}",
+                    t!("cli-station-pass", uid = uid, seconds = record.seconds)
+                ),
+                (Verdict::Fail, error) => println!(
+                    "{}",
+                    t!(
+                        "cli-station-fail",
+                        uid = uid,
+                        reason = error.as_deref().unwrap_or_default()
+                    )
+                ),
+            }
+            if !args.quiet {
+                print!("\x07");
+            }
+            println!("{}", waiting);
+        }
+    }
+    Ok(())
+}
+
+/// check a unit and flash it - `None` when this station has already flashed it
+async fn flash_unit(
+    device: &UsbDevice,
+    firmware: &StationFirmware,
+    policy: &Policy,
+    downloads: &mut StationDownloads,
+    flashed: &mut HashSet<String>,
+    token: &CancelToken,
+    timeouts: Timeouts,
+) -> Option<JournalEntry> {
+    task::sleep(STATION_SETTLE_DELAY).await;
+    let port = device.serial_port();
+
+    let source = match firmware {
+        StationFirmware::File(_, source) => source.clone(),
+        StationFirmware::Release(release) => Source::Release {
+            tag: release.tag_name.clone(),
+        },
+    };
+    let version = source.version();
+    let entry = JournalEntry::new("station", source);
+    // without its own port, any other unit on the bench could answer in its place
+    let port = match port {
+        Some(port) => port,
+        None => {
+            let err = CommandError::Device(
+                format!(
+                    "cannot identify the port of unit {}",
+                    device.serial_number.as_deref().unwrap_or("-")
+                )
+                .into(),
+            );
+            return Some(entry.finish(&Err(err)));
+        }
+    };
+    let details = match check_device(Some(&port)) {
+        Ok(details) => details,
+        Err(err) => return Some(entry.finish(&Err(err))),
+    };
+
+    // units restart once they're flashed, and show up again
+    if flashed.contains(&details.uid) {
+        println!("{}", t!("cli-station-skipped", uid = details.uid.as_str()));
+        return None;
+    }
+    let entry = entry.with_device(&details);
+
+    // units are checked one by one, since what's allowed depends on the model and what it runs
+    let target = Target::device(&details);
+    let permitted = match firmware {
+        StationFirmware::File(path, _) => policy.check_file(path, &target),
+        StationFirmware::Release(release) => policy.check_release(release, &target),
+    };
+    if let Err(err) = permitted {
+        return Some(entry.finish(&Err(err)));
+    }
+    println!(
+        "{}",
+        t!(
+            "cli-station-flashing",
+            device = details.device_name.as_str(),
+            uid = details.uid.as_str()
+        )
+    );
+
+    let file = match firmware {
+        StationFirmware::File(path, _) => path.clone(),
+        StationFirmware::Release(release) => {
+            match station_download(release, &details, downloads).await {
+                Ok(path) => path,
+                Err(err) => return Some(entry.finish(&Err(err))),
+            }
+        }
+    };
+
+    // a unit that times out cancels its own token - only ctrl-c stops the station
+    let device = PirateDevice {
+        port: Some(port),
+        raw_device: None,
+        token: token.child(),
+        timeouts,
+    };
+    // the unit passes once it restarts reporting the firmware it was flashed with
+    let result = session::run(
+        &mut InstallSession::new(),
+        Firmware::File(file),
+        false,
+        version,
+        &Github,
+        &device,
+        |_, _| (),
+    )
+    .await;
+    // one that restarted with the wrong firmware would only fail the same way again
+    if matches!(result, Ok(()) | Err(CommandError::Verification(_))) {
+        flashed.insert(details.uid.clone());
+    }
+    Some(entry.finish(&result))
+}
+
+/// the release asset for a unit, downloaded the first time a model and revision is seen
+async fn station_download(
+    release: &Release,
+    details: &CheckResponse,
+    downloads: &mut StationDownloads,
+) -> Result<PathBuf, CommandError> {
+    let revision =
+        details.hardware_version.chars().last().ok_or_else(|| {
+            CommandError::Device("device did not report a hardware version".into())
+        })?;
+    let key = (details.device_model.trim().to_lowercase(), revision);
+    if let Some(path) = downloads.files.get(&key) {
+        return Ok(path.clone());
+    }
+
+    let asset = release
+        .find_asset(&key.0, revision)
+        .cloned()
+        .ok_or_else(|| {
+            CommandError::Retrieval(
+                format!(
+                    "release {} has no firmware for {} revision {}",
+                    release.tag_name, key.0, revision
+                )
+                .into(),
+            )
+        })?;
+    println!("{}", t!("cli-downloading", name = asset.name.as_str()));
+    let path = fetch_asset(asset, |_| ()).await?;
+    println!("{}", downloads.verifier.verify(&path)?);
+    downloads.files.insert(key, path.clone());
+    Ok(path)
+}
+
+/// the firmware policy every install is held to - exiting if it's configured but unreadable
+fn enforced_policy(file: Option<&Path>) -> Policy {
+    match policy::load(file) {
+        Ok(policy) => policy,
+        Err(err) => fail(err),
+    }
+}
+
+/// report a failed install and exit
+fn exit_on_failure(result: Result<(), CommandError>) {
+    if let Err(err) = result {
+        if matches!(err, CommandError::Cancelled | CommandError::Timeout(_)) {
+            println!("{}", t!("cli-left-in-bootloader"));
+        }
+        fail(err);
+    }
+}
+
+/// report an error with a hint on what to do about it, and exit with its code
+fn fail(err: CommandError) -> ! {
+    error!("{}", err.report());
+    println!("{}", err.hint());
+    std::process::exit(err.exit_code());
+}
+
+/// find the asset to recover a device with - a specific tag, or the latest release the policy allows
+async fn fetch_recovery_asset(
+    args: &RecoverArgs,
+    policy: &Policy,
+    target: &Target,
+    simulation: Option<Simulation>,
+) -> Result<(String, Asset), CommandError> {
+    let mut releases = match simulation {
+        Some(_) => simulate::releases(),
+        None => fetch_releases().await?,
+    };
+    let release = match &args.tag {
+        Some(tag) => releases.iter().find(|release| &release.tag_name == tag),
+        None => {
+            releases.retain(|release| policy.check_release(release, target).is_ok());
+            latest_in(&releases, &Channels::new(args.channels.clone()))
+        }
+    }
+    .ok_or_else(|| CommandError::Retrieval("no matching release found".into()))?;
+    policy.check_release(release, target)?;
+
+    release
+        .find_asset(args.model.id(), args.revision)
+        .map(|asset| (release.tag_name.clone(), asset.clone()))
+        .ok_or_else(|| {
+            CommandError::Retrieval(
+                format!(
+                    "release {} has no firmware for {} revision {}",
+                    release.tag_name, args.model, args.revision
+                )
+                .into(),
+            )
+        })
+}
@@ -0,0 +1,614 @@
+use std::{
+    net::IpAddr,
+    path::{Component, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_std::{
+    channel::{self, Sender},
+    task,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tide::{sse, utils::async_trait, Body, Middleware, Next, Request, Response, StatusCode};
+
+use ahoy_core::{
+    command::{
+        channel::{Channel, Channels},
+        device::{check_device, only_port},
+        doctor::DeviceDetails,
+        github::{fetch_releases, latest_in, DownloadProgress},
+        history::{read as read_history, try_record, JournalEntry, Source},
+        policy::{Policy, Target},
+        session::{self, Event, Firmware, Github, InstallSession, PirateDevice, Stage},
+        signature::Verifier,
+        transfer::{CancelToken, ProgressEvent, Timeouts},
+        CommandError, Context, Detail,
+    },
+    usb::observer::Observer,
+};
+
+/// a failure, with the same hint and exit code the command line would give
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub error: String,
+    pub hint: Option<String>,
+    pub exit_code: Option<i32>,
+}
+
+impl ApiError {
+    fn new(error: impl Into<String>) -> ApiError {
+        ApiError {
+            error: error.into(),
+            hint: None,
+            exit_code: None,
+        }
+    }
+}
+
+impl From<&CommandError> for ApiError {
+    fn from(err: &CommandError) -> ApiError {
+        ApiError {
+            error: err.report().to_string(),
+            hint: Some(err.hint()),
+            exit_code: Some(err.exit_code()),
+        }
+    }
+}
+
+/// where the current (or last) install is up to
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct InstallStatus {
+    pub running: bool,
+    pub stage: &'static str,
+    pub bytes: u64,
+    pub total: u64,
+    pub error: Option<ApiError>,
+}
+
+impl Default for InstallStatus {
+    fn default() -> Self {
+        InstallStatus {
+            running: false,
+            stage: stage_name(&Stage::Idle),
+            bytes: 0,
+            total: 0,
+            error: None,
+        }
+    }
+}
+
+impl InstallStatus {
+    fn update(&mut self, event: &Event, session: &InstallSession) {
+        self.stage = stage_name(session.stage());
+        match event {
+            Event::DownloadProgress(DownloadProgress { bytes, total })
+            | Event::Progress(ProgressEvent::Writing { bytes, total }) => {
+                self.bytes = *bytes;
+                self.total = *total;
+            }
+            _ => (),
+        }
+        if let Stage::Failed(err) = session.stage() {
+            self.error = Some(err.into());
+        }
+    }
+}
+
+fn stage_name(stage: &Stage) -> &'static str {
+    match stage {
+        Stage::Idle => "idle",
+        Stage::Downloading => "downloading",
+        Stage::AwaitingBootloader { .. } => "awaiting_bootloader",
+        Stage::Flashing => "flashing",
+        Stage::Rebooting => "rebooting",
+        Stage::Verifying => "verifying",
+        Stage::Done => "done",
+        Stage::Failed(_) => "failed",
+    }
+}
+
+/// the install the server is running - there's only ever one, since there's only one usb bus
+#[derive(Default)]
+struct Job {
+    status: InstallStatus,
+    token: Option<CancelToken>,
+    listeners: Vec<Sender<InstallStatus>>,
+}
+
+impl Job {
+    /// send the status to every event stream, forgetting the ones that have gone away
+    fn publish(&mut self) {
+        let status = self.status.clone();
+        self.listeners
+            .retain(|listener| listener.try_send(status.clone()).is_ok());
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    job: Arc<Mutex<Job>>,
+    policy: Arc<Policy>,
+    verifier: Arc<Verifier>,
+    timeouts: Timeouts,
+    /// the only place local files can be installed from
+    firmware_dir: Option<PathBuf>,
+}
+
+/// what to install - a file from the firmware directory, a release tag,
+/// or the latest stable release by default
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct InstallRequest {
+    /// a file name in the firmware directory - not a path
+    file: Option<PathBuf>,
+    tag: Option<String>,
+    port: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct DeviceQuery {
+    port: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ReleaseQuery {
+    /// comma separated, like `--channel`
+    channel: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct HistoryQuery {
+    device: Option<String>,
+}
+
+/// a pirate midi device on the usb bus
+#[derive(Serialize, Debug)]
+struct DeviceSummary {
+    bootloader: bool,
+    serial_number: Option<String>,
+    port: Option<String>,
+}
+
+/// a fresh token for clients to send as `Authorization: Bearer <token>`
+pub fn new_token() -> Result<String, CommandError> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|err| {
+        CommandError::IO(Detail::new("unable to generate an api token").because(err))
+    })?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// keeps out requests a web page could have made on the operator's behalf - from another
+/// origin, or through a host name that isn't an address - and anything without the token
+struct Guard {
+    token: String,
+}
+
+#[async_trait]
+impl Middleware<State> for Guard {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let host = req.header("Host").map(|host| host.as_str());
+        let origin = req.header("Origin").map(|origin| origin.as_str());
+        if !matches!(host, Some(host) if is_local_host(host)) {
+            warn!("refusing a request for host {:?}", host);
+            return json(
+                StatusCode::Forbidden,
+                &ApiError::new("requests must be addressed to an ip address or localhost"),
+            );
+        }
+        if let (Some(origin), Some(host)) = (origin, host) {
+            if origin != format!("http://{}", host) {
+                warn!("refusing a request from {}", origin);
+                return json(
+                    StatusCode::Forbidden,
+                    &ApiError::new("cross-origin requests are not allowed"),
+                );
+            }
+        }
+
+        let authorization = req.header("Authorization").map(|value| value.as_str());
+        if authorization != Some(format!("Bearer {}", self.token).as_str()) {
+            return json(
+                StatusCode::Unauthorized,
+                &ApiError::new("missing or wrong api token"),
+            );
+        }
+        Ok(next.run(req).await)
+    }
+}
+
+/// whether a `Host` header names this machine by address or as localhost.
+/// any other name could have been pointed here by someone else's dns
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+}
+
+/// serve the api until the process is stopped
+pub async fn serve(
+    bind: &str,
+    token: String,
+    firmware_dir: Option<PathBuf>,
+    policy: Policy,
+    verifier: Verifier,
+    timeouts: Timeouts,
+) -> Result<(), CommandError> {
+    let mut app = tide::with_state(State {
+        job: Arc::new(Mutex::new(Job::default())),
+        policy: Arc::new(policy),
+        verifier: Arc::new(verifier),
+        timeouts,
+        firmware_dir,
+    });
+    app.with(Guard { token });
+    app.at("/devices").get(list_devices);
+    app.at("/device").get(device_info);
+    app.at("/releases").get(list_releases);
+    app.at("/history").get(history);
+    app.at("/install")
+        .get(install_status)
+        .post(start_install)
+        .delete(cancel_install);
+    app.at("/install/events").get(sse::endpoint(install_events));
+
+    info!("serving api on: {}", bind);
+    app.listen(bind.to_string()).await.map_err(|err| {
+        CommandError::IO(Detail::new(format!("unable to serve on {}", bind)).because(err))
+    })
+}
+
+fn json(status: StatusCode, value: &impl Serialize) -> tide::Result {
+    Ok(Response::builder(status)
+        .body(Body::from_json(value)?)
+        .build())
+}
+
+/// device and network failures become json, with a status that says whose fault it was
+fn respond(result: Result<impl Serialize, CommandError>) -> tide::Result {
+    match result {
+        Ok(value) => json(StatusCode::Ok, &value),
+        Err(err) => {
+            let status = match err {
+                CommandError::NoDevice(_) => StatusCode::NotFound,
+                CommandError::Permission(_)
+                | CommandError::Policy(_)
+                | CommandError::Signature(_) => StatusCode::Forbidden,
+                CommandError::Retrieval(_)
+                | CommandError::Download(_)
+                | CommandError::Checksum(_) => StatusCode::BadGateway,
+                _ => StatusCode::InternalServerError,
+            };
+            json(status, &ApiError::from(&err))
+        }
+    }
+}
+
+/// devices are left alone while an install has them - they may be restarting, or mid-flash
+fn install_running(state: &State) -> Option<tide::Result> {
+    if state.job.lock().unwrap().status.running {
+        Some(json(
+            StatusCode::Conflict,
+            &ApiError::new("an install is running"),
+        ))
+    } else {
+        None
+    }
+}
+
+async fn list_devices(req: Request<State>) -> tide::Result {
+    if let Some(conflict) = install_running(req.state()) {
+        return conflict;
+    }
+    respond(
+        task::spawn_blocking(|| {
+            let mut observer =
+                Observer::new().context(CommandError::Device, "unable to list usb devices")?;
+            Ok(observer
+                .fetch()
+                .into_iter()
+                .filter(|device| device.is_stm_device() || device.is_dfu_device())
+                .map(|device| DeviceSummary {
+                    bootloader: device.is_dfu_device(),
+                    port: device.serial_port(),
+                    serial_number: device.serial_number,
+                })
+                .collect::<Vec<DeviceSummary>>())
+        })
+        .await,
+    )
+}
+
+async fn device_info(req: Request<State>) -> tide::Result {
+    if let Some(conflict) = install_running(req.state()) {
+        return conflict;
+    }
+    let query: DeviceQuery = req.query()?;
+    respond(
+        task::spawn_blocking(move || {
+            let port = match query.port {
+                Some(port) => port,
+                None => only_port()?,
+            };
+            check_device(Some(&port))
+        })
+        .await
+        .map(DeviceDetails::from),
+    )
+}
+
+async fn list_releases(req: Request<State>) -> tide::Result {
+    let query: ReleaseQuery = req.query()?;
+    let channels = match query
+        .channel
+        .as_deref()
+        .unwrap_or("stable")
+        .split(',')
+        .map(Channel::from_str)
+        .collect::<Result<Vec<Channel>, String>>()
+    {
+        Ok(channels) => Channels::new(channels),
+        Err(err) => return json(StatusCode::BadRequest, &ApiError::new(err)),
+    };
+
+    respond(fetch_releases().await.map(|releases| {
+        releases
+            .into_iter()
+            .filter(|release| channels.matches(release))
+            .collect::<Vec<_>>()
+    }))
+}
+
+async fn history(req: Request<State>) -> tide::Result {
+    let query: HistoryQuery = req.query()?;
+    respond(read_history(query.device.as_deref()))
+}
+
+async fn install_status(req: Request<State>) -> tide::Result {
+    let status = req.state().job.lock().unwrap().status.clone();
+    json(StatusCode::Ok, &status)
+}
+
+/// start an install in the background - follow it with `GET /install/events`
+async fn start_install(mut req: Request<State>) -> tide::Result {
+    // a form post doesn't need a preflight, so only json is taken
+    if !matches!(req.content_type(), Some(mime) if mime.essence() == "application/json") {
+        return json(
+            StatusCode::UnsupportedMediaType,
+            &ApiError::new("the request body must be application/json"),
+        );
+    }
+    let mut request: InstallRequest = req.body_json().await?;
+    let state = req.state().clone();
+    if let Some(file) = request.file.take() {
+        let name = match file.components().collect::<Vec<_>>().as_slice() {
+            [Component::Normal(name)] => name.to_os_string(),
+            _ => {
+                return json(
+                    StatusCode::BadRequest,
+                    &ApiError::new("file must be a file name in the firmware directory"),
+                )
+            }
+        };
+        match &state.firmware_dir {
+            Some(dir) => request.file = Some(dir.join(name)),
+            None => {
+                return json(
+                    StatusCode::Forbidden,
+                    &ApiError::new("local files can't be installed without --firmware-dir"),
+                )
+            }
+        }
+    }
+    let token = CancelToken::new();
+
+    let status = {
+        let mut job = state.job.lock().unwrap();
+        if job.status.running {
+            return json(
+                StatusCode::Conflict,
+                &ApiError::new("an install is already running"),
+            );
+        }
+        job.status = InstallStatus {
+            running: true,
+            ..InstallStatus::default()
+        };
+        job.token = Some(token.clone());
+        job.publish();
+        job.status.clone()
+    };
+
+    task::spawn(async move {
+        let result = install(&state, request, token).await;
+
+        let mut job = state.job.lock().unwrap();
+        job.status.running = false;
+        job.token = None;
+        if let Err(err) = &result {
+            warn!("api install failed: {}", err.report());
+            job.status.stage = stage_name(&Stage::Failed(err.clone()));
+            job.status.error = Some(err.into());
+        }
+        job.publish();
+    });
+
+    json(StatusCode::Accepted, &status)
+}
+
+/// stop the running install between firmware blocks
+async fn cancel_install(req: Request<State>) -> tide::Result {
+    match &req.state().job.lock().unwrap().token {
+        Some(token) => {
+            token.cancel();
+            Ok(Response::new(StatusCode::Accepted))
+        }
+        None => json(
+            StatusCode::Conflict,
+            &ApiError::new("no install is running"),
+        ),
+    }
+}
+
+/// every status change, as server-sent `status` events - starting with the current one
+async fn install_events(req: Request<State>, sender: sse::Sender) -> tide::Result<()> {
+    let (listener, updates) = channel::unbounded();
+    let status = {
+        let mut job = req.state().job.lock().unwrap();
+        job.listeners.push(listener);
+        job.status.clone()
+    };
+
+    sender
+        .send("status", serde_json::to_string(&status)?, None)
+        .await?;
+    while let Ok(status) = updates.recv().await {
+        sender
+            .send("status", serde_json::to_string(&status)?, None)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn install(
+    state: &State,
+    request: InstallRequest,
+    token: CancelToken,
+) -> Result<(), CommandError> {
+    // without a port there's no telling which device would answer
+    let port = match request.port {
+        Some(port) => port,
+        None => task::spawn_blocking(only_port).await?,
+    };
+    let details = task::spawn_blocking({
+        let port = port.clone();
+        move || check_device(Some(&port))
+    })
+    .await?;
+
+    let target = Target::device(&details);
+    let (firmware, source) = match (request.file, request.tag) {
+        (Some(file), _) => {
+            state.policy.check_file(&file, &target)?;
+            let source = Source::from_file(&file)?;
+            (Firmware::File(file), source)
+        }
+        (None, tag) => {
+            let revision = details.hardware_version.chars().last().ok_or_else(|| {
+                CommandError::Device("device did not report a hardware version".into())
+            })?;
+            let mut releases = fetch_releases().await?;
+            let release = match &tag {
+                Some(tag) => releases.iter().find(|release| &release.tag_name == tag),
+                None => {
+                    releases.retain(|release| state.policy.check_release(release, &target).is_ok());
+                    latest_in(&releases, &Channels::default())
+                }
+            }
+            .ok_or_else(|| CommandError::Retrieval("no matching release found".into()))?;
+            state.policy.check_release(release, &target)?;
+            let asset = release
+                .find_asset(&details.device_model, revision)
+                .ok_or_else(|| {
+                    CommandError::Retrieval(
+                        format!(
+                            "release {} has no firmware for {} revision {}",
+                            release.tag_name, details.device_model, revision
+                        )
+                        .into(),
+                    )
+                })?;
+            (
+                Firmware::Asset(Box::new(asset.clone())),
+                Source::Release {
+                    tag: release.tag_name.clone(),
+                },
+            )
+        }
+    };
+
+    let version = source.version();
+    let entry = JournalEntry::new("api", source).with_device(&details);
+    let device = PirateDevice {
+        port: Some(port),
+        raw_device: None,
+        token,
+        timeouts: state.timeouts,
+    };
+    let result = session::run(
+        &mut InstallSession::new().verifying(state.verifier.as_ref().clone()),
+        firmware,
+        false,
+        version,
+        &Github,
+        &device,
+        |event, session| {
+            let mut job = state.job.lock().unwrap();
+            job.status.update(event, session);
+            job.publish();
+        },
+    )
+    .await;
+    try_record(&entry.finish(&result));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ahoy_core::command::{
+        session::{Event, Firmware, InstallSession},
+        transfer::ProgressEvent,
+        CommandError,
+    };
+
+    use super::{is_local_host, ApiError, InstallStatus};
+
+    #[test]
+    fn status_follows_the_session() {
+        let mut session = InstallSession::new();
+        let mut status = InstallStatus::default();
+        for event in [
+            Event::Start {
+                firmware: Firmware::File(PathBuf::from("firmware.bin")),
+                in_bootloader: true,
+                version: None,
+            },
+            Event::Progress(ProgressEvent::Writing {
+                bytes: 1024,
+                total: 4096,
+            }),
+        ] {
+            session.handle(event.clone());
+            status.update(&event, &session);
+        }
+        assert_eq!(status.stage, "flashing");
+        assert_eq!((status.bytes, status.total), (1024, 4096));
+        assert_eq!(status.error, None);
+
+        let event = Event::Flashed(Err(CommandError::Cancelled));
+        session.handle(event.clone());
+        status.update(&event, &session);
+        assert_eq!(status.stage, "failed");
+        assert_eq!(status.error, Some(ApiError::from(&CommandError::Cancelled)));
+    }
+
+    #[test]
+    fn only_local_hosts_are_served() {
+        for host in ["127.0.0.1:7878", "localhost:7878", "[::1]:7878", "10.0.0.2"] {
+            assert!(is_local_host(host), "{}", host);
+        }
+        for host in ["example.com:7878", "localhost.example.com", ""] {
+            assert!(!is_local_host(host), "{}", host);
+        }
+    }
+}
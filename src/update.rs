@@ -1,7 +1,18 @@
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use self_update::{backends::github::Update, cargo_crate_version};
 
-use super::CommandError;
+use ahoy_core::command::{CommandError, Context};
+
+/// the newest version of ahoy, if it's newer than this one - for reports, where failing to check isn't fatal
+pub async fn latest_version() -> Option<String> {
+    match update_available().await {
+        Ok(latest) => latest,
+        Err(err) => {
+            warn!("unable to check for application updates: {}", err.report());
+            None
+        }
+    }
+}
 
 pub async fn update_available() -> Result<Option<String>, CommandError> {
     let updater = Update::configure()
@@ -10,17 +21,17 @@ pub async fn update_available() -> Result<Option<String>, CommandError> {
         .bin_name("ahoy")
         .current_version(cargo_crate_version!())
         .build()
-        .map_err(|e| CommandError::Update(format!("error creating for update builder: {}", e)))?;
+        .context(CommandError::Update, "error creating for update builder")?;
 
     let latest = updater
         .get_latest_release()
-        .map_err(|e| CommandError::Update(format!("could not check for updates: {}", e)))?;
+        .context(CommandError::Update, "could not check for updates")?;
 
     trace!("release response: {:?}", latest);
     info!("current version: {}", cargo_crate_version!());
     info!("latest release available: {}", latest.version);
     let is_greater = self_update::version::bump_is_greater(cargo_crate_version!(), &latest.version)
-        .map_err(|e| CommandError::Update(format!("issue compairing versions: {}", e)))?;
+        .context(CommandError::Update, "issue compairing versions")?;
 
     debug!("is new release greater? - {}", is_greater);
 
@@ -40,16 +51,16 @@ pub async fn update_self(interactive: bool) -> Result<(), CommandError> {
         .show_download_progress(interactive)
         .current_version(cargo_crate_version!())
         .build()
-        .map_err(|e| CommandError::Update(format!("unable to build updater: {}", e)))?
+        .context(CommandError::Update, "unable to build updater")?
         .update()
-        .map_err(|e| CommandError::Update(format!("unable to update: {}", e)))?;
+        .context(CommandError::Update, "unable to update")?;
     info!("update status: `{}`!", status.version());
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::command::update::update_available;
+    use super::update_available;
 
     #[async_std::test]
     async fn test_update_status() -> std::io::Result<()> {